@@ -1,3 +1,5 @@
+use giga_chess::lichess::archive::LichessPuzzleArchive;
+use giga_chess::lichess::archive::query::PuzzleQuery;
 use giga_chess::lichess::parser::LichessPuzzleParser;
 use giga_chess::prelude::{Color, Piece};
 use indicatif::{ProgressBar, ProgressStyle};
@@ -7,10 +9,11 @@ use std::io::BufReader;
 use std::path::Path;
 
 const PUZZLE_PATH: &str = "lichess_db_puzzle.csv.zst";
+const ARCHIVE_PATH: &str = "lichess_puzzle_archive.bin";
 const PUZZLE_URL: &str = "https://database.lichess.org/lichess_db_puzzle.csv.zst";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    if !Path::new(PUZZLE_PATH).exists() {
+    if !Path::new(ARCHIVE_PATH).exists() && !Path::new(PUZZLE_PATH).exists() {
         println!("Downloading puzzle database...");
         let response = ureq::get(PUZZLE_URL).call()?;
 
@@ -36,58 +39,56 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         dl_bar.finish_and_clear();
     }
 
-    let file = BufReader::new(File::open(PUZZLE_PATH)?);
-    let parser = LichessPuzzleParser::new(file)?;
-
-    let parse_bar = ProgressBar::new_spinner();
-    parse_bar.set_style(
-        ProgressStyle::default_spinner().template("{spinner:.green} {msg} [{elapsed_precise}]")?,
-    );
+    let archive = if Path::new(ARCHIVE_PATH).exists() {
+        LichessPuzzleArchive::from_bytes(std::fs::read(ARCHIVE_PATH)?)?
+    } else {
+        println!("Building puzzle archive...");
+        let file = BufReader::new(File::open(PUZZLE_PATH)?);
+        let archive = LichessPuzzleArchive::try_from_iter(LichessPuzzleParser::new(file)?)?;
+        std::fs::write(ARCHIVE_PATH, archive.as_bytes())?;
+        archive
+    };
+
+    // e.g. `cargo run --example lichess_puzzle_stats -- 1500 1800 fork`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut query = PuzzleQuery::default();
+    if let [min, max, ..] = args.as_slice() {
+        query = query.with_rating(min.parse()?..=max.parse()?);
+    }
+    for theme in args.iter().skip(2) {
+        query = query.with_theme(theme.parse()?);
+    }
 
     let mut stats = Stats::default();
-    let mut errors = 0usize;
-    for (i, result) in parser.enumerate() {
-        if i % 1000 == 0 {
-            parse_bar.set_message(format!("{i} puzzles processed ({errors} errors)"));
-            parse_bar.tick();
+    for puzzle in archive.query(&query)? {
+        stats.count += 1;
+        stats.ratings.push(puzzle.rating as usize);
+        stats
+            .rating_deviation
+            .push(puzzle.rating_deviation as usize);
+        stats.times_played.push(puzzle.times_played as usize);
+        stats.move_counts.push(puzzle.puzzle.solution().len());
+
+        for theme in puzzle.themes {
+            *stats.themes.entry(theme.to_string()).or_default() += 1;
         }
-
-        match result {
-            Ok(entry) => {
-                stats.count += 1;
-                stats.ratings.push(entry.rating);
-                stats.rating_deviation.push(entry.rating_deviation);
-                stats.times_played.push(entry.times_played);
-                stats.move_counts.push(entry.moves.len());
-
-                for theme in entry.themes {
-                    *stats.themes.entry(theme).or_default() += 1;
-                }
-                for tag in entry.opening_tags {
-                    *stats.opening_tags.entry(tag).or_default() += 1;
-                }
-                for piece in Piece::ALL {
-                    for color in Color::ALL {
-                        stats
-                            .piece_counts
-                            .entry((piece, color))
-                            .or_default()
-                            .push(entry.pos.board.specific_piece_count(piece, color));
-                    }
-                }
-            }
-            Err(e) => {
-                errors += 1;
-                if errors <= 20 {
-                    eprintln!("Error at row {i}: {e}");
-                } else if errors == 21 {
-                    eprintln!("...more errors were omitted")
-                }
+        for piece in Piece::ALL {
+            for color in Color::ALL {
+                stats.piece_counts.entry((piece, color)).or_default().push(
+                    puzzle
+                        .puzzle
+                        .position()
+                        .board
+                        .specific_piece_count(piece, color),
+                );
             }
         }
     }
 
-    parse_bar.finish_with_message(format!("{} puzzles done ({errors} errors)", stats.count));
+    println!("{} of {} puzzles matched", stats.count, archive.len());
+    if stats.count == 0 {
+        return Ok(());
+    }
 
     print_num_stats("Rating", &mut stats.ratings);
     println!();
@@ -99,8 +100,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!();
     print_freq("Themes", &stats.themes, 100);
     println!();
-    print_freq("Opening Tags", &stats.opening_tags, 30);
-    println!();
 
     println!("Piece Counts:");
     for color in Color::ALL {
@@ -123,7 +122,6 @@ struct Stats {
     times_played: Vec<usize>,
     move_counts: Vec<usize>,
     themes: HashMap<String, usize>,
-    opening_tags: HashMap<String, usize>,
     piece_counts: HashMap<(Piece, Color), Vec<u8>>,
 }
 
@@ -0,0 +1,4 @@
+pub mod annotation;
+#[cfg(feature = "bit-codec")]
+pub mod explorer;
+pub mod mining;
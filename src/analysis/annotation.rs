@@ -0,0 +1,301 @@
+use crate::core::position::Position;
+use crate::engine::score::Score;
+use crate::engine::{Engine, SearchLimit};
+use crate::error::EngineResult;
+use crate::game::Game;
+use crate::game::outcome::GameOutcome;
+use crate::moves::generator::MoveGenerator;
+use crate::notation::san::move_to_san;
+use crate::prelude::{ChessMove, Color, Piece};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationConfig {
+    pub limit: SearchLimit,
+    /// Minimum loss of win percentage (0-100) for a move to count as an inaccuracy
+    pub inaccuracy: f64,
+    /// Minimum loss of win percentage (0-100) for a move to count as a mistake
+    pub mistake: f64,
+    /// Minimum loss of win percentage (0-100) for a move to count as a blunder
+    pub blunder: f64,
+}
+
+impl Default for AnnotationConfig {
+    /// Thresholds as used by Lichess
+    fn default() -> Self {
+        Self {
+            limit: SearchLimit::depth(12),
+            inaccuracy: 5.0,
+            mistake: 10.0,
+            blunder: 15.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "strum",
+    derive(strum::EnumIter, strum::EnumIs, strum::EnumCount)
+)]
+pub enum MoveClassification {
+    /// The best move which also sacrificed material
+    Brilliant,
+    /// The move the engine suggested
+    Best,
+    /// Not the engine's choice but below the inaccuracy threshold
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClassification {
+    /// The numeric annotation glyph as used in PGN
+    pub fn nag(&self) -> Option<u8> {
+        match self {
+            Self::Brilliant => Some(3),
+            Self::Best | Self::Good => None,
+            Self::Inaccuracy => Some(6),
+            Self::Mistake => Some(2),
+            Self::Blunder => Some(4),
+        }
+    }
+
+    pub fn symbol(&self) -> Option<&'static str> {
+        match self {
+            Self::Brilliant => Some("!!"),
+            Self::Best | Self::Good => None,
+            Self::Inaccuracy => Some("?!"),
+            Self::Mistake => Some("?"),
+            Self::Blunder => Some("??"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveAnnotation {
+    pub color: Color,
+    pub mv: ChessMove,
+    pub san: String,
+    /// Evaluation of the position before the move, from white's perspective
+    pub eval_before: Score,
+    /// Evaluation of the position after the move, from white's perspective
+    pub eval_after: Score,
+    /// The engine's suggestion for the position before the move
+    pub best_move: Option<ChessMove>,
+    pub best_san: Option<String>,
+    /// How much win percentage (0-100) the mover lost with this move
+    pub win_percent_loss: f64,
+    /// How many centipawns the mover lost with this move, evaluations are capped at ±1000
+    pub centipawn_loss: u32,
+    /// Move accuracy (0-100) derived from the win percentage loss
+    pub accuracy: f64,
+    pub classification: MoveClassification,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SideSummary {
+    pub moves: usize,
+    pub average_centipawn_loss: f64,
+    /// Mean accuracy of all moves (0-100)
+    pub accuracy: f64,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+    pub brilliant: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameAnnotation {
+    /// Annotations for every ply of the game
+    pub moves: Vec<MoveAnnotation>,
+    pub white: SideSummary,
+    pub black: SideSummary,
+}
+
+impl GameAnnotation {
+    pub fn summary(&self, color: Color) -> &SideSummary {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+}
+
+/// Evaluates every position of the game with the engine and classifies each move.
+pub fn annotate_game(
+    game: &Game,
+    engine: &mut dyn Engine,
+    config: &AnnotationConfig,
+) -> EngineResult<GameAnnotation> {
+    engine.new_game()?;
+
+    let mut replay = Game::from_position(*game.start_position());
+    let mut before = evaluate(&replay, engine, &config.limit)?;
+    let mut moves = Vec::with_capacity(game.history().len());
+
+    for &mv in game.history() {
+        let pos = *replay.position();
+        let color = pos.side_to_move;
+        let san = move_to_san(&pos, mv, replay.legal_moves())?;
+        let best_san = before
+            .best_move
+            .and_then(|best| move_to_san(&pos, best, replay.legal_moves()).ok());
+
+        replay.play_move(mv)?;
+        let after = evaluate(&replay, engine, &config.limit)?;
+
+        let win_percent_loss =
+            (before.score.win_percent(color) - after.score.win_percent(color)).max(0.0);
+        let centipawn_loss = (before.score.cp_for(color) - after.score.cp_for(color)).max(0) as u32;
+        let accuracy = (103.1668 * (-0.04354 * win_percent_loss).exp() - 3.1669).clamp(0.0, 100.0);
+
+        let is_best = before.best_move == Some(mv);
+        let classification = if win_percent_loss >= config.blunder {
+            MoveClassification::Blunder
+        } else if win_percent_loss >= config.mistake {
+            MoveClassification::Mistake
+        } else if win_percent_loss >= config.inaccuracy {
+            MoveClassification::Inaccuracy
+        } else if is_best
+            && is_sacrifice(&pos, mv)
+            && (30.0..95.0).contains(&before.score.win_percent(color))
+        {
+            MoveClassification::Brilliant
+        } else if is_best {
+            MoveClassification::Best
+        } else {
+            MoveClassification::Good
+        };
+
+        moves.push(MoveAnnotation {
+            color,
+            mv,
+            san,
+            eval_before: before.score,
+            eval_after: after.score,
+            best_move: before.best_move,
+            best_san,
+            win_percent_loss,
+            centipawn_loss,
+            accuracy,
+            classification,
+        });
+        before = after;
+    }
+
+    let white = summarize(&moves, Color::White);
+    let black = summarize(&moves, Color::Black);
+    Ok(GameAnnotation {
+        moves,
+        white,
+        black,
+    })
+}
+
+struct Evaluation {
+    score: Score,
+    best_move: Option<ChessMove>,
+}
+
+/// Finished games are scored by their outcome instead of asking the engine
+fn evaluate(game: &Game, engine: &mut dyn Engine, limit: &SearchLimit) -> EngineResult<Evaluation> {
+    match game.outcome() {
+        Some(GameOutcome::Decisive { winner, .. }) => Ok(Evaluation {
+            score: Score::Mate { winner, moves: 0 },
+            best_move: None,
+        }),
+        Some(_) => Ok(Evaluation {
+            score: Score::DRAW,
+            best_move: None,
+        }),
+        None => {
+            let result = engine.search(game, limit)?;
+            Ok(Evaluation {
+                score: result.score().unwrap_or_default(),
+                best_move: result.best_move(),
+            })
+        }
+    }
+}
+
+/// A piece moves to an attacked square where it's worth more than what it captured
+fn is_sacrifice(pos: &Position, mv: ChessMove) -> bool {
+    let Some((piece, color)) = pos.board.piece_at(mv.from()) else {
+        return false;
+    };
+    if matches!(piece, Piece::Pawn | Piece::King) {
+        return false;
+    }
+    let piece = mv.flags().promotion_piece().unwrap_or(piece);
+    let captured = pos
+        .board
+        .piece_at_with_color(mv.to(), color.opposite())
+        .map_or(0, |captured| captured.value());
+
+    let next = pos.make_move(mv);
+    piece.value() > captured
+        && MoveGenerator::get().is_square_attacked(&next.board, mv.to(), color.opposite())
+}
+
+fn summarize(moves: &[MoveAnnotation], color: Color) -> SideSummary {
+    let own: Vec<&MoveAnnotation> = moves.iter().filter(|m| m.color == color).collect();
+    if own.is_empty() {
+        return SideSummary::default();
+    }
+
+    let count = |classification: MoveClassification| {
+        own.iter()
+            .filter(|m| m.classification == classification)
+            .count()
+    };
+    let len = own.len() as f64;
+    SideSummary {
+        moves: own.len(),
+        average_centipawn_loss: own.iter().map(|m| m.centipawn_loss as f64).sum::<f64>() / len,
+        accuracy: own.iter().map(|m| m.accuracy).sum::<f64>() / len,
+        inaccuracies: count(MoveClassification::Inaccuracy),
+        mistakes: count(MoveClassification::Mistake),
+        blunders: count(MoveClassification::Blunder),
+        brilliant: count(MoveClassification::Brilliant),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::annotation::{AnnotationConfig, MoveClassification, annotate_game};
+    use crate::engine::SearchLimit;
+    use crate::engine::search::MaterialSearch;
+    use crate::game::Game;
+    use crate::notation::pgn::{PgnHeaders, annotated_pgn};
+    use crate::prelude::*;
+
+    #[test]
+    fn test_detects_queen_blunder() {
+        let mut game = Game::new();
+        for (from, to) in [(E2, E4), (E7, E5), (D1, H5), (B8, C6), (H5, E5), (C6, E5)] {
+            let mv = ChessMove::from_position(game.position(), from, to, None).unwrap();
+            game.play_move(mv).unwrap();
+        }
+
+        let config = AnnotationConfig {
+            limit: SearchLimit::depth(2),
+            ..Default::default()
+        };
+        let annotation = annotate_game(&game, &mut MaterialSearch::default(), &config).unwrap();
+        assert_eq!(annotation.moves.len(), 6);
+
+        let blunder = &annotation.moves[4];
+        assert_eq!(blunder.san, "Qxe5+");
+        assert_eq!(blunder.classification, MoveClassification::Blunder);
+        assert!(blunder.centipawn_loss >= 500);
+        assert_eq!(annotation.white.blunders, 1);
+        assert_eq!(annotation.black.blunders, 0);
+        assert!(annotation.white.accuracy < annotation.black.accuracy);
+
+        let pgn = annotated_pgn(&game, &PgnHeaders::default(), &annotation);
+        assert!(pgn.contains("3. Qxe5+ $4 { [%eval"));
+        assert!(pgn.contains("Blunder."));
+    }
+}
@@ -0,0 +1,393 @@
+use crate::core::position::Position;
+use crate::game::Game;
+use crate::game::outcome::GameOutcome;
+use crate::prelude::{ChessMove, Color};
+use bit_codec::{BitDecode, BitReader, BitWriter};
+use std::collections::{HashMap, HashSet};
+use std::io::{Error, ErrorKind};
+
+const MAGIC: &[u8; 4] = b"GCOE";
+pub const FORMAT_VERSION: u16 = 1;
+/// Magic bytes, version and position count
+const HEADER_LEN: usize = 10;
+/// A position hash as u64 LE followed by the offset of its moves as u32 LE
+const TABLE_ENTRY_LEN: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplorerConfig {
+    /// Only the first plies of each game are recorded
+    pub max_plies: usize,
+    /// Amount of highest rated games kept per move
+    pub top_games: usize,
+    /// Positions reached in fewer games are left out
+    pub min_games: u32,
+}
+
+impl Default for ExplorerConfig {
+    fn default() -> Self {
+        Self {
+            max_plies: 40,
+            top_games: 4,
+            min_games: 1,
+        }
+    }
+}
+
+/// Statistics of a move played in a position of the explorer
+#[derive(Debug, Clone, PartialEq, Eq, bit_codec::BitEncode, bit_codec::BitDecode)]
+pub struct MoveStats {
+    pub mv: ChessMove,
+    pub white_wins: u32,
+    pub draws: u32,
+    pub black_wins: u32,
+    /// The highest rated games by the average rating of both players, best first
+    pub top_games: Vec<TopGame>,
+    rating_sum: u64,
+    rated_games: u32,
+}
+
+impl MoveStats {
+    fn new(mv: ChessMove) -> Self {
+        Self {
+            mv,
+            white_wins: 0,
+            draws: 0,
+            black_wins: 0,
+            top_games: vec![],
+            rating_sum: 0,
+            rated_games: 0,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.white_wins + self.draws + self.black_wins
+    }
+
+    /// Average rating of both players over the games where both were rated
+    pub fn average_rating(&self) -> Option<u16> {
+        (self.rated_games > 0).then(|| (self.rating_sum / self.rated_games as u64) as u16)
+    }
+
+    fn record(&mut self, winner: Option<Color>, top_game: Option<&TopGame>, top_games: usize) {
+        match winner {
+            Some(Color::White) => self.white_wins += 1,
+            Some(Color::Black) => self.black_wins += 1,
+            None => self.draws += 1,
+        }
+
+        let Some(top_game) = top_game else {
+            return;
+        };
+        self.rating_sum += top_game.average_rating() as u64;
+        self.rated_games += 1;
+        let index = self
+            .top_games
+            .partition_point(|other| other.average_rating() >= top_game.average_rating());
+        if index < top_games {
+            self.top_games.insert(index, top_game.clone());
+            self.top_games.truncate(top_games);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, bit_codec::BitEncode, bit_codec::BitDecode)]
+pub struct TopGame {
+    pub id: String,
+    pub white_elo: u16,
+    pub black_elo: u16,
+    /// None for draws
+    pub winner: Option<Color>,
+}
+
+impl TopGame {
+    pub fn average_rating(&self) -> u16 {
+        ((self.white_elo as u32 + self.black_elo as u32) / 2) as u16
+    }
+}
+
+/// Collects the move statistics of games, see [`OpeningExplorer`]
+#[derive(Debug, Clone, Default)]
+pub struct OpeningExplorerBuilder {
+    config: ExplorerConfig,
+    positions: HashMap<u64, Vec<MoveStats>>,
+}
+
+impl OpeningExplorerBuilder {
+    pub fn new(config: ExplorerConfig) -> Self {
+        Self {
+            config,
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Records the moves of a finished game, unfinished or aborted games are skipped.
+    /// A move played again in a position the game already passed counts only once.
+    pub fn add_game(
+        &mut self,
+        game: &Game,
+        id: &str,
+        white_elo: Option<u16>,
+        black_elo: Option<u16>,
+    ) {
+        let winner = match game.outcome() {
+            Some(GameOutcome::Decisive { winner, .. }) => Some(winner),
+            Some(GameOutcome::Draw(_)) => None,
+            Some(GameOutcome::Aborted(_)) | None => return,
+        };
+        let top_game = white_elo
+            .zip(black_elo)
+            .map(|(white_elo, black_elo)| TopGame {
+                id: id.to_string(),
+                white_elo,
+                black_elo,
+                winner,
+            });
+
+        let mut pos = *game.start_position();
+        let mut seen = HashSet::new();
+        for &mv in game.history().iter().take(self.config.max_plies) {
+            if seen.insert((pos.hash, mv)) {
+                self.record(pos.hash, mv, winner, top_game.as_ref());
+            }
+            pos = pos.make_move(mv);
+        }
+    }
+
+    /// Records a game of a Lichess database dump, its id is taken from the Site tag
+    #[cfg(feature = "lichess-game-parser")]
+    pub fn add_lichess_game(
+        &mut self,
+        game: &crate::lichess::games::LichessGame,
+    ) -> crate::error::PgnResult<()> {
+        let id = game
+            .pgn
+            .headers
+            .site
+            .as_deref()
+            .and_then(|site| site.rsplit('/').next())
+            .unwrap_or_default();
+        self.add_game(&game.to_game()?, id, game.white_elo, game.black_elo);
+        Ok(())
+    }
+
+    pub fn build(self) -> std::io::Result<OpeningExplorer> {
+        let mut positions: Vec<_> = self
+            .positions
+            .into_iter()
+            .filter(|(_, moves)| {
+                moves.iter().map(MoveStats::total).sum::<u32>() >= self.config.min_games
+            })
+            .collect();
+        positions.sort_unstable_by_key(|(hash, _)| *hash);
+
+        let mut table = Vec::with_capacity(positions.len() * TABLE_ENTRY_LEN);
+        let mut data = Vec::new();
+        for (hash, mut moves) in positions {
+            moves.sort_by_key(|stats| std::cmp::Reverse(stats.total()));
+            table.extend_from_slice(&hash.to_le_bytes());
+            table.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            let mut writer = BitWriter::new(&mut data);
+            writer.write(&moves)?;
+            writer.flush()?;
+        }
+
+        let len = table.len() / TABLE_ENTRY_LEN;
+        let mut bytes = Vec::with_capacity(HEADER_LEN + table.len() + data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(len as u32).to_le_bytes());
+        bytes.extend_from_slice(&table);
+        bytes.extend_from_slice(&data);
+        Ok(OpeningExplorer { bytes, len })
+    }
+
+    fn record(
+        &mut self,
+        hash: u64,
+        mv: ChessMove,
+        winner: Option<Color>,
+        top_game: Option<&TopGame>,
+    ) {
+        let moves = self.positions.entry(hash).or_default();
+        let stats = match moves.iter().position(|stats| stats.mv == mv) {
+            Some(index) => &mut moves[index],
+            None => {
+                moves.push(MoveStats::new(mv));
+                moves.last_mut().unwrap()
+            }
+        };
+        stats.record(winner, top_game, self.config.top_games);
+    }
+}
+
+/// Move statistics per position like the Lichess opening explorer, built from own game collections.
+///
+/// Layout: the magic bytes `GCOE`, the format version as u16 LE, the amount of positions as u32 LE,
+/// the position table sorted by hash, then the encoded moves of each position.
+/// Lookups only decode the moves of the requested position.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OpeningExplorer {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl OpeningExplorer {
+    /// Loads an explorer previously written with [`OpeningExplorer::as_bytes`]
+    pub fn from_bytes(bytes: Vec<u8>) -> std::io::Result<Self> {
+        let header = bytes
+            .get(..HEADER_LEN)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Missing header"))?;
+        if &header[..4] != MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Not an opening explorer",
+            ));
+        }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version > FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Unsupported explorer version {version}"),
+            ));
+        }
+
+        let len = u32::from_le_bytes(header[6..10].try_into().unwrap()) as usize;
+        if bytes.len() < HEADER_LEN + len * TABLE_ENTRY_LEN {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "Truncated table"));
+        }
+        Ok(Self { bytes, len })
+    }
+
+    /// The moves played in the position, most played first, empty if it was never reached
+    pub fn explore(&self, pos: &Position) -> std::io::Result<Vec<MoveStats>> {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.table_hash(mid) < pos.hash {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if low == self.len || self.table_hash(low) != pos.hash {
+            return Ok(vec![]);
+        }
+
+        let data_start = HEADER_LEN + self.len * TABLE_ENTRY_LEN;
+        let start = data_start + self.table_offset(low);
+        let entry = self
+            .bytes
+            .get(start..)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Truncated moves"))?;
+        Vec::<MoveStats>::decode(&mut BitReader::new(entry))
+    }
+
+    fn table_hash(&self, index: usize) -> u64 {
+        let start = HEADER_LEN + index * TABLE_ENTRY_LEN;
+        u64::from_le_bytes(self.bytes[start..start + 8].try_into().unwrap())
+    }
+
+    fn table_offset(&self, index: usize) -> usize {
+        let start = HEADER_LEN + index * TABLE_ENTRY_LEN + 8;
+        u32::from_le_bytes(self.bytes[start..start + 4].try_into().unwrap()) as usize
+    }
+}
+
+// Accessors
+impl OpeningExplorer {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Amount of positions
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::explorer::{
+        ExplorerConfig, HEADER_LEN, OpeningExplorer, OpeningExplorerBuilder, TABLE_ENTRY_LEN,
+    };
+    use crate::core::position::Position;
+    use crate::game::Game;
+    use crate::prelude::*;
+
+    fn play(moves: &[(Square, Square)]) -> Game {
+        let mut game = Game::new();
+        for &(from, to) in moves {
+            game.play(from, to).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_explore() {
+        let mut builder = OpeningExplorerBuilder::new(ExplorerConfig {
+            top_games: 2,
+            ..Default::default()
+        });
+
+        let mut game = play(&[(E2, E4), (E7, E5)]);
+        game.resign(Color::Black);
+        builder.add_game(&game, "a", Some(2000), Some(2200));
+        let mut game = play(&[(E2, E4), (C7, C5)]);
+        game.agree_draw();
+        builder.add_game(&game, "b", Some(2500), Some(2500));
+        let mut game = play(&[(D2, D4), (D7, D5)]);
+        game.resign(Color::White);
+        builder.add_game(&game, "c", None, Some(1500));
+        let mut game = play(&[(E2, E4)]);
+        game.resign(Color::White);
+        builder.add_game(&game, "d", Some(1000), Some(1000));
+        // Unfinished games are skipped
+        builder.add_game(&play(&[(G1, F3)]), "e", Some(3000), Some(3000));
+
+        let explorer = builder.build().unwrap();
+        let explorer = OpeningExplorer::from_bytes(explorer.as_bytes().to_vec()).unwrap();
+
+        let moves = explorer.explore(&Position::default()).unwrap();
+        assert_eq!(moves.len(), 2);
+        let e4 = &moves[0];
+        assert_eq!(e4.mv.to_uci(), "e2e4");
+        assert_eq!((e4.white_wins, e4.draws, e4.black_wins), (1, 1, 1));
+        assert_eq!(e4.average_rating(), Some(1866));
+        let top: Vec<&str> = e4.top_games.iter().map(|game| game.id.as_str()).collect();
+        assert_eq!(top, ["b", "a"]);
+
+        let d4 = &moves[1];
+        assert_eq!(d4.black_wins, 1);
+        assert_eq!(d4.average_rating(), None);
+
+        let after_e4 = Position::default().make_move(e4.mv);
+        assert_eq!(explorer.explore(&after_e4).unwrap().len(), 2);
+        let after_nf3 = play(&[(G1, F3)]);
+        assert!(explorer.explore(after_nf3.position()).unwrap().is_empty());
+
+        // A truncated explorer fails instead of looking like an unknown position
+        let mut bytes = explorer.as_bytes().to_vec();
+        bytes.truncate(HEADER_LEN + explorer.len() * TABLE_ENTRY_LEN);
+        let truncated = OpeningExplorer::from_bytes(bytes).unwrap();
+        assert!(truncated.explore(&Position::default()).is_err());
+    }
+
+    #[test]
+    fn test_repetitions_count_once() {
+        let shuffle = [(G1, F3), (G8, F6), (F3, G1), (F6, G8)];
+        let mut game = play(&[shuffle, shuffle].concat());
+        game.claim_draw().unwrap();
+        let mut builder = OpeningExplorerBuilder::new(ExplorerConfig::default());
+        builder.add_game(&game, "a", Some(2000), Some(2000));
+        let explorer = builder.build().unwrap();
+
+        let moves = explorer.explore(&Position::default()).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].draws, 1);
+        assert_eq!(moves[0].top_games.len(), 1);
+    }
+}
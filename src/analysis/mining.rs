@@ -0,0 +1,259 @@
+use crate::core::puzzle::Puzzle;
+use crate::engine::score::Score;
+use crate::engine::{Engine, SearchLimit};
+use crate::error::EngineResult;
+use crate::game::Game;
+use crate::game::outcome::GameOutcome;
+use crate::prelude::{ChessMove, Color};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiningConfig {
+    pub limit: SearchLimit,
+    /// Minimum loss of win percentage (0-100) for a move to count as a blunder
+    pub blunder: f64,
+    /// Minimum win percentage (0-100) of the punishing side after the blunder
+    pub winning: f64,
+    /// How much win percentage (0-100) the second best move has to lose for a solution move to be unique
+    pub unique_margin: f64,
+    /// A non-mating solution ends once the solver gained this much material in centipawns
+    pub converted_cp: i32,
+    /// Maximum amount of plies of a solution
+    pub max_plies: usize,
+}
+
+impl Default for MiningConfig {
+    fn default() -> Self {
+        Self {
+            limit: SearchLimit::depth(12),
+            blunder: 15.0,
+            winning: 80.0,
+            unique_margin: 20.0,
+            converted_cp: 200,
+            max_plies: 15,
+        }
+    }
+}
+
+/// A puzzle found in a game, see [`mine_game`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MinedPuzzle {
+    /// Index of the blunder in the history of the game
+    pub ply: usize,
+    pub puzzle: Puzzle,
+    /// Evaluation after the blunder, from white's perspective
+    pub score: Score,
+}
+
+impl MinedPuzzle {
+    /// The side solving the puzzle
+    pub fn solver(&self) -> Color {
+        self.puzzle.position().side_to_move.opposite()
+    }
+
+    /// The record for a [`LichessPuzzleArchive`](crate::lichess::archive::LichessPuzzleArchive), themes are detected from the solution.
+    /// `game_id` is the id of the mined game, empty if it has none.
+    #[cfg(feature = "lichess-puzzle-parser")]
+    pub fn to_lichess(
+        &self,
+        id: impl Into<String>,
+        game_id: impl Into<String>,
+        rating: u16,
+    ) -> crate::lichess::puzzle::LichessPuzzle {
+        crate::lichess::puzzle::LichessPuzzle {
+            id: id.into(),
+            puzzle: self.puzzle.clone(),
+            themes: crate::lichess::themes::detect(&self.puzzle),
+            rating,
+            rating_deviation: 500,
+            times_played: 0,
+            unknown_themes: vec![],
+            popularity: 0,
+            game_id: game_id.into(),
+            game_ply: self.ply as u16,
+            opening_tags: vec![],
+        }
+    }
+}
+
+/// Finds positions where one side blundered and the other has a unique winning continuation.
+///
+/// The solution follows the engine until the advantage is converted into material or mate,
+/// positions where the solver has more than one good move are skipped.
+pub fn mine_game(
+    game: &Game,
+    engine: &mut dyn Engine,
+    config: &MiningConfig,
+) -> EngineResult<Vec<MinedPuzzle>> {
+    engine.new_game()?;
+
+    let mut replay = Game::from_position(*game.start_position());
+    let mut before = evaluate(&replay, engine, &config.limit)?;
+    let mut puzzles = Vec::new();
+
+    for (ply, &mv) in game.history().iter().enumerate() {
+        let pos = *replay.position();
+        let color = pos.side_to_move;
+        replay.play_move(mv)?;
+        if replay.is_over() {
+            break;
+        }
+        let after = evaluate(&replay, engine, &config.limit)?;
+
+        let loss = before.win_percent(color) - after.win_percent(color);
+        let solver = color.opposite();
+        if loss >= config.blunder
+            && after.win_percent(solver) >= config.winning
+            && let Some(solution) = solve(&replay, engine, config)?
+        {
+            puzzles.push(MinedPuzzle {
+                ply,
+                puzzle: Puzzle::new(pos, mv, solution),
+                score: after,
+            });
+        }
+        before = after;
+    }
+
+    Ok(puzzles)
+}
+
+/// The solution for the side to move, None if a solver move isn't unique or the advantage isn't converted in time
+fn solve(
+    game: &Game,
+    engine: &mut dyn Engine,
+    config: &MiningConfig,
+) -> EngineResult<Option<Vec<ChessMove>>> {
+    let mut game = game.clone();
+    let solver = game.position().side_to_move;
+    let initial = game.position().board.material_balance(solver);
+    let multi_pv = config.limit.clone().with_multi_pv(2);
+    let mut solution = Vec::new();
+
+    while solution.len() < config.max_plies {
+        let result = engine.search(&game, &multi_pv)?;
+        let Some(best) = result.best_line() else {
+            break;
+        };
+        let Some(&mv) = best.pv.first() else {
+            break;
+        };
+        let mating = matches!(best.score, Score::Mate { winner, .. } if winner == solver);
+        let unique = result.lines.get(1).is_none_or(|second| {
+            best.score.win_percent(solver) - second.score.win_percent(solver)
+                >= config.unique_margin
+        });
+        if !unique {
+            // The solution has to end with a move of the solver
+            if solution.pop().is_some() {
+                game.undo_move();
+            }
+            break;
+        }
+
+        game.play_move(mv)?;
+        solution.push(mv);
+        if game.is_over() {
+            break;
+        }
+        let gained = game.position().board.material_balance(solver) - initial;
+        if !mating && gained >= config.converted_cp {
+            return Ok(Some(solution));
+        }
+
+        let Some(reply) = engine.search(&game, &config.limit)?.best_move() else {
+            break;
+        };
+        game.play_move(reply)?;
+        solution.push(reply);
+    }
+
+    let converted = match game.outcome() {
+        Some(GameOutcome::Decisive { winner, .. }) => winner == solver,
+        Some(_) => false,
+        None => game.position().board.material_balance(solver) - initial >= config.converted_cp,
+    };
+    if solution.is_empty() || !converted || solution.len().is_multiple_of(2) {
+        return Ok(None);
+    }
+    Ok(Some(solution))
+}
+
+fn evaluate(game: &Game, engine: &mut dyn Engine, limit: &SearchLimit) -> EngineResult<Score> {
+    Ok(engine.search(game, limit)?.score().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::mining::{MiningConfig, mine_game, solve};
+    use crate::engine::score::Score;
+    use crate::engine::search::MaterialSearch;
+    use crate::engine::{Engine, SearchLimit, SearchLine, SearchResult};
+    use crate::error::EngineResult;
+    use crate::game::Game;
+    use crate::prelude::*;
+
+    /// Answers the searches with prepared lines in order
+    struct ScriptedEngine(Vec<Vec<(Score, ChessMove)>>);
+
+    impl Engine for ScriptedEngine {
+        fn search(&mut self, _game: &Game, _limit: &SearchLimit) -> EngineResult<SearchResult> {
+            let lines = self.0.remove(0);
+            Ok(SearchResult {
+                lines: lines
+                    .into_iter()
+                    .map(|(score, mv)| SearchLine {
+                        score,
+                        depth: 1,
+                        pv: vec![mv],
+                    })
+                    .collect(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_mines_queen_blunder() {
+        let mut game = Game::new();
+        for (from, to) in [(E2, E4), (E7, E5), (D1, H5), (B8, C6), (H5, E5), (C6, E5)] {
+            let mv = ChessMove::from_position(game.position(), from, to, None).unwrap();
+            game.play_move(mv).unwrap();
+        }
+
+        let config = MiningConfig {
+            limit: SearchLimit::depth(2),
+            ..Default::default()
+        };
+        let puzzles = mine_game(&game, &mut MaterialSearch::default(), &config).unwrap();
+        assert_eq!(puzzles.len(), 1);
+
+        let mined = &puzzles[0];
+        assert_eq!(mined.ply, 4);
+        assert_eq!(mined.solver(), Color::Black);
+        assert_eq!(mined.puzzle.last_move().to_uci(), "h5e5");
+        let solution: Vec<String> = mined.puzzle.solution().iter().map(|m| m.to_uci()).collect();
+        assert_eq!(solution, ["c6e5"]);
+    }
+
+    #[test]
+    fn test_solution_ends_on_solver_move() {
+        let game = Game::from_position("3rk3/8/8/8/8/8/8/3QK3 w - - 0 1".parse().unwrap());
+        let capture = ChessMove::from_position(game.position(), D1, D8, None).unwrap();
+        let after_capture = game.position().make_move(capture);
+        let recapture = ChessMove::from_position(&after_capture, E8, D8, None).unwrap();
+        let after_recapture = after_capture.make_move(recapture);
+        let king_move = |to| ChessMove::from_position(&after_recapture, E1, to, None).unwrap();
+
+        // The solver's next move isn't unique, so the line ends with the capture and the recapture is dropped
+        let mate = Score::Mate {
+            winner: Color::White,
+            moves: 2,
+        };
+        let mut engine = ScriptedEngine(vec![
+            vec![(mate, capture)],
+            vec![(mate, recapture)],
+            vec![(Score::DRAW, king_move(E2)), (Score::DRAW, king_move(F2))],
+        ]);
+        let solution = solve(&game, &mut engine, &MiningConfig::default()).unwrap();
+        assert_eq!(solution, Some(vec![capture]));
+    }
+}
@@ -351,6 +351,18 @@ impl ChessBoard {
             .find(|&piece| self.piece_bb(piece, color).is_set(square))
     }
 
+    /// Material value of the color minus the opponent's, see [`Piece::value`]
+    pub fn material_balance(&self, color: Color) -> i32 {
+        Piece::ALL
+            .iter()
+            .map(|&piece| {
+                let own = self.specific_piece_count(piece, color) as i32;
+                let theirs = self.specific_piece_count(piece, color.opposite()) as i32;
+                (own - theirs) * piece.value()
+            })
+            .sum()
+    }
+
     pub fn has_sufficient_material(&self, color: Color) -> bool {
         if !self.piece_bb(Piece::Pawn, color).is_empty() {
             return true;
@@ -68,6 +68,18 @@ impl Piece {
         }
     }
 
+    /// Conventional material value in centipawns, the king is valued at 0.
+    pub const fn value(&self) -> i32 {
+        match self {
+            Self::Pawn => 100,
+            Self::Knight => 320,
+            Self::Bishop => 330,
+            Self::Rook => 500,
+            Self::Queen => 900,
+            Self::King => 0,
+        }
+    }
+
     pub fn fen_char(&self, color: Color) -> char {
         if color == Color::White {
             self.char()
@@ -1,5 +1,6 @@
 use crate::core::zobrist::ZobristKeys;
 use crate::error::{FenError, FenResult};
+use crate::moves::generator::MoveGenerator;
 use crate::prelude::*;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
@@ -53,6 +54,10 @@ impl Position {
         let color = self.side_to_move;
         let opponent = color.opposite();
 
+        if let Some(piece) = mv.drop_piece() {
+            return self.make_drop(piece, to);
+        }
+
         let Some(piece) = self.board.piece_at_with_color(from, color) else {
             return self;
         };
@@ -148,6 +153,38 @@ impl Position {
         self
     }
 
+    /// Dropping a piece from the pocket, assuming it's legal.
+    fn make_drop(mut self, piece: Piece, to: Square) -> Self {
+        let color = self.side_to_move;
+        let mut hash = self.hash;
+
+        self.board.set(piece, color, to);
+        hash ^= ZobristKeys::piece_key(piece, color, to);
+
+        if let Some(sq) = self.en_passant_square.take() {
+            hash ^= ZobristKeys::ep_key(sq);
+        }
+        self.half_moves = if piece == Piece::Pawn {
+            0
+        } else {
+            self.half_moves.saturating_add(1)
+        };
+        if color == Color::Black {
+            self.full_moves = self.full_moves.saturating_add(1);
+        }
+        self.side_to_move = color.opposite();
+        hash ^= ZobristKeys::side_key();
+
+        self.hash = hash;
+        self
+    }
+
+    /// If the side to move is in check and has no legal moves
+    pub fn is_checkmate(&self) -> bool {
+        let generator = MoveGenerator::get();
+        generator.is_in_check(self, self.side_to_move) && generator.generate(self).is_empty()
+    }
+
     pub fn pretty_grid(&self) -> String {
         self.board.pretty_grid()
     }
@@ -1,71 +1,232 @@
 use crate::core::position::Position;
+use crate::engine::{Engine, SearchLimit, SearchResult};
+use crate::error::EngineResult;
+use crate::game::Game;
 use crate::moves::naive::NaivePromotionMove;
-use crate::prelude::ChessMove;
+use crate::prelude::{ChessMove, Square};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PuzzlePlayer {
     pz: Puzzle,
+    policy: PuzzlePolicy,
+    /// The moves played so far, the opponent's replies included
+    played: Vec<ChessMove>,
     index: usize,
     times_failed: usize,
+    hints_used: usize,
+}
+
+/// Which moves besides the one of the solution are accepted
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PuzzlePolicy {
+    /// Accept any mating move on the last move of the solution, like Lichess does
+    pub any_mate: bool,
+    /// Additional accepted moves by index into the solution
+    pub alternatives: Vec<(u16, ChessMove)>,
+    /// With [`PuzzlePlayer::try_move_verified`], accept moves the engine scores at most this many centipawns below the solution move
+    pub engine_margin_cp: Option<u32>,
+}
+
+impl Default for PuzzlePolicy {
+    fn default() -> Self {
+        Self {
+            any_mate: true,
+            alternatives: vec![],
+            engine_margin_cp: None,
+        }
+    }
 }
 
 impl PuzzlePlayer {
     pub fn new(pz: Puzzle) -> Self {
         Self {
             pz,
+            policy: PuzzlePolicy::default(),
+            played: vec![],
             index: 0,
             times_failed: 0,
+            hints_used: 0,
         }
     }
 
+    pub fn with_policy(mut self, policy: PuzzlePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     pub fn try_move(&mut self, mv: NaivePromotionMove) -> PuzzleEvent {
-        let pos = self.position();
-        let Some(chess_move) = mv.get_move(&pos) else {
-            self.times_failed += 1;
-            return PuzzleEvent::Illegal;
+        let chess_move = match self.legal_move(mv) {
+            Ok(chess_move) => chess_move,
+            Err(event) => return event,
         };
-        if chess_move != self.pz.solution[self.index] {
+        if self.is_accepted(chess_move) {
+            self.advance(chess_move)
+        } else {
             self.times_failed += 1;
-            return PuzzleEvent::Wrong;
+            PuzzleEvent::Wrong
         }
-        self.index += 1;
-        if self.index < self.pz.solution.len() {
-            self.index += 1;
-        }
-        if self.index >= self.pz.solution.len() {
-            PuzzleEvent::Solved
+    }
+
+    /// Like [`PuzzlePlayer::try_move`], but asks the engine about moves the policy doesn't accept otherwise
+    pub fn try_move_verified(
+        &mut self,
+        mv: NaivePromotionMove,
+        engine: &mut dyn Engine,
+        limit: &SearchLimit,
+    ) -> EngineResult<PuzzleEvent> {
+        let chess_move = match self.legal_move(mv) {
+            Ok(chess_move) => chess_move,
+            Err(event) => return Ok(event),
+        };
+        if self.is_accepted(chess_move) || self.engine_accepts(chess_move, engine, limit)? {
+            Ok(self.advance(chess_move))
         } else {
-            PuzzleEvent::Correct
+            self.times_failed += 1;
+            Ok(PuzzleEvent::Wrong)
         }
     }
 
+    /// The square of the piece to move next, counts as a used hint
+    pub fn hint(&mut self) -> Option<Square> {
+        let mv = self.expected_move()?;
+        self.hints_used += 1;
+        Some(mv.from())
+    }
+
+    /// The move of the solution to play next, counts as a used hint
+    pub fn reveal(&mut self) -> Option<ChessMove> {
+        let mv = self.expected_move()?;
+        self.hints_used += 1;
+        Some(mv)
+    }
+
     pub fn position(&self) -> Position {
         let mut position = self.pz.pos.make_move(self.pz.last_move);
-        for &mv in &self.pz.solution[..self.index] {
+        for &mv in &self.played {
             position = position.make_move(mv);
         }
         position
     }
 
     pub fn last_move(&self) -> Option<&ChessMove> {
-        if self.index == 0 {
-            Some(&self.pz.last_move)
+        self.played.last().or(Some(&self.pz.last_move))
+    }
+
+    fn expected_move(&self) -> Option<ChessMove> {
+        self.pz.solution.get(self.index).copied()
+    }
+
+    fn legal_move(&mut self, mv: NaivePromotionMove) -> Result<ChessMove, PuzzleEvent> {
+        if self.is_solved() {
+            return Err(PuzzleEvent::Solved);
+        }
+        mv.get_move(&self.position()).ok_or_else(|| {
+            self.times_failed += 1;
+            PuzzleEvent::Illegal
+        })
+    }
+
+    fn is_accepted(&self, mv: ChessMove) -> bool {
+        if self.expected_move() == Some(mv) {
+            return true;
+        }
+        if self
+            .policy
+            .alternatives
+            .iter()
+            .any(|&(index, alternative)| index as usize == self.index && alternative == mv)
+        {
+            return true;
+        }
+        let is_last = self.index + 1 >= self.pz.solution.len();
+        self.policy.any_mate && is_last && self.position().make_move(mv).is_checkmate()
+    }
+
+    fn engine_accepts(
+        &self,
+        mv: ChessMove,
+        engine: &mut dyn Engine,
+        limit: &SearchLimit,
+    ) -> EngineResult<bool> {
+        let (Some(margin), Some(expected)) = (self.policy.engine_margin_cp, self.expected_move())
+        else {
+            return Ok(false);
+        };
+        let pos = self.position();
+        let solver = pos.side_to_move;
+        let mut evaluate = |mv: ChessMove| -> EngineResult<i32> {
+            let game = Game::from_position(pos.make_move(mv));
+            let result = match SearchResult::for_finished(&game) {
+                Some(result) => result,
+                None => engine.search(&game, limit)?,
+            };
+            Ok(result.score().unwrap_or_default().cp_for(solver))
+        };
+        let expected = evaluate(expected)?;
+        Ok(evaluate(mv)? + margin as i32 >= expected)
+    }
+
+    /// Plays the accepted move and the opponent's reply.
+    /// An accepted move other than the solution's leaves the scripted line, so the puzzle counts as solved.
+    fn advance(&mut self, mv: ChessMove) -> PuzzleEvent {
+        let on_solution = self.expected_move() == Some(mv);
+        self.played.push(mv);
+        self.index += 1;
+
+        let pos = self.position();
+        let reply = self
+            .pz
+            .solution
+            .get(self.index)
+            .copied()
+            .filter(|_| on_solution && !pos.is_checkmate());
+        let Some(reply) = reply else {
+            self.index = self.pz.solution.len();
+            return PuzzleEvent::Solved;
+        };
+
+        self.played.push(reply);
+        self.index += 1;
+        if self.is_solved() {
+            PuzzleEvent::Solved
         } else {
-            self.pz.solution.get(self.index - 1)
+            PuzzleEvent::Correct { reply }
         }
     }
+}
 
+// Accessors
+impl PuzzlePlayer {
     pub fn times_failed(&self) -> usize {
         self.times_failed
     }
+
+    /// Hints and revealed moves
+    pub fn hints_used(&self) -> usize {
+        self.hints_used
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.index >= self.pz.solution.len()
+    }
+
+    pub fn policy(&self) -> &PuzzlePolicy {
+        &self.policy
+    }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum PuzzleEvent {
     Illegal,
     Wrong,
-    Correct,
+    /// The move was accepted and the opponent answered with the reply
+    Correct {
+        reply: ChessMove,
+    },
     Solved,
 }
 
@@ -103,3 +264,91 @@ impl Puzzle {
         &self.solution
     }
 }
+
+#[cfg(test)]
+impl Puzzle {
+    /// A puzzle from a FEN and moves in UCI notation, the first move leads into the puzzle
+    pub(crate) fn from_uci(fen: &str, moves: &[&str]) -> Self {
+        let start: Position = fen.parse().unwrap();
+        let mut pos = start;
+        let moves: Vec<ChessMove> = moves
+            .iter()
+            .map(|m| {
+                let mv = m
+                    .parse::<NaivePromotionMove>()
+                    .unwrap()
+                    .get_move(&pos)
+                    .unwrap();
+                pos = pos.make_move(mv);
+                mv
+            })
+            .collect();
+        Self::new(start, moves[0], moves[1..].to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::puzzle::{Puzzle, PuzzleEvent, PuzzlePlayer, PuzzlePolicy};
+    use crate::moves::naive::NaivePromotionMove;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_accepts_alternative_mate() {
+        let pz = Puzzle::from_uci("6k1/1p3ppp/8/8/8/8/8/R3R1K1 b - - 0 1", &["b7b6", "a1a8"]);
+        let mut player = PuzzlePlayer::new(pz.clone());
+        assert_eq!(player.hint(), Some(A1));
+        assert_eq!(
+            player.try_move("e1e8".parse().unwrap()),
+            PuzzleEvent::Solved
+        );
+        assert!(player.is_solved());
+        assert_eq!(player.hints_used(), 1);
+
+        let strict = PuzzlePolicy {
+            any_mate: false,
+            ..Default::default()
+        };
+        let mut player = PuzzlePlayer::new(pz).with_policy(strict);
+        assert_eq!(player.try_move("e1e8".parse().unwrap()), PuzzleEvent::Wrong);
+        assert_eq!(player.times_failed(), 1);
+    }
+
+    #[test]
+    fn test_correct_contains_reply() {
+        let pz = Puzzle::from_uci(
+            "r3k3/8/8/8/2N5/8/8/4K3 b - - 0 1",
+            &["e8d7", "c4b6", "d7c7", "b6a8"],
+        );
+        let mut player = PuzzlePlayer::new(pz);
+        let event = player.try_move("c4b6".parse().unwrap());
+        assert!(matches!(event, PuzzleEvent::Correct { reply } if reply.to_uci() == "d7c7"));
+        assert_eq!(player.reveal().map(|mv| mv.to_uci()), Some("b6a8".into()));
+        assert_eq!(
+            player.try_move("b6a8".parse().unwrap()),
+            PuzzleEvent::Solved
+        );
+    }
+
+    #[test]
+    fn test_alternative_leaves_solution() {
+        let pz = Puzzle::from_uci(
+            "r3k3/8/8/8/2N5/8/8/4K3 b - - 0 1",
+            &["e8d7", "c4b6", "d7c7", "b6a8"],
+        );
+        let alternative = "c4e5".parse::<NaivePromotionMove>().unwrap();
+        let start = pz.position().make_move(*pz.last_move());
+        let policy = PuzzlePolicy {
+            alternatives: vec![(0, alternative.get_move(&start).unwrap())],
+            ..Default::default()
+        };
+        let mut player = PuzzlePlayer::new(pz).with_policy(policy);
+        // The scripted reply would still be legal, but belongs to a different position
+        assert_eq!(player.try_move(alternative), PuzzleEvent::Solved);
+        assert!(player.is_solved());
+        assert_eq!(
+            player.last_move().map(|mv| mv.to_uci()),
+            Some("c4e5".into())
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use crate::engine::score::Score;
+use crate::error::EngineResult;
+use crate::game::Game;
+use crate::game::outcome::GameOutcome;
+use crate::moves::generator::MoveGenerator;
+use crate::prelude::{ChessMove, Color};
+
+pub mod score;
+pub mod search;
+
+/// Anything that can search a position and suggest moves, be it an external UCI engine or a built-in searcher.
+pub trait Engine {
+    /// Signals that the following searches belong to a new game
+    fn new_game(&mut self) -> EngineResult<()> {
+        Ok(())
+    }
+
+    /// Searches the current position of the game.
+    /// The game is passed as a whole so the engine can account for its history (repetitions etc.).
+    fn search(&mut self, game: &Game, limit: &SearchLimit) -> EngineResult<SearchResult>;
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchLimit {
+    /// Stop the search once this depth is reached
+    pub depth: Option<u32>,
+    /// Stop the search after this many ms
+    pub move_time_ms: Option<u64>,
+    /// Stop the search after this many nodes
+    pub nodes: Option<u64>,
+    /// How many of the best lines to report, 0 is treated like 1
+    pub multi_pv: u32,
+    /// Remaining time on the clocks, the engine manages its own time for the move
+    pub clock: Option<SearchClock>,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SearchClock {
+    pub white_ms: u64,
+    pub black_ms: u64,
+    pub white_inc_ms: u64,
+    pub black_inc_ms: u64,
+}
+
+impl SearchClock {
+    /// A simple time budget for the next move: a fraction of the remaining time plus most of the increment
+    pub fn budget_ms(&self, color: Color) -> u64 {
+        let (remaining, increment) = match color {
+            Color::White => (self.white_ms, self.white_inc_ms),
+            Color::Black => (self.black_ms, self.black_inc_ms),
+        };
+        (remaining / 30 + increment * 3 / 4).min(remaining / 2)
+    }
+}
+
+impl SearchLimit {
+    pub fn depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn move_time(ms: u64) -> Self {
+        Self {
+            move_time_ms: Some(ms),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_multi_pv(mut self, lines: u32) -> Self {
+        self.multi_pv = lines;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: SearchClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchLine {
+    /// Evaluation from white's perspective
+    pub score: Score,
+    pub depth: u32,
+    /// The principal variation, empty if the position has no legal moves
+    pub pv: Vec<ChessMove>,
+}
+
+/// The lines an engine found, ordered best first
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub lines: Vec<SearchLine>,
+}
+
+impl SearchResult {
+    /// The result for a position without legal moves
+    pub fn terminal(score: Score) -> Self {
+        Self {
+            lines: vec![SearchLine {
+                score,
+                depth: 0,
+                pv: vec![],
+            }],
+        }
+    }
+
+    /// The result for a finished game or a position without legal moves, None if there's something to search
+    pub fn for_finished(game: &Game) -> Option<Self> {
+        if let Some(outcome) = game.outcome() {
+            let score = match outcome {
+                GameOutcome::Decisive { winner, .. } => Score::Mate { winner, moves: 0 },
+                _ => Score::DRAW,
+            };
+            return Some(Self::terminal(score));
+        }
+        if !game.legal_moves().is_empty() {
+            return None;
+        }
+
+        let pos = game.position();
+        let score = if MoveGenerator::get().is_in_check(pos, pos.side_to_move) {
+            Score::Mate {
+                winner: pos.side_to_move.opposite(),
+                moves: 0,
+            }
+        } else {
+            Score::DRAW
+        };
+        Some(Self::terminal(score))
+    }
+
+    pub fn best_line(&self) -> Option<&SearchLine> {
+        self.lines.first()
+    }
+
+    pub fn best_move(&self) -> Option<ChessMove> {
+        self.best_line().and_then(|line| line.pv.first().copied())
+    }
+
+    pub fn score(&self) -> Option<Score> {
+        self.best_line().map(|line| line.score)
+    }
+}
@@ -0,0 +1,114 @@
+use crate::prelude::Color;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+/// Centipawn evaluations are capped at this value when converting to win probabilities or centipawn losses.
+pub const CP_CEILING: i32 = 1000;
+
+/// An engine evaluation from white's perspective.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Score {
+    /// Advantage in centipawns, positive values favor white
+    Cp(i32),
+    /// The winner can force mate in the given amount of moves, 0 if the position is already mate
+    Mate { winner: Color, moves: u32 },
+}
+
+impl Score {
+    pub const DRAW: Self = Self::Cp(0);
+
+    /// Builds a score from an evaluation given from the perspective of `color`
+    pub fn from_perspective(cp: i32, color: Color) -> Self {
+        match color {
+            Color::White => Self::Cp(cp),
+            Color::Black => Self::Cp(-cp),
+        }
+    }
+
+    /// Centipawns from the perspective of `color`, mates are capped at [`CP_CEILING`]
+    pub fn cp_for(&self, color: Color) -> i32 {
+        let white = match *self {
+            Self::Cp(cp) => cp.clamp(-CP_CEILING, CP_CEILING),
+            Self::Mate {
+                winner: Color::White,
+                ..
+            } => CP_CEILING,
+            Self::Mate {
+                winner: Color::Black,
+                ..
+            } => -CP_CEILING,
+        };
+        match color {
+            Color::White => white,
+            Color::Black => -white,
+        }
+    }
+
+    /// Win probability of `color` in percent (0-100).
+    ///
+    /// Uses the logistic model Lichess fitted on rated games, mates count as certain wins.
+    pub fn win_percent(&self, color: Color) -> f64 {
+        if let Self::Mate { winner, .. } = *self {
+            return if winner == color { 100.0 } else { 0.0 };
+        }
+        let cp = self.cp_for(color) as f64;
+        50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * cp).exp()) - 1.0)
+    }
+
+    pub fn is_mate(&self) -> bool {
+        matches!(self, Self::Mate { .. })
+    }
+
+    /// A key that orders scores from best for black to best for white
+    fn order_key(&self) -> i64 {
+        match *self {
+            Self::Cp(cp) => cp as i64,
+            Self::Mate {
+                winner: Color::White,
+                moves,
+            } => i64::MAX - moves as i64,
+            Self::Mate {
+                winner: Color::Black,
+                moves,
+            } => i64::MIN + moves as i64,
+        }
+    }
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Self::DRAW
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Ordered from white's perspective, a faster mate for white is the greatest score
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order_key().cmp(&other.order_key())
+    }
+}
+
+/// Formats the score like the PGN `[%eval]` command: `0.35`, `-1.20`, `#3` or `#-3`
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Cp(cp) => write!(f, "{:.2}", cp as f64 / 100.0),
+            Self::Mate {
+                winner: Color::White,
+                moves,
+            } => write!(f, "#{moves}"),
+            Self::Mate {
+                winner: Color::Black,
+                moves,
+            } => write!(f, "#-{moves}"),
+        }
+    }
+}
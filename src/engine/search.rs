@@ -0,0 +1,321 @@
+use crate::core::position::Position;
+use crate::engine::score::Score;
+use crate::engine::{Engine, SearchLimit, SearchLine, SearchResult};
+use crate::error::EngineResult;
+use crate::game::Game;
+use crate::moves::generator::MoveGenerator;
+use crate::prelude::{ChessMove, Color, Piece};
+use std::time::{Duration, Instant};
+
+const MATE: i32 = 100_000;
+const MATE_THRESHOLD: i32 = MATE - 1_000;
+
+/// A small alpha-beta searcher which only evaluates material.
+///
+/// It finds short tactics and mates reliably at low depths but has no positional understanding,
+/// which makes it useful for tests, sparring and as a fallback when no UCI engine is available.
+#[derive(Debug, Clone)]
+pub struct MaterialSearch {
+    default_depth: u32,
+    nodes: u64,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+}
+
+impl Default for MaterialSearch {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl MaterialSearch {
+    /// The default depth is used when the search limit doesn't specify one
+    pub fn new(default_depth: u32) -> Self {
+        Self {
+            default_depth: default_depth.max(1),
+            nodes: 0,
+            node_limit: None,
+            deadline: None,
+        }
+    }
+
+    /// Nodes visited during the last search
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    fn should_stop(&self) -> bool {
+        self.node_limit.is_some_and(|limit| self.nodes >= limit)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Scores every root move, returns None if the search was stopped
+    fn search_root(
+        &mut self,
+        pos: &Position,
+        moves: &[ChessMove],
+        depth: u32,
+        multi_pv: usize,
+    ) -> Option<Vec<(i32, Vec<ChessMove>)>> {
+        let mut scored: Vec<(i32, Vec<ChessMove>)> = Vec::with_capacity(moves.len());
+        let mut alpha = -MATE;
+
+        for &mv in moves {
+            let mut child_pv = Vec::new();
+            // With multiple lines every root move needs an exact score, so only the single line search narrows the window
+            let window_alpha = if multi_pv > 1 { -MATE } else { alpha };
+            let score = -self.negamax(
+                &pos.make_move(mv),
+                depth - 1,
+                1,
+                -MATE,
+                -window_alpha,
+                &mut child_pv,
+            )?;
+            alpha = alpha.max(score);
+
+            let mut pv = vec![mv];
+            pv.extend(child_pv);
+            scored.push((score, pv));
+        }
+
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.truncate(multi_pv);
+        Some(scored)
+    }
+
+    fn negamax(
+        &mut self,
+        pos: &Position,
+        depth: u32,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<ChessMove>,
+    ) -> Option<i32> {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(1024) && self.should_stop() {
+            return None;
+        }
+
+        let moves = MoveGenerator::get().generate(pos);
+        if moves.is_empty() {
+            return Some(terminal_score(pos, ply));
+        }
+        if depth == 0 {
+            return Some(self.quiescence(pos, alpha, beta));
+        }
+
+        let mut best = -MATE;
+        for mv in ordered(pos, moves.as_slice()) {
+            let mut child_pv = Vec::new();
+            let score = -self.negamax(
+                &pos.make_move(mv),
+                depth - 1,
+                ply + 1,
+                -beta,
+                -alpha,
+                &mut child_pv,
+            )?;
+
+            if score > best {
+                best = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend(child_pv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        Some(best)
+    }
+
+    fn quiescence(&mut self, pos: &Position, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        let stand_pat = evaluate(pos);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let moves = MoveGenerator::get().generate(pos);
+        let captures: Vec<ChessMove> = moves.iter().copied().filter(|mv| mv.is_capture()).collect();
+        for mv in ordered(pos, &captures) {
+            let score = -self.quiescence(&pos.make_move(mv), -beta, -alpha);
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+}
+
+impl Engine for MaterialSearch {
+    fn search(&mut self, game: &Game, limit: &SearchLimit) -> EngineResult<SearchResult> {
+        if let Some(result) = SearchResult::for_finished(game) {
+            return Ok(result);
+        }
+        let pos = *game.position();
+
+        self.nodes = 0;
+        self.node_limit = limit.nodes;
+        self.deadline = limit
+            .move_time_ms
+            .or(limit.clock.map(|clock| clock.budget_ms(pos.side_to_move)))
+            .map(|ms| Instant::now() + Duration::from_millis(ms));
+
+        let max_depth = limit.depth.unwrap_or(self.default_depth).max(1);
+        let multi_pv = (limit.multi_pv as usize).max(1);
+        let root_moves = ordered(&pos, game.legal_moves().as_slice());
+
+        let mut completed: Vec<(i32, Vec<ChessMove>)> = Vec::new();
+        let mut completed_depth = 0;
+        for depth in 1..=max_depth {
+            let Some(lines) = self.search_root(&pos, &root_moves, depth, multi_pv) else {
+                break;
+            };
+            completed = lines;
+            completed_depth = depth;
+            if self.should_stop() {
+                break;
+            }
+        }
+
+        // Even a stopped search should suggest a move
+        if completed.is_empty() {
+            // The evaluation is from the opponent's perspective after the move
+            completed.push((
+                -evaluate(&pos.make_move(root_moves[0])),
+                vec![root_moves[0]],
+            ));
+        }
+
+        let lines = completed
+            .into_iter()
+            .map(|(score, pv)| SearchLine {
+                score: to_score(score, pos.side_to_move),
+                depth: completed_depth,
+                pv,
+            })
+            .collect();
+        Ok(SearchResult { lines })
+    }
+}
+
+/// Material balance from the side to move's perspective
+fn evaluate(pos: &Position) -> i32 {
+    pos.board.material_balance(pos.side_to_move)
+}
+
+fn terminal_score(pos: &Position, ply: i32) -> i32 {
+    if MoveGenerator::get().is_in_check(pos, pos.side_to_move) {
+        -MATE + ply
+    } else {
+        0
+    }
+}
+
+fn to_score(score: i32, side_to_move: Color) -> Score {
+    if score.abs() >= MATE_THRESHOLD {
+        let plies = (MATE - score.abs()) as u32;
+        let winner = if score > 0 {
+            side_to_move
+        } else {
+            side_to_move.opposite()
+        };
+        Score::Mate {
+            winner,
+            moves: plies.div_ceil(2),
+        }
+    } else {
+        Score::from_perspective(score, side_to_move)
+    }
+}
+
+/// Captures first, most valuable victim by least valuable attacker
+fn ordered(pos: &Position, moves: &[ChessMove]) -> Vec<ChessMove> {
+    let mut moves = moves.to_vec();
+    moves.sort_by_cached_key(|mv| {
+        if !mv.is_capture() {
+            return 0;
+        }
+        let victim = pos
+            .board
+            .piece_at(mv.to())
+            .map_or(Piece::Pawn.value(), |(piece, _)| piece.value());
+        let attacker = pos
+            .board
+            .piece_at(mv.from())
+            .map_or(0, |(piece, _)| piece.value());
+        -(victim * 10 - attacker)
+    });
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::position::Position;
+    use crate::engine::score::Score;
+    use crate::engine::search::MaterialSearch;
+    use crate::engine::{Engine, SearchLimit};
+    use crate::game::Game;
+    use crate::prelude::*;
+    use std::str::FromStr;
+
+    fn search(fen: &str, limit: SearchLimit) -> crate::engine::SearchResult {
+        let game = Game::from_position(Position::from_str(fen).unwrap());
+        MaterialSearch::default().search(&game, &limit).unwrap()
+    }
+
+    #[test]
+    fn test_finds_back_rank_mate() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", SearchLimit::depth(2));
+        let best = result.best_move().unwrap();
+        assert_eq!((best.from(), best.to()), (A1, A8));
+        assert_eq!(
+            result.score(),
+            Some(Score::Mate {
+                winner: Color::White,
+                moves: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_wins_hanging_queen() {
+        let result = search("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", SearchLimit::depth(2));
+        let best = result.best_move().unwrap();
+        assert_eq!((best.from(), best.to()), (D1, D5));
+    }
+
+    #[test]
+    fn test_multi_pv_and_terminal() {
+        let result = search(
+            "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1",
+            SearchLimit::depth(1).with_multi_pv(3),
+        );
+        assert_eq!(result.lines.len(), 3);
+        assert!(result.lines[0].score >= result.lines[1].score);
+        assert!(result.lines[1].score >= result.lines[2].score);
+
+        let mated = search(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            SearchLimit::depth(2),
+        );
+        assert_eq!(mated.best_move(), None);
+        assert_eq!(
+            mated.score(),
+            Some(Score::Mate {
+                winner: Color::Black,
+                moves: 0
+            })
+        );
+    }
+}
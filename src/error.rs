@@ -28,21 +28,69 @@ pub enum ChessError {
     NoDrawClaimable,
 }
 
+pub type EngineResult<T> = Result<T, EngineError>;
+#[derive(Debug, thiserror::Error)]
+pub enum EngineError {
+    #[error(transparent)]
+    Chess(#[from] ChessError),
+    #[error("Engine returned an illegal move: {0}")]
+    IllegalMove(String),
+    #[error("Engine I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Engine did not return a best move")]
+    NoBestMove,
+    #[error("Engine protocol error: {0}")]
+    Protocol(String),
+}
+
+pub type PgnResult<T> = Result<T, PgnError>;
+#[derive(Debug, thiserror::Error)]
+pub enum PgnError {
+    #[error("Illegal move {san} at ply {ply}")]
+    IllegalMove { ply: usize, san: String },
+    #[error("Invalid FEN: {0}")]
+    InvalidFen(#[from] FenError),
+    #[error("Invalid tag: {0}")]
+    InvalidTag(String),
+    #[error("Unterminated {0}")]
+    Unterminated(&'static str),
+}
+
 pub type SessionResult<T> = Result<T, SessionError>;
 #[derive(Debug, thiserror::Error)]
 pub enum SessionError {
+    #[error("The game can only be aborted before both sides moved")]
+    AbortNotAllowed,
     #[error(transparent)]
     Chess(#[from] ChessError),
+    #[error("The clock is not paused")]
+    ClockNotPaused,
+    #[error("The clock is paused")]
+    ClockPaused,
+    #[error("Delta for ply {delta_ply} doesn't follow ply {ply}")]
+    DeltaOutOfSync { ply: u16, delta_ply: u16 },
     #[error("Draw offer already offered")]
     DrawAlreadyOffered,
     #[error("Game is already over")]
     GameOver,
+    #[error("Game is not over yet")]
+    GameNotOver,
     #[error("Invalid FEN: {0}")]
     InvalidFen(#[from] FenError),
     #[error("There is no draw offer to accept or decline")]
     NoDrawOffer,
+    #[error("The session has no clock")]
+    NoClock,
+    #[error("There is no move to take back")]
+    NoMoveToTakeBack,
+    #[error("There is no takeback request to accept or decline")]
+    NoTakebackRequest,
     #[error("Color is not to move")]
     NotMovingColor,
     #[error("The clock is not out of time")]
     NotOutOfTime,
+    #[error("Takeback already requested")]
+    TakebackAlreadyRequested,
+    #[error("Takebacks are not possible in this mode")]
+    TakebackNotAllowed,
 }
@@ -2,15 +2,21 @@ use crate::core::bitboard::BitBoard;
 use crate::core::position::Position;
 use crate::error::{ChessError, ChessResult};
 use crate::game::mode::GameMode;
-use crate::game::outcome::{DecisiveReason, DrawReason, GameOutcome};
+use crate::game::outcome::{AbortReason, DecisiveReason, DrawReason, GameOutcome};
+use crate::game::pocket::Pocket;
 use crate::game::state::GameState;
 use crate::moves::generator::MoveGenerator;
 use crate::moves::list::MoveList;
 use crate::notation::san::move_to_san;
+use crate::openings;
+use crate::openings::Opening;
 use crate::prelude::{ChessMove, Color, MoveFlags, Piece, Square};
 
+#[cfg(feature = "game-archive")]
+pub mod archive;
 pub mod mode;
 pub mod outcome;
+pub mod pocket;
 pub mod state;
 
 /// A chess game that encapsulates the overall game state as well as current legal moves, move history and outcome.
@@ -19,6 +25,7 @@ pub mod state;
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Game {
     mode: GameMode,
+    start_pos: Position,
     pos: Position,
     legal_moves: MoveList,
     history: Vec<ChessMove>,
@@ -26,6 +33,10 @@ pub struct Game {
     outcome: Option<GameOutcome>,
     /// The pieces which white captured at 0, black captured at 1.
     captured_pieces: [Vec<Piece>; 2],
+    /// Pieces that can be dropped, only used in modes with drops.
+    pocket: Pocket,
+    /// Squares of pieces that were promoted from pawns, they turn back into pawns once captured in drop modes.
+    promoted: BitBoard,
 }
 
 impl Default for Game {
@@ -43,12 +54,15 @@ impl Game {
         let legal_moves = MoveGenerator::get().generate(&pos);
         Self {
             mode: GameMode::Standard,
+            start_pos: pos,
             pos,
             legal_moves,
             history: vec![],
             hash_history: vec![pos.hash],
             outcome: None,
             captured_pieces: [vec![], vec![]],
+            pocket: Pocket::default(),
+            promoted: BitBoard::empty(),
         }
     }
 
@@ -67,8 +81,18 @@ impl Game {
         self
     }
 
+    pub fn with_pocket(mut self, pocket: Pocket) -> Self {
+        self.pocket = pocket;
+        self
+    }
+
     pub fn play_move(&mut self, mv: ChessMove) -> ChessResult<()> {
-        if !self.legal_moves.contains(mv) {
+        if let Some(piece) = mv.drop_piece() {
+            if !self.is_legal_drop(piece, mv.to()) {
+                return Err(ChessError::IllegalMove);
+            }
+            self.pocket.take(self.pos.side_to_move, piece);
+        } else if !self.legal_moves.contains(mv) {
             return Err(ChessError::IllegalMove);
         }
 
@@ -84,6 +108,7 @@ impl Game {
             }
         }
 
+        self.promoted = track_promoted(self.promoted, mv);
         self.pos = self.pos.make_move(mv);
         self.history.push(mv);
         self.hash_history.push(self.pos.hash);
@@ -114,6 +139,34 @@ impl Game {
         Ok(())
     }
 
+    /// Takes back the last move, None if there is no move to take back.
+    ///
+    /// Any outcome is cleared since the game continues from the previous position.
+    pub fn undo_move(&mut self) -> Option<ChessMove> {
+        let mv = self.history.pop()?;
+        self.hash_history.pop();
+        let mover = self.pos.side_to_move.opposite();
+        if mv.flags().is_capture() {
+            self.captured_pieces[mover as usize].pop();
+        }
+        if let Some(piece) = mv.drop_piece() {
+            self.pocket.add(mover, piece);
+        }
+
+        // Positions don't keep enough information to unmake a move (castling rights, en passant, clocks),
+        // so the previous position is replayed from the start.
+        self.pos = self.start_pos;
+        self.promoted = BitBoard::empty();
+        for mv in &self.history {
+            self.promoted = track_promoted(self.promoted, *mv);
+            self.pos = self.pos.make_move(*mv);
+        }
+        self.legal_moves = MoveGenerator::get().generate(&self.pos);
+        self.outcome = None;
+
+        Some(mv)
+    }
+
     pub fn play_move_get_san(&mut self, mv: ChessMove) -> ChessResult<String> {
         let san = move_to_san(&self.pos, mv, &self.legal_moves)?;
         self.play_move(mv)?;
@@ -186,6 +239,10 @@ impl Game {
         }
     }
 
+    pub fn abort(&mut self, reason: AbortReason) {
+        self.outcome = Some(GameOutcome::Aborted(reason));
+    }
+
     pub fn agree_draw(&mut self) {
         self.outcome = Some(GameOutcome::Draw(DrawReason::Agreement));
     }
@@ -193,14 +250,67 @@ impl Game {
     pub fn force_outcome(&mut self, outcome: GameOutcome) {
         self.outcome = Some(outcome);
     }
+
+    /// Adds a piece the side may drop later on, only useful in modes with drops
+    pub fn add_to_pocket(&mut self, color: Color, piece: Piece) {
+        self.pocket.add(color, piece);
+    }
+
+    pub fn is_legal_drop(&self, piece: Piece, to: Square) -> bool {
+        let color = self.pos.side_to_move;
+        self.mode.has_drops()
+            && self.pocket.count(color, piece) > 0
+            && self.pos.board.piece_at(to).is_none()
+            && !(piece == Piece::Pawn && to.is_any_promotion_square())
+            && MoveGenerator::get().is_legal(&self.pos, ChessMove::drop(piece, to))
+    }
+
+    /// All drops the side to move can make, empty in modes without drops
+    pub fn legal_drops(&self) -> Vec<ChessMove> {
+        self.drops().collect()
+    }
+
+    fn drops(&self) -> impl Iterator<Item = ChessMove> + '_ {
+        let color = self.pos.side_to_move;
+        let empty = !self.pos.board.occupied_bb();
+        self.pocket
+            .pieces(color)
+            .filter(|_| self.mode.has_drops())
+            .flat_map(move |piece| empty.iter().map(move |to| (piece, to)))
+            .filter(|(piece, to)| self.is_legal_drop(*piece, *to))
+            .map(|(piece, to)| ChessMove::drop(piece, to))
+    }
+}
+
+/// Promoted pieces follow their moves and are removed once captured
+fn track_promoted(mut promoted: BitBoard, mv: ChessMove) -> BitBoard {
+    if mv.is_drop() {
+        return promoted;
+    }
+    let was_promoted = promoted.is_set(mv.from());
+    promoted.clear(mv.from());
+    promoted.clear(mv.to());
+    if was_promoted || mv.is_promotion() {
+        promoted.set(mv.to());
+    }
+    promoted
 }
 
 // Accessors
 impl Game {
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
     pub fn position(&self) -> &Position {
         &self.pos
     }
 
+    /// The position the game started from, before any move in the history was played
+    pub fn start_position(&self) -> &Position {
+        &self.start_pos
+    }
+
     pub fn legal_moves(&self) -> &MoveList {
         &self.legal_moves
     }
@@ -214,7 +324,7 @@ impl Game {
             GameState::DrawSeventyFive
         } else if self.repetition_count() >= 5 {
             GameState::DrawFivefold
-        } else if self.legal_moves.is_empty() {
+        } else if self.legal_moves.is_empty() && self.drops().next().is_none() {
             if MoveGenerator::get().is_in_check(&self.pos, self.pos.side_to_move) {
                 GameState::Checkmate
             } else {
@@ -224,7 +334,8 @@ impl Game {
             GameState::DrawFiftyMoveClaimable
         } else if self.repetition_count() >= 3 {
             GameState::DrawRepetitionClaimable
-        } else if !self.pos.board.has_sufficient_material(Color::White)
+        } else if !self.mode.has_drops()
+            && !self.pos.board.has_sufficient_material(Color::White)
             && !self.pos.board.has_sufficient_material(Color::Black)
         {
             GameState::DrawInsufficientMaterial
@@ -262,6 +373,15 @@ impl Game {
         &self.captured_pieces[color as usize]
     }
 
+    pub fn pocket(&self) -> &Pocket {
+        &self.pocket
+    }
+
+    /// Squares of pieces that were promoted from pawns
+    pub fn promoted_pieces(&self) -> BitBoard {
+        self.promoted
+    }
+
     pub fn king_threats(&self, color: Color) -> BitBoard {
         MoveGenerator::get().all_king_attackers(self.position(), color)
     }
@@ -277,6 +397,18 @@ impl Game {
     pub fn fen(&self) -> String {
         self.pos.to_string()
     }
+
+    /// The last opening of the table reached in this game, transpositions included.
+    /// The compiled in table is a subset of the Lichess openings, see [`crate::openings::classify`].
+    pub fn opening(&self) -> Option<&'static Opening> {
+        let mut pos = self.start_pos;
+        let mut opening = openings::classify(&pos);
+        for &mv in &self.history {
+            pos = pos.make_move(mv);
+            opening = openings::classify(&pos).or(opening);
+        }
+        opening
+    }
 }
 
 #[cfg(test)]
@@ -645,4 +777,30 @@ mod tests {
         assert!(game.king_threats(Color::White).is_empty());
         assert!(game.king_threats(Color::Black).is_empty());
     }
+
+    #[test]
+    fn test_undo_move() {
+        let mut game = Game::new();
+        play(&mut game, E2, E4);
+        play(&mut game, D7, D5);
+        let before_capture = game.clone();
+
+        play(&mut game, E4, D5);
+        assert_eq!(game.captured_pieces(Color::White), &[Piece::Pawn]);
+        assert_eq!(
+            game.undo_move().map(|mv| (mv.from(), mv.to())),
+            Some((E4, D5))
+        );
+        assert_eq!(game, before_capture);
+
+        play(&mut game, F1, B5);
+        play(&mut game, B8, C6);
+        game.resign(Color::Black);
+        assert!(game.undo_move().is_some());
+        assert!(!game.is_over());
+        assert!(game.find_move(B8, C6, None).is_some());
+
+        let mut empty = Game::new();
+        assert_eq!(empty.undo_move(), None);
+    }
 }
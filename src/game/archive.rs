@@ -0,0 +1,548 @@
+use crate::core::position::Position;
+use crate::game::mode::GameMode;
+use crate::game::outcome::GameOutcome;
+use crate::moves::generator::MoveGenerator;
+use crate::notation::pgn::PgnHeaders;
+use crate::prelude::ChessMove;
+use crate::session::SessionRecord;
+use crate::session::clock::MoveTime;
+use crate::session::config::{SessionConfig, StartingPosition, TimeControl};
+use bit_codec::{BitDecode, BitReader, BitWriter};
+use std::io::{Error, ErrorKind, Write};
+use std::str::FromStr;
+
+/// Amount of games per zstd frame
+pub const CHUNK_SIZE: usize = 256;
+const COMPRESSION_LEVEL: i32 = 12;
+const MAGIC: &[u8; 4] = b"GCGA";
+pub const FORMAT_VERSION: u16 = 1;
+/// Marks a move that isn't part of the generated legal moves, like a drop
+const OTHER_MOVE: u8 = u8::MAX;
+
+/// A finished game as stored in a [`GameArchive`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchivedGame {
+    pub mode: GameMode,
+    pub starting_position: StartingPosition,
+    pub time_control: TimeControl,
+    pub headers: PgnHeaders,
+    pub moves: Vec<ChessMove>,
+    /// Empty if the times weren't recorded
+    pub move_times: Vec<MoveTime>,
+    /// Milliseconds since UNIX epoch
+    pub started_at_ms: Option<u64>,
+    pub outcome: Option<GameOutcome>,
+    pub adjudication_reason: Option<String>,
+}
+
+impl ArchivedGame {
+    /// A record that can be restored into a [`Session`](crate::prelude::Session), the clock is replayed from the move times
+    pub fn to_record(&self) -> SessionRecord {
+        let config = SessionConfig {
+            mode: self.mode,
+            starting_position: self.starting_position.clone(),
+            time_control: self.time_control.clone(),
+            pgn: self.headers.clone(),
+            ..Default::default()
+        };
+        let mut record = SessionRecord::from_moves(
+            config,
+            self.moves.clone(),
+            self.move_times.clone(),
+            self.started_at_ms,
+            self.outcome,
+        );
+        record.adjudication_reason = self.adjudication_reason.clone();
+        record
+    }
+}
+
+impl From<&SessionRecord> for ArchivedGame {
+    fn from(record: &SessionRecord) -> Self {
+        Self {
+            mode: record.config.mode,
+            starting_position: record.config.starting_position.clone(),
+            time_control: record.config.time_control.clone(),
+            headers: record.config.pgn.clone(),
+            moves: record.moves.clone(),
+            move_times: record.move_times.clone(),
+            started_at_ms: record.started_at_ms,
+            outcome: record.outcome,
+            adjudication_reason: record.adjudication_reason.clone(),
+        }
+    }
+}
+
+/// The stored form of an [`ArchivedGame`], moves are indices into the legal moves of their position
+#[derive(bit_codec::BitEncode, bit_codec::BitDecode)]
+struct EncodedGame {
+    mode: GameMode,
+    starting_position: StartingPosition,
+    time_control: TimeControl,
+    headers: PgnHeaders,
+    moves: Vec<u8>,
+    /// The moves marked with [`OTHER_MOVE`], in order
+    other_moves: Vec<ChessMove>,
+    started_at_ms: Option<u64>,
+    /// Milliseconds since the previous move or the start of the game, think and remaining time
+    move_times: Vec<(u64, Option<u64>, Option<u64>)>,
+    outcome: Option<GameOutcome>,
+    adjudication_reason: Option<String>,
+}
+
+impl EncodedGame {
+    fn from_game(game: &ArchivedGame) -> std::io::Result<Self> {
+        let mut pos = start_position(&game.starting_position)?;
+        let mut moves = Vec::with_capacity(game.moves.len());
+        let mut other_moves = Vec::new();
+        for &mv in &game.moves {
+            let legal_moves = MoveGenerator::get().generate(&pos);
+            match legal_moves.iter().position(|&legal| legal == mv) {
+                Some(index) => moves.push(index as u8),
+                None => {
+                    moves.push(OTHER_MOVE);
+                    other_moves.push(mv);
+                }
+            }
+            pos = pos.make_move(mv);
+        }
+
+        let mut last_ms = game.started_at_ms.unwrap_or_default();
+        let move_times = game
+            .move_times
+            .iter()
+            .map(|time| {
+                let delta_ms = time.played_at_ms.wrapping_sub(last_ms);
+                last_ms = time.played_at_ms;
+                (delta_ms, time.think_ms, time.remaining_ms)
+            })
+            .collect();
+
+        Ok(Self {
+            mode: game.mode,
+            starting_position: game.starting_position.clone(),
+            time_control: game.time_control.clone(),
+            headers: game.headers.clone(),
+            moves,
+            other_moves,
+            started_at_ms: game.started_at_ms,
+            move_times,
+            outcome: game.outcome,
+            adjudication_reason: game.adjudication_reason.clone(),
+        })
+    }
+
+    fn into_game(self) -> std::io::Result<ArchivedGame> {
+        let mut pos = start_position(&self.starting_position)?;
+        let mut other_moves = self.other_moves.into_iter();
+        let mut moves = Vec::with_capacity(self.moves.len());
+        for index in self.moves {
+            let mv = if index == OTHER_MOVE {
+                other_moves.next()
+            } else {
+                MoveGenerator::get()
+                    .generate(&pos)
+                    .as_slice()
+                    .get(index as usize)
+                    .copied()
+            }
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid move index"))?;
+            pos = pos.make_move(mv);
+            moves.push(mv);
+        }
+
+        let mut last_ms = self.started_at_ms.unwrap_or_default();
+        let move_times = self
+            .move_times
+            .into_iter()
+            .map(|(delta_ms, think_ms, remaining_ms)| {
+                last_ms = last_ms.wrapping_add(delta_ms);
+                MoveTime {
+                    played_at_ms: last_ms,
+                    think_ms,
+                    remaining_ms,
+                }
+            })
+            .collect();
+
+        Ok(ArchivedGame {
+            mode: self.mode,
+            starting_position: self.starting_position,
+            time_control: self.time_control,
+            headers: self.headers,
+            moves,
+            started_at_ms: self.started_at_ms,
+            move_times,
+            outcome: self.outcome,
+            adjudication_reason: self.adjudication_reason,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, bit_codec::BitEncode, bit_codec::BitDecode)]
+struct GameArchiveIndex {
+    len: u64,
+    /// Byte offsets of the chunk frames, followed by the end of the last frame
+    chunk_offsets: Vec<u64>,
+}
+
+/// Streams games into the [`GameArchive`] format, only the current chunk is kept in memory
+pub struct GameArchiveWriter<W: Write> {
+    writer: W,
+    index: GameArchiveIndex,
+    chunk: Vec<EncodedGame>,
+    /// Bytes written after the header
+    written: u64,
+}
+
+impl<W: Write> GameArchiveWriter<W> {
+    pub fn new(mut writer: W) -> std::io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            index: GameArchiveIndex::default(),
+            chunk: Vec::with_capacity(CHUNK_SIZE),
+            written: 0,
+        })
+    }
+
+    pub fn push(&mut self, game: &ArchivedGame) -> std::io::Result<()> {
+        self.chunk.push(EncodedGame::from_game(game)?);
+        self.index.len += 1;
+        if self.chunk.len() == CHUNK_SIZE {
+            self.write_chunk()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the remaining games and the index, returns the inner writer
+    pub fn finish(mut self) -> std::io::Result<W> {
+        if !self.chunk.is_empty() {
+            self.write_chunk()?;
+        }
+        self.index.chunk_offsets.push(self.written);
+
+        let mut index_bytes = Vec::new();
+        {
+            let encoder = zstd::Encoder::new(&mut index_bytes, COMPRESSION_LEVEL)?;
+            let mut writer = BitWriter::new(encoder);
+            writer.write(&self.index)?;
+            writer.flush()?;
+            writer.into_inner().finish()?;
+        }
+        self.writer.write_all(&index_bytes)?;
+        self.writer
+            .write_all(&(index_bytes.len() as u32).to_le_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_chunk(&mut self) -> std::io::Result<()> {
+        let mut frame = Vec::new();
+        {
+            let encoder = zstd::Encoder::new(&mut frame, COMPRESSION_LEVEL)?;
+            let mut writer = BitWriter::new(encoder);
+            for game in &self.chunk {
+                writer.write(game)?;
+            }
+            writer.flush()?;
+            writer.into_inner().finish()?;
+        }
+        self.writer.write_all(&frame)?;
+        self.index.chunk_offsets.push(self.written);
+        self.written += frame.len() as u64;
+        self.chunk.clear();
+        Ok(())
+    }
+}
+
+/// Finished games stored in independently compressed chunks, for random access and parallel decoding.
+///
+/// Layout: the magic bytes `GCGA`, the format version as u16 LE, the chunk frames,
+/// the zstd compressed index, then the length of the compressed index as u32 LE.
+/// The index comes last so archives can be written as a stream, see [`GameArchiveWriter`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GameArchive {
+    bytes: Vec<u8>,
+    index: GameArchiveIndex,
+    version: u16,
+}
+
+impl GameArchive {
+    pub fn try_from_games<'a, I>(games: I) -> std::io::Result<Self>
+    where
+        I: IntoIterator<Item = &'a ArchivedGame>,
+    {
+        let mut writer = GameArchiveWriter::new(Vec::new())?;
+        for game in games {
+            writer.push(game)?;
+        }
+        Self::from_bytes(writer.finish()?)
+    }
+
+    /// Loads an archive previously written with a [`GameArchiveWriter`], only the index is decoded
+    pub fn from_bytes(bytes: Vec<u8>) -> std::io::Result<Self> {
+        let version = bytes
+            .strip_prefix(MAGIC)
+            .and_then(|rest| rest.get(..2))
+            .map(|v| u16::from_le_bytes([v[0], v[1]]))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Not a game archive"))?;
+        if version > FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Unsupported archive version {version}"),
+            ));
+        }
+
+        let index_end = bytes
+            .len()
+            .checked_sub(4)
+            .filter(|&end| end >= 6)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Missing index length"))?;
+        let index_len = u32::from_le_bytes(bytes[index_end..].try_into().unwrap()) as usize;
+        let index_bytes = index_end
+            .checked_sub(index_len)
+            .and_then(|start| bytes.get(start..index_end))
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Truncated index"))?;
+        let index =
+            GameArchiveIndex::decode(&mut BitReader::new(zstd::Decoder::new(index_bytes)?))?;
+
+        Ok(Self {
+            bytes,
+            index,
+            version,
+        })
+    }
+
+    pub fn iter(&self) -> GameArchiveIter<'_> {
+        GameArchiveIter {
+            archive: self,
+            chunk: 0,
+            buffer: Vec::new().into_iter(),
+        }
+    }
+
+    /// The game at the given position of the archive, only its chunk is decompressed
+    pub fn get(&self, index: usize) -> std::io::Result<Option<ArchivedGame>> {
+        if index >= self.len() {
+            return Ok(None);
+        }
+        let mut games = self.decode_chunk(index / CHUNK_SIZE, index % CHUNK_SIZE + 1)?;
+        Ok(games.pop())
+    }
+
+    /// Decodes the chunks in parallel and keeps the games the function maps to Some, in archive order
+    #[cfg(feature = "rayon")]
+    pub fn par_filter_map<T, F>(&self, f: F) -> std::io::Result<Vec<T>>
+    where
+        T: Send,
+        F: Fn(ArchivedGame) -> Option<T> + Sync,
+    {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+        let chunks = (0..self.chunk_count())
+            .into_par_iter()
+            .map(|chunk| {
+                let games = self.decode_chunk(chunk, CHUNK_SIZE)?;
+                Ok(games.into_iter().filter_map(&f).collect::<Vec<_>>())
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    /// Decodes up to `take` games from the start of the given chunk
+    fn decode_chunk(&self, chunk: usize, take: usize) -> std::io::Result<Vec<ArchivedGame>> {
+        let (start, end) = self
+            .index
+            .chunk_offsets
+            .get(chunk)
+            .zip(self.index.chunk_offsets.get(chunk + 1))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Chunk out of range"))?;
+        let frame = self
+            .bytes
+            .get(6 + *start as usize..6 + *end as usize)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Truncated chunk"))?;
+
+        let count = CHUNK_SIZE.min(self.len() - chunk * CHUNK_SIZE).min(take);
+        let mut reader = BitReader::new(zstd::Decoder::new(frame)?);
+        (0..count)
+            .map(|_| EncodedGame::decode(&mut reader)?.into_game())
+            .collect()
+    }
+}
+
+// Accessors
+impl GameArchive {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The format version the archive was written with
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.index.chunk_offsets.len().saturating_sub(1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.len == 0
+    }
+}
+
+pub struct GameArchiveIter<'a> {
+    archive: &'a GameArchive,
+    chunk: usize,
+    buffer: std::vec::IntoIter<ArchivedGame>,
+}
+
+impl<'a> Iterator for GameArchiveIter<'a> {
+    type Item = std::io::Result<ArchivedGame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(game) = self.buffer.next() {
+                return Some(Ok(game));
+            }
+            if self.chunk >= self.archive.chunk_count() {
+                return None;
+            }
+            let chunk = self.chunk;
+            self.chunk += 1;
+            match self.archive.decode_chunk(chunk, CHUNK_SIZE) {
+                Ok(games) => self.buffer = games.into_iter(),
+                Err(e) => {
+                    self.chunk = self.archive.chunk_count();
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+fn start_position(starting_position: &StartingPosition) -> std::io::Result<Position> {
+    match starting_position {
+        StartingPosition::Default => Ok(Position::default()),
+        StartingPosition::Fen(fen) => {
+            Position::from_str(fen).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Game;
+    use crate::game::archive::{ArchivedGame, CHUNK_SIZE, GameArchive};
+    use crate::game::outcome::{DecisiveReason, GameOutcome};
+    use crate::notation::pgn::PgnHeaders;
+    use crate::prelude::*;
+    use crate::session::action::SessionAction;
+    use crate::session::clock::{ChessClockConfig, MoveTime};
+    use crate::session::config::{SessionConfig, StartingPosition, TimeControl};
+
+    /// A deterministic game of up to 60 plies
+    fn game(seed: usize) -> ArchivedGame {
+        let mut game = Game::new();
+        for ply in 0..60 {
+            if game.is_over() {
+                break;
+            }
+            let legal_moves = game.legal_moves().as_slice().to_vec();
+            game.play_move(legal_moves[(seed * 31 + ply * 7) % legal_moves.len()])
+                .unwrap();
+        }
+        ArchivedGame {
+            headers: PgnHeaders {
+                event: Some(format!("Game {seed}")),
+                ..Default::default()
+            },
+            moves: game.history().to_vec(),
+            outcome: game.outcome(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_archive() {
+        let games: Vec<_> = (0..CHUNK_SIZE + 10).map(game).collect();
+        let archive = GameArchive::try_from_games(&games).unwrap();
+        let archive = GameArchive::from_bytes(archive.as_bytes().to_vec()).unwrap();
+        assert_eq!(archive.len(), games.len());
+        assert_eq!(archive.chunk_count(), 2);
+
+        let plies: usize = games.iter().map(|game| game.moves.len()).sum();
+        assert!(archive.as_bytes().len() < plies);
+
+        let decoded: Vec<_> = archive.iter().map(Result::unwrap).collect();
+        assert_eq!(decoded, games);
+        assert_eq!(
+            archive.get(CHUNK_SIZE + 3).unwrap().as_ref(),
+            games.get(CHUNK_SIZE + 3)
+        );
+        assert_eq!(archive.get(games.len()).unwrap(), None);
+
+        #[cfg(feature = "rayon")]
+        {
+            let events = archive.par_filter_map(|game| game.headers.event).unwrap();
+            assert_eq!(events.len(), games.len());
+            assert_eq!(events[CHUNK_SIZE], format!("Game {CHUNK_SIZE}"));
+        }
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let config = ChessClockConfig::fischer(60_000, 1_000);
+        let mut session = Session::from_config(&SessionConfig {
+            starting_position: StartingPosition::Fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string()),
+            time_control: TimeControl::Clock(config),
+            ..Default::default()
+        })
+        .unwrap();
+        session
+            .act(
+                Color::White,
+                SessionAction::Move(session.game().find_move(E2, E4, None).unwrap()),
+                1_000,
+            )
+            .unwrap();
+        session
+            .act(
+                Color::Black,
+                SessionAction::Move(session.game().find_move(E8, D7, None).unwrap()),
+                4_000,
+            )
+            .unwrap();
+        session
+            .act(Color::White, SessionAction::Resign, 5_000)
+            .unwrap();
+
+        let archived = ArchivedGame::from(&session.record());
+        let archive = GameArchive::try_from_games([&archived]).unwrap();
+        let decoded = archive.get(0).unwrap().unwrap();
+        assert_eq!(decoded, archived);
+        assert_eq!(
+            decoded.move_times[1],
+            MoveTime {
+                played_at_ms: 4_000,
+                think_ms: Some(3_000),
+                remaining_ms: Some(58_000),
+            }
+        );
+
+        let restored = decoded.to_record().restore().unwrap();
+        assert_eq!(restored.game().position(), session.game().position());
+        assert_eq!(
+            restored.game().outcome(),
+            Some(GameOutcome::Decisive {
+                winner: Color::Black,
+                reason: DecisiveReason::Resignation
+            })
+        );
+        assert_eq!(restored.clock(), session.clock());
+    }
+}
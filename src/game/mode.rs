@@ -13,4 +13,13 @@
 pub enum GameMode {
     #[default]
     Standard,
+    /// Captured pieces are passed to the partner on another board, who may drop them as a move
+    Bughouse,
+}
+
+impl GameMode {
+    /// If pieces can be dropped from the pocket
+    pub fn has_drops(&self) -> bool {
+        matches!(self, Self::Bughouse)
+    }
 }
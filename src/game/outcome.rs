@@ -7,13 +7,15 @@ use crate::prelude::Color;
     feature = "bit-codec",
     derive(bit_codec::BitEncode, bit_codec::BitDecode)
 )]
-#[cfg_attr(feature = "bit-codec", bits(disc = 1))]
+#[cfg_attr(feature = "bit-codec", bits(disc = 2))]
 pub enum GameOutcome {
     Decisive {
         winner: Color,
         reason: DecisiveReason,
     },
     Draw(DrawReason),
+    /// The game ended without a result
+    Aborted(AbortReason),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -27,7 +29,7 @@ pub enum GameOutcome {
     feature = "bit-codec",
     derive(bit_codec::BitEncode, bit_codec::BitDecode)
 )]
-#[cfg_attr(feature = "bit-codec", bits(disc = 2))]
+#[cfg_attr(feature = "bit-codec", bits(disc = 3))]
 pub enum DecisiveReason {
     /// One sides king was in check and had no legal moves to move it out of check.
     Checkmate,
@@ -35,6 +37,10 @@ pub enum DecisiveReason {
     Resignation,
     /// One side ran out of time.
     Timeout,
+    /// An arbiter or adjudication rule decided the game.
+    Adjudication,
+    /// The game on the partner's board decided the team match, e.g. in Bughouse.
+    PartnerBoard,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -48,7 +54,7 @@ pub enum DecisiveReason {
     feature = "bit-codec",
     derive(bit_codec::BitEncode, bit_codec::BitDecode)
 )]
-#[cfg_attr(feature = "bit-codec", bits(disc = 3))]
+#[cfg_attr(feature = "bit-codec", bits(disc = 4))]
 pub enum DrawReason {
     /// One side had no legal moves but was not in check.
     Stalemate,
@@ -66,4 +72,27 @@ pub enum DrawReason {
     InsufficientMaterial,
     /// One side ran out of time but the other side had insufficient material.
     TimeoutVsInsufficient,
+    /// An arbiter or adjudication rule declared the game drawn.
+    Adjudication,
+    /// The game on the partner's board ended in a draw, e.g. in Bughouse.
+    PartnerBoard,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "strum",
+    derive(strum::EnumIter, strum::EnumIs, strum::EnumCount)
+)]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+#[cfg_attr(feature = "bit-codec", bits(disc = 2))]
+pub enum AbortReason {
+    /// One side didn't make its first move in time.
+    FirstMoveTimeout,
+    /// One side aborted the game before both sides moved.
+    Requested,
 }
@@ -0,0 +1,48 @@
+use crate::prelude::{Color, Piece};
+
+/// Captured pieces that can be dropped back onto the board, as in Crazyhouse and Bughouse
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub struct Pocket {
+    /// Piece counts of white at 0, black at 1, indexed by piece
+    counts: [[u8; 6]; 2],
+}
+
+impl Pocket {
+    /// Kings can't be captured and are never added
+    pub fn add(&mut self, color: Color, piece: Piece) {
+        if piece != Piece::King {
+            self.counts[color as usize][piece as usize] += 1;
+        }
+    }
+
+    /// Returns false if the piece wasn't in the pocket
+    pub fn take(&mut self, color: Color, piece: Piece) -> bool {
+        let count = &mut self.counts[color as usize][piece as usize];
+        if *count == 0 {
+            return false;
+        }
+        *count -= 1;
+        true
+    }
+
+    pub fn count(&self, color: Color, piece: Piece) -> u8 {
+        self.counts[color as usize][piece as usize]
+    }
+
+    pub fn is_empty(&self, color: Color) -> bool {
+        self.counts[color as usize].iter().all(|count| *count == 0)
+    }
+
+    /// Every kind of piece the side has at least once
+    pub fn pieces(&self, color: Color) -> impl Iterator<Item = Piece> + '_ {
+        Piece::ALL
+            .into_iter()
+            .filter(move |piece| self.count(color, *piece) > 0)
+    }
+}
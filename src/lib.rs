@@ -1,13 +1,17 @@
+pub mod analysis;
 #[cfg(feature = "serde")]
 mod big_array;
 pub mod core;
+pub mod engine;
 pub mod error;
 pub mod game;
-#[cfg(feature = "lichess-puzzle-parser")]
+#[cfg(any(feature = "lichess-game-parser", feature = "lichess-puzzle-parser"))]
 pub mod lichess;
 pub mod moves;
 pub mod notation;
+pub mod openings;
 pub mod prelude;
 pub mod session;
 #[cfg(feature = "stockfish-manager")]
 pub mod stockfish;
+pub mod tournament;
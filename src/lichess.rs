@@ -1,5 +1,14 @@
 #[cfg(feature = "lichess-puzzle-archive")]
 pub mod archive;
+#[cfg(feature = "lichess-game-parser")]
+pub mod games;
+#[cfg(feature = "lichess-puzzle-parser")]
+pub mod opening;
+#[cfg(feature = "lichess-puzzle-parser")]
 pub mod parser;
+#[cfg(feature = "lichess-puzzle-parser")]
 pub mod puzzle;
+#[cfg(feature = "lichess-puzzle-parser")]
 pub mod themes;
+#[cfg(feature = "lichess-puzzle-parser")]
+pub mod training;
@@ -1,91 +1,626 @@
 use crate::core::puzzle::Puzzle;
+use crate::lichess::archive::index::LichessPuzzleIndex;
+use crate::lichess::archive::query::PuzzleQuery;
 use crate::lichess::parser::LichessPuzzleEntry;
 use crate::lichess::puzzle::LichessPuzzle;
-use crate::prelude::ChessMove;
+use crate::lichess::themes::LichessPuzzleTheme;
 use bit_codec::{BitDecode, BitReader, BitWriter};
+use std::io::{Error, ErrorKind};
 
-#[derive(Clone, Eq, PartialEq, Hash)]
+pub mod index;
+pub mod query;
+
+/// Amount of puzzles per zstd frame
+pub const CHUNK_SIZE: usize = 1024;
+const COMPRESSION_LEVEL: i32 = 19;
+const MAGIC: &[u8; 4] = b"GCPA";
+/// Version 1 archives are a single zstd stream of puzzles without the metadata
+pub const FORMAT_VERSION: u16 = 2;
+const ZSTD_MAGIC: &[u8; 4] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Lichess puzzles stored in independently compressed chunks with an index for random access.
+///
+/// Layout: the magic bytes `GCPA`, the format version as u16 LE, the length of the compressed index as u32 LE,
+/// the zstd compressed index, then the chunk frames.
+/// With serde the archive is stored as these bytes, so archives serialized before the format was versioned still load.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct LichessPuzzleArchive(Vec<u8>);
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Vec<u8>", into = "Vec<u8>")
+)]
+pub struct LichessPuzzleArchive {
+    bytes: Vec<u8>,
+    index: LichessPuzzleIndex,
+    version: u16,
+    data_start: usize,
+}
 
 impl LichessPuzzleArchive {
     pub fn try_from_iter<I>(iter: I) -> std::io::Result<Self>
     where
         I: IntoIterator<Item = std::io::Result<LichessPuzzleEntry>>,
     {
-        let mut buffer = Vec::new();
-        {
-            let encoder = zstd::Encoder::new(&mut buffer, 22)?;
-            let mut writer = BitWriter::new(encoder);
+        let puzzles = iter
+            .into_iter()
+            .filter_map(|entry| entry.and_then(puzzle_from_entry).transpose());
+        Self::try_from_puzzles(puzzles)
+    }
 
-            for entry in iter {
-                let entry = entry?;
-                let themes = entry
-                    .themes
-                    .iter()
-                    .map(|t| {
-                        t.parse()
-                            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
-                    })
-                    .collect::<Result<Vec<_>, _>>()?;
-
-                let mut moves = entry
-                    .moves
-                    .into_iter()
-                    .map(|m| {
-                        m.get_move(&entry.pos).ok_or_else(|| {
-                            std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid move")
-                        })
-                    })
-                    .collect::<Result<Vec<ChessMove>, _>>()?;
-
-                let Some(first_move) = moves.pop() else {
-                    continue;
-                };
-
-                let puzzle = LichessPuzzle {
-                    id: entry.id,
-                    puzzle: Puzzle::new(entry.pos, first_move, moves),
-                    themes,
-                    rating: entry.rating as u16,
-                    rating_deviation: entry.rating_deviation as u16,
-                    times_played: entry.times_played as u32,
-                };
-                writer.write(&puzzle)?;
+    /// Builds the archive from already converted puzzles, e.g. mined from own games
+    pub fn try_from_puzzles<I>(puzzles: I) -> std::io::Result<Self>
+    where
+        I: IntoIterator<Item = std::io::Result<LichessPuzzle>>,
+    {
+        let mut index = LichessPuzzleIndex::default();
+        let mut data = Vec::new();
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+
+        for puzzle in puzzles {
+            let puzzle = puzzle?;
+            index.insert(&puzzle);
+            chunk.push(puzzle);
+            if chunk.len() == CHUNK_SIZE {
+                write_chunk(&mut data, &mut index, &chunk)?;
+                chunk.clear();
             }
+        }
+        if !chunk.is_empty() {
+            write_chunk(&mut data, &mut index, &chunk)?;
+        }
+        index.finish(data.len());
 
+        let mut index_bytes = Vec::new();
+        {
+            let encoder = zstd::Encoder::new(&mut index_bytes, COMPRESSION_LEVEL)?;
+            let mut writer = BitWriter::new(encoder);
+            writer.write(&index)?;
             writer.flush()?;
             writer.into_inner().finish()?;
         }
 
-        Ok(Self(buffer))
+        let mut bytes = Vec::with_capacity(10 + index_bytes.len() + data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(index_bytes.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&index_bytes);
+        let data_start = bytes.len();
+        bytes.extend_from_slice(&data);
+
+        Ok(Self {
+            bytes,
+            index,
+            version: FORMAT_VERSION,
+            data_start,
+        })
+    }
+
+    /// Loads an archive previously written with [`LichessPuzzleArchive::as_bytes`], only the index is decoded.
+    /// Version 1 archives are converted to the current format, which decodes all of their puzzles.
+    pub fn from_bytes(bytes: Vec<u8>) -> std::io::Result<Self> {
+        if bytes.starts_with(ZSTD_MAGIC) {
+            return Self::from_version_1(&bytes);
+        }
+        let version = bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Unknown archive format"))?
+            .get(..2)
+            .map(|v| u16::from_le_bytes([v[0], v[1]]))
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Missing version"))?;
+        if version != FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Unsupported archive version {version}"),
+            ));
+        }
+
+        let header_len = 6;
+        let index_start = header_len + 4;
+        let index_len = bytes
+            .get(header_len..index_start)
+            .map(|len| u32::from_le_bytes(len.try_into().unwrap()) as usize)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Missing index length"))?;
+        let data_start = index_start + index_len;
+        let index_bytes = bytes
+            .get(index_start..data_start)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Truncated index"))?;
+        let index =
+            LichessPuzzleIndex::decode(&mut BitReader::new(zstd::Decoder::new(index_bytes)?))?;
+
+        Ok(Self {
+            bytes,
+            index,
+            version,
+            data_start,
+        })
+    }
+
+    fn from_version_1(bytes: &[u8]) -> std::io::Result<Self> {
+        let mut reader = BitReader::new(zstd::Decoder::new(bytes)?);
+        let puzzles = std::iter::from_fn(|| match LegacyLichessPuzzle::decode(&mut reader) {
+            Ok(puzzle) => Some(Ok(LichessPuzzle::from(puzzle))),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+            Err(e) => Some(Err(e)),
+        });
+        let mut archive = Self::try_from_puzzles(puzzles)?;
+        archive.version = 1;
+        Ok(archive)
     }
 
+    /// Iterates over all puzzles in archive order, the first chunk is decoded right away
     pub fn iter(&self) -> std::io::Result<LichessPuzzleIter<'_>> {
-        let decoder = zstd::Decoder::new(self.0.as_slice())?;
+        let buffer = match self.index.chunk_count() {
+            0 => Vec::new(),
+            _ => self.decode_chunk(0, CHUNK_SIZE)?,
+        };
         Ok(LichessPuzzleIter {
-            reader: BitReader::new(decoder),
+            archive: self,
+            chunk: 1,
+            buffer: buffer.into_iter(),
         })
     }
 
+    /// The puzzle at the given position of the archive, only its chunk is decompressed
+    pub fn get(&self, index: usize) -> std::io::Result<Option<LichessPuzzle>> {
+        if index >= self.len() {
+            return Ok(None);
+        }
+        let mut puzzles = self.decode_chunk(index / CHUNK_SIZE, index % CHUNK_SIZE + 1)?;
+        Ok(puzzles.pop())
+    }
+
+    pub fn get_by_id(&self, id: &str) -> std::io::Result<Option<LichessPuzzle>> {
+        for index in self.index.by_id(id) {
+            if let Some(puzzle) = self.get(index as usize)?
+                && puzzle.id == id
+            {
+                return Ok(Some(puzzle));
+            }
+        }
+        Ok(None)
+    }
+
+    /// All puzzles matching the query, in archive order
+    pub fn query(&self, query: &PuzzleQuery) -> std::io::Result<Vec<LichessPuzzle>> {
+        let mut puzzles = Vec::new();
+        for chunk in self.candidate_chunks(self.candidates(query).as_deref()) {
+            puzzles.extend(self.query_chunk(chunk, query)?);
+        }
+        Ok(puzzles)
+    }
+
+    /// Like [`LichessPuzzleArchive::query`], but decodes the chunks in parallel
+    #[cfg(feature = "rayon")]
+    pub fn par_query(&self, query: &PuzzleQuery) -> std::io::Result<Vec<LichessPuzzle>> {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+        let chunks = self
+            .candidate_chunks(self.candidates(query).as_deref())
+            .into_par_iter()
+            .map(|chunk| self.query_chunk(chunk, query))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    /// A random puzzle matching the query, the seed determines the pick
+    pub fn random_seeded(
+        &self,
+        query: &PuzzleQuery,
+        seed: u64,
+    ) -> std::io::Result<Option<LichessPuzzle>> {
+        let candidates = self.candidates(query);
+        let chunks = self.candidate_chunks(candidates.as_deref());
+        if chunks.is_empty() {
+            return Ok(None);
+        }
+
+        // The first chunk is picked through the candidates, so chunks with more candidates are picked more often
+        let pick = mix(seed) as usize;
+        let first = match &candidates {
+            Some(candidates) => candidates[pick % candidates.len()] as usize / CHUNK_SIZE,
+            None => chunks[pick % chunks.len()],
+        };
+        let start = chunks.partition_point(|&chunk| chunk < first);
+
+        // The index is coarser than the query, so every chunk is decoded once until a puzzle matches
+        for i in 0..chunks.len() {
+            let mut puzzles = self.query_chunk(chunks[(start + i) % chunks.len()], query)?;
+            if !puzzles.is_empty() {
+                let index = mix(seed ^ pick as u64) as usize % puzzles.len();
+                return Ok(Some(puzzles.swap_remove(index)));
+            }
+        }
+        Ok(None)
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn random(&self, query: &PuzzleQuery) -> std::io::Result<Option<LichessPuzzle>> {
+        use rand::RngExt;
+        self.random_seeded(query, rand::rng().random())
+    }
+
+    /// Puzzles that may match the query according to the index, None if the index can't narrow it down
+    fn candidates(&self, query: &PuzzleQuery) -> Option<Vec<u32>> {
+        let mut candidates = query
+            .rating()
+            .map(|rating| self.index.by_rating(rating.clone()));
+        for &theme in query.themes() {
+            let postings = self.index.by_theme(theme);
+            candidates = Some(match candidates {
+                Some(candidates) => intersect(&candidates, postings),
+                None => postings.to_vec(),
+            });
+        }
+        candidates
+    }
+
+    fn candidate_chunks(&self, candidates: Option<&[u32]>) -> Vec<usize> {
+        match candidates {
+            Some(candidates) => {
+                let mut chunks: Vec<usize> = candidates
+                    .iter()
+                    .map(|&index| index as usize / CHUNK_SIZE)
+                    .collect();
+                chunks.dedup();
+                chunks
+            }
+            None => (0..self.index.chunk_count()).collect(),
+        }
+    }
+
+    fn query_chunk(
+        &self,
+        chunk: usize,
+        query: &PuzzleQuery,
+    ) -> std::io::Result<Vec<LichessPuzzle>> {
+        let mut puzzles = self.decode_chunk(chunk, CHUNK_SIZE)?;
+        puzzles.retain(|puzzle| query.matches(puzzle));
+        Ok(puzzles)
+    }
+
+    /// Decodes up to `take` puzzles from the start of the given chunk
+    fn decode_chunk(&self, chunk: usize, take: usize) -> std::io::Result<Vec<LichessPuzzle>> {
+        let range = self
+            .index
+            .chunk_range(chunk)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Chunk out of range"))?;
+        let frame = self
+            .bytes
+            .get(self.data_start + range.start..self.data_start + range.end)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Truncated chunk"))?;
+
+        let count = CHUNK_SIZE.min(self.len() - chunk * CHUNK_SIZE).min(take);
+        let mut reader = BitReader::new(zstd::Decoder::new(frame)?);
+        (0..count)
+            .map(|_| LichessPuzzle::decode(&mut reader))
+            .collect()
+    }
+}
+
+// Accessors
+impl LichessPuzzleArchive {
     pub fn as_bytes(&self) -> &[u8] {
-        &self.0
+        &self.bytes
+    }
+
+    /// The format version the archive was written with, [`LichessPuzzleArchive::as_bytes`] is always the current one
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn index(&self) -> &LichessPuzzleIndex {
+        &self.index
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+impl TryFrom<Vec<u8>> for LichessPuzzleArchive {
+    type Error = Error;
+
+    fn try_from(bytes: Vec<u8>) -> std::io::Result<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl From<LichessPuzzleArchive> for Vec<u8> {
+    fn from(archive: LichessPuzzleArchive) -> Self {
+        archive.bytes
     }
 }
 
 pub struct LichessPuzzleIter<'a> {
-    reader: BitReader<zstd::Decoder<'a, std::io::BufReader<&'a [u8]>>>,
+    archive: &'a LichessPuzzleArchive,
+    chunk: usize,
+    buffer: std::vec::IntoIter<LichessPuzzle>,
 }
 
 impl<'a> Iterator for LichessPuzzleIter<'a> {
     type Item = std::io::Result<LichessPuzzle>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match LichessPuzzle::decode(&mut self.reader) {
-            Ok(puzzle) => Some(Ok(puzzle)),
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => None,
-            Err(e) => Some(Err(e)),
+        loop {
+            if let Some(puzzle) = self.buffer.next() {
+                return Some(Ok(puzzle));
+            }
+            if self.chunk >= self.archive.index.chunk_count() {
+                return None;
+            }
+            let chunk = self.chunk;
+            self.chunk += 1;
+            match self.archive.decode_chunk(chunk, CHUNK_SIZE) {
+                Ok(puzzles) => self.buffer = puzzles.into_iter(),
+                Err(e) => {
+                    self.chunk = self.archive.index.chunk_count();
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// The first move of a Lichess puzzle is the opponent's move leading into it
+fn puzzle_from_entry(entry: LichessPuzzleEntry) -> std::io::Result<Option<LichessPuzzle>> {
+    let mut themes = Vec::new();
+    let mut unknown_themes = Vec::new();
+    for theme in &entry.themes {
+        match theme.parse() {
+            Ok(theme) => themes.push(theme),
+            Err(_) => unknown_themes.push(theme.clone()),
+        }
+    }
+
+    let mut pos = entry.pos;
+    let mut moves = Vec::with_capacity(entry.moves.len());
+    for naive in &entry.moves {
+        let mv = naive
+            .get_move(&pos)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid move"))?;
+        pos = pos.make_move(mv);
+        moves.push(mv);
+    }
+
+    if moves.is_empty() {
+        return Ok(None);
+    }
+    let first_move = moves.remove(0);
+
+    Ok(Some(LichessPuzzle {
+        puzzle: Puzzle::new(entry.pos, first_move, moves),
+        themes,
+        rating: entry.rating as u16,
+        rating_deviation: entry.rating_deviation as u16,
+        times_played: entry.times_played as u32,
+        unknown_themes,
+        popularity: entry.popularity.clamp(-100, 100) as i8,
+        game_id: entry.game_id().to_string(),
+        game_ply: entry.game_ply().unwrap_or_default(),
+        opening_tags: entry
+            .opening_tags
+            .iter()
+            .map(|tag| tag.parse().unwrap_or_else(|e| match e {}))
+            .collect(),
+        id: entry.id,
+    }))
+}
+
+/// The puzzle layout of version 1 archives, encoded back to back in one zstd stream
+#[derive(bit_codec::BitEncode, bit_codec::BitDecode)]
+struct LegacyLichessPuzzle {
+    id: String,
+    puzzle: Puzzle,
+    themes: Vec<LichessPuzzleTheme>,
+    #[bits(13)]
+    rating: u16,
+    #[bits(9)]
+    rating_deviation: u16,
+    times_played: u32,
+}
+
+impl From<LegacyLichessPuzzle> for LichessPuzzle {
+    fn from(legacy: LegacyLichessPuzzle) -> Self {
+        Self {
+            id: legacy.id,
+            puzzle: legacy.puzzle,
+            themes: legacy.themes,
+            rating: legacy.rating,
+            rating_deviation: legacy.rating_deviation,
+            times_played: legacy.times_played,
+            unknown_themes: vec![],
+            popularity: 0,
+            game_id: String::new(),
+            game_ply: 0,
+            opening_tags: vec![],
         }
     }
 }
+
+fn write_chunk(
+    data: &mut Vec<u8>,
+    index: &mut LichessPuzzleIndex,
+    chunk: &[LichessPuzzle],
+) -> std::io::Result<()> {
+    index.push_chunk(data.len());
+    let encoder = zstd::Encoder::new(data, COMPRESSION_LEVEL)?;
+    let mut writer = BitWriter::new(encoder);
+    for puzzle in chunk {
+        writer.write(puzzle)?;
+    }
+    writer.flush()?;
+    writer.into_inner().finish()?;
+    Ok(())
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::new();
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                result.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    result
+}
+
+/// SplitMix64, spreads nearby seeds over the candidates
+fn mix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::position::Position;
+    use crate::lichess::archive::LegacyLichessPuzzle;
+    use crate::lichess::archive::query::PuzzleQuery;
+    use crate::lichess::archive::{CHUNK_SIZE, LichessPuzzleArchive};
+    use crate::lichess::opening::LichessOpeningTag;
+    use crate::lichess::parser::LichessPuzzleEntry;
+    use crate::lichess::themes::LichessPuzzleTheme;
+    use crate::prelude::Color;
+    use bit_codec::BitWriter;
+
+    fn entry(i: usize) -> std::io::Result<LichessPuzzleEntry> {
+        let themes = if i.is_multiple_of(2) {
+            "fork short"
+        } else {
+            "mateIn1 someFutureTheme"
+        };
+        Ok(LichessPuzzleEntry {
+            id: format!("p{i:05}"),
+            pos: Position::default(),
+            moves: ["e2e4", "e7e5", "g1f3"]
+                .iter()
+                .map(|m| m.parse().unwrap())
+                .collect(),
+            rating: 400 + i * 3,
+            rating_deviation: 75,
+            popularity: 90,
+            times_played: i,
+            themes: themes.split_whitespace().map(String::from).collect(),
+            game_url: format!("https://lichess.org/game{i}/black#{}", i + 10),
+            opening_tags: vec![
+                "Sicilian_Defense".into(),
+                "Sicilian_Defense_Najdorf_Variation".into(),
+            ],
+        })
+    }
+
+    #[test]
+    fn test_random_access() {
+        let count = CHUNK_SIZE + 100;
+        let archive = LichessPuzzleArchive::try_from_iter((0..count).map(entry)).unwrap();
+        let archive = LichessPuzzleArchive::from_bytes(archive.as_bytes().to_vec()).unwrap();
+        assert_eq!(archive.len(), count);
+        assert_eq!(archive.iter().unwrap().count(), count);
+
+        let puzzle = archive.get_by_id("p01050").unwrap().unwrap();
+        assert_eq!(puzzle.rating, 400 + 1050 * 3);
+        assert_eq!(puzzle.puzzle.last_move().to_uci(), "e2e4");
+        assert_eq!(puzzle.puzzle.solution().len(), 2);
+        assert_eq!(puzzle.game_id, "game1050");
+        assert_eq!(puzzle.game_ply, 1060);
+        assert_eq!(puzzle.popularity, 90);
+        assert_eq!(puzzle.opening_tags[0], LichessOpeningTag::SicilianDefense);
+        assert!(!puzzle.opening_tags[1].is_family());
+        let odd = archive.get(1051).unwrap().unwrap();
+        assert_eq!(odd.unknown_themes, ["someFutureTheme"]);
+        assert!(archive.get_by_id("missing").unwrap().is_none());
+        assert!(archive.get(count).unwrap().is_none());
+
+        let query = PuzzleQuery::default()
+            .with_rating(1000..=1200)
+            .with_theme(LichessPuzzleTheme::MateIn1);
+        for seed in 0..20 {
+            let puzzle = archive.random_seeded(&query, seed).unwrap().unwrap();
+            assert!((1000..=1200).contains(&puzzle.rating));
+            assert!(puzzle.themes.contains(&LichessPuzzleTheme::MateIn1));
+        }
+        assert!(
+            archive
+                .random_seeded(&PuzzleQuery::default().with_rating(5000..=6000), 0)
+                .unwrap()
+                .is_none()
+        );
+
+        // Queries the index can't narrow down decode each chunk at most once
+        let black = PuzzleQuery::default().with_side_to_move(Color::Black);
+        assert!(archive.random_seeded(&black, 7).unwrap().is_some());
+        let white = PuzzleQuery::default().with_side_to_move(Color::White);
+        assert!(archive.random_seeded(&white, 7).unwrap().is_none());
+
+        let again = LichessPuzzleArchive::try_from_iter((0..count).map(entry)).unwrap();
+        assert_eq!(again.as_bytes(), archive.as_bytes());
+    }
+
+    #[test]
+    fn test_query() {
+        let archive = LichessPuzzleArchive::try_from_iter((0..300).map(entry)).unwrap();
+        let query = PuzzleQuery::default()
+            .with_rating(500..=700)
+            .with_theme(LichessPuzzleTheme::Fork)
+            .without_theme(LichessPuzzleTheme::MateIn1)
+            .with_side_to_move(Color::Black)
+            .with_solution_length(2..=2);
+        let puzzles = archive.query(&query).unwrap();
+        assert_eq!(puzzles.len(), 34);
+        assert!(puzzles.iter().all(|p| query.matches(p)));
+        #[cfg(feature = "rayon")]
+        assert_eq!(archive.par_query(&query).unwrap(), puzzles);
+
+        let white = PuzzleQuery::default().with_side_to_move(Color::White);
+        assert!(archive.query(&white).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reads_version_1() {
+        let current = LichessPuzzleArchive::try_from_iter((0..10).map(entry)).unwrap();
+        let puzzles: Vec<_> = current.iter().unwrap().map(Result::unwrap).collect();
+
+        // Version 1 archives are one zstd stream of puzzles without metadata or header
+        let mut bytes = Vec::new();
+        let mut writer = BitWriter::new(zstd::Encoder::new(&mut bytes, 22).unwrap());
+        for puzzle in &puzzles {
+            let legacy = LegacyLichessPuzzle {
+                id: puzzle.id.clone(),
+                puzzle: puzzle.puzzle.clone(),
+                themes: puzzle.themes.clone(),
+                rating: puzzle.rating,
+                rating_deviation: puzzle.rating_deviation,
+                times_played: puzzle.times_played,
+            };
+            writer.write(&legacy).unwrap();
+        }
+        writer.flush().unwrap();
+        writer.into_inner().finish().unwrap();
+
+        let legacy = LichessPuzzleArchive::from_bytes(bytes).unwrap();
+        assert_eq!(legacy.version(), 1);
+        assert_eq!(current.version(), 2);
+        assert_eq!(legacy.len(), puzzles.len());
+
+        let puzzle = legacy.get_by_id("p00003").unwrap().unwrap();
+        assert_eq!(puzzle.puzzle, puzzles[3].puzzle);
+        assert_eq!(puzzle.themes, puzzles[3].themes);
+        assert!(puzzle.game_id.is_empty());
+
+        let converted = LichessPuzzleArchive::from_bytes(legacy.as_bytes().to_vec()).unwrap();
+        assert_eq!(converted.version(), 2);
+        assert_eq!(converted.get(3).unwrap(), Some(puzzle));
+        assert!(LichessPuzzleArchive::from_bytes(vec![1, 2, 3, 4]).is_err());
+    }
+}
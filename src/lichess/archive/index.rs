@@ -0,0 +1,105 @@
+use crate::lichess::puzzle::LichessPuzzle;
+use crate::lichess::themes::LichessPuzzleTheme;
+use std::collections::BTreeMap;
+use std::ops::{Range, RangeInclusive};
+
+/// Width of the rating buckets of the index
+pub const RATING_BUCKET_SIZE: u16 = 100;
+
+/// Lookup tables of a [`LichessPuzzleArchive`](super::LichessPuzzleArchive).
+///
+/// Puzzles are referenced by their position in the archive, all postings are sorted.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Hash, bit_codec::BitEncode, bit_codec::BitDecode,
+)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+pub struct LichessPuzzleIndex {
+    len: u32,
+    /// Byte offsets of the chunk frames, followed by the end of the last frame
+    chunk_offsets: Vec<u64>,
+    /// FNV-1a hashes of the puzzle ids, sorted by hash
+    ids: Vec<(u64, u32)>,
+    rating_buckets: BTreeMap<u16, Vec<u32>>,
+    themes: BTreeMap<LichessPuzzleTheme, Vec<u32>>,
+}
+
+impl LichessPuzzleIndex {
+    pub(super) fn insert(&mut self, puzzle: &LichessPuzzle) {
+        let index = self.len;
+        self.ids.push((id_hash(&puzzle.id), index));
+        self.rating_buckets
+            .entry(puzzle.rating / RATING_BUCKET_SIZE)
+            .or_default()
+            .push(index);
+        for theme in &puzzle.themes {
+            let postings = self.themes.entry(*theme).or_default();
+            if postings.last() != Some(&index) {
+                postings.push(index);
+            }
+        }
+        self.len += 1;
+    }
+
+    pub(super) fn push_chunk(&mut self, offset: usize) {
+        self.chunk_offsets.push(offset as u64);
+    }
+
+    pub(super) fn finish(&mut self, end: usize) {
+        self.chunk_offsets.push(end as u64);
+        self.ids.sort_unstable();
+    }
+
+    /// Byte range of the given chunk frame, relative to the start of the chunk data
+    pub(super) fn chunk_range(&self, chunk: usize) -> Option<Range<usize>> {
+        let start = *self.chunk_offsets.get(chunk)? as usize;
+        let end = *self.chunk_offsets.get(chunk + 1)? as usize;
+        Some(start..end)
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_offsets.len().saturating_sub(1)
+    }
+
+    /// Puzzles whose id shares the hash of the given id
+    pub fn by_id(&self, id: &str) -> impl Iterator<Item = u32> + '_ {
+        let hash = id_hash(id);
+        let start = self.ids.partition_point(|&(h, _)| h < hash);
+        self.ids[start..]
+            .iter()
+            .take_while(move |&&(h, _)| h == hash)
+            .map(|&(_, index)| index)
+    }
+
+    /// Puzzles in all buckets overlapping the rating range, their exact rating may lie outside of it
+    pub fn by_rating(&self, rating: RangeInclusive<u16>) -> Vec<u32> {
+        let buckets = rating.start() / RATING_BUCKET_SIZE..=rating.end() / RATING_BUCKET_SIZE;
+        let mut postings: Vec<u32> = self
+            .rating_buckets
+            .range(buckets)
+            .flat_map(|(_, postings)| postings.iter().copied())
+            .collect();
+        postings.sort_unstable();
+        postings
+    }
+
+    pub fn by_theme(&self, theme: LichessPuzzleTheme) -> &[u32] {
+        self.themes.get(&theme).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+// Accessors
+impl LichessPuzzleIndex {
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+fn id_hash(id: &str) -> u64 {
+    id.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
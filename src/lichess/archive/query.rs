@@ -0,0 +1,96 @@
+use crate::lichess::puzzle::LichessPuzzle;
+use crate::lichess::themes::LichessPuzzleTheme;
+use crate::prelude::Color;
+use std::ops::RangeInclusive;
+
+/// Filters over the puzzles of a [`LichessPuzzleArchive`](super::LichessPuzzleArchive), all filters have to match.
+///
+/// Rating and required themes are resolved through the index, the others by decoding the candidate chunks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PuzzleQuery {
+    rating: Option<RangeInclusive<u16>>,
+    rating_deviation: Option<RangeInclusive<u16>>,
+    times_played: Option<RangeInclusive<u32>>,
+    themes: Vec<LichessPuzzleTheme>,
+    excluded_themes: Vec<LichessPuzzleTheme>,
+    side_to_move: Option<Color>,
+    solution_length: Option<RangeInclusive<usize>>,
+    piece_count: Option<RangeInclusive<u8>>,
+}
+
+impl PuzzleQuery {
+    pub fn with_rating(mut self, rating: RangeInclusive<u16>) -> Self {
+        self.rating = Some(rating);
+        self
+    }
+
+    pub fn with_rating_deviation(mut self, rating_deviation: RangeInclusive<u16>) -> Self {
+        self.rating_deviation = Some(rating_deviation);
+        self
+    }
+
+    pub fn with_times_played(mut self, times_played: RangeInclusive<u32>) -> Self {
+        self.times_played = Some(times_played);
+        self
+    }
+
+    /// Requires the theme, can be called multiple times
+    pub fn with_theme(mut self, theme: LichessPuzzleTheme) -> Self {
+        self.themes.push(theme);
+        self
+    }
+
+    /// Excludes the theme, can be called multiple times
+    pub fn without_theme(mut self, theme: LichessPuzzleTheme) -> Self {
+        self.excluded_themes.push(theme);
+        self
+    }
+
+    /// The side solving the puzzle
+    pub fn with_side_to_move(mut self, color: Color) -> Self {
+        self.side_to_move = Some(color);
+        self
+    }
+
+    /// Amount of plies of the solution, the opponent's replies included
+    pub fn with_solution_length(mut self, plies: RangeInclusive<usize>) -> Self {
+        self.solution_length = Some(plies);
+        self
+    }
+
+    /// Amount of pieces on the board, kings included
+    pub fn with_piece_count(mut self, pieces: RangeInclusive<u8>) -> Self {
+        self.piece_count = Some(pieces);
+        self
+    }
+
+    pub fn matches(&self, puzzle: &LichessPuzzle) -> bool {
+        let position = puzzle.puzzle.position();
+        contains(&self.rating, puzzle.rating)
+            && contains(&self.rating_deviation, puzzle.rating_deviation)
+            && contains(&self.times_played, puzzle.times_played)
+            && self.themes.iter().all(|t| puzzle.themes.contains(t))
+            && !self.excluded_themes.iter().any(|t| puzzle.themes.contains(t))
+            // The position is the one before the opponent's move leading into the puzzle
+            && self
+                .side_to_move
+                .is_none_or(|color| position.side_to_move == color.opposite())
+            && contains(&self.solution_length, puzzle.puzzle.solution().len())
+            && contains(&self.piece_count, position.board.total_piece_count())
+    }
+}
+
+// Accessors
+impl PuzzleQuery {
+    pub fn rating(&self) -> Option<&RangeInclusive<u16>> {
+        self.rating.as_ref()
+    }
+
+    pub fn themes(&self) -> &[LichessPuzzleTheme] {
+        &self.themes
+    }
+}
+
+fn contains<T: PartialOrd>(range: &Option<RangeInclusive<T>>, value: T) -> bool {
+    range.as_ref().is_none_or(|range| range.contains(&value))
+}
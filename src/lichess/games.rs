@@ -0,0 +1,572 @@
+use crate::engine::score::Score;
+use crate::error::PgnResult;
+use crate::game::Game;
+use crate::game::outcome::{DecisiveReason, DrawReason, GameOutcome};
+use crate::notation::pgn::{PgnGame, parse_pgn};
+use crate::prelude::Color;
+use crate::session::SessionRecord;
+use crate::session::clock::{ChessClockConfig, MoveTime};
+use crate::session::config::{SessionConfig, StartingPosition, TimeControl};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::ops::RangeInclusive;
+use zstd::Decoder;
+
+/// Streams the games of a Lichess database dump like `lichess_db_standard_rated_2013-01.pgn.zst`.
+///
+/// Games are filtered by their tags before their moves are parsed, so skipped games are cheap.
+pub struct LichessGameParser<R: Read> {
+    reader: BufReader<Decoder<'static, BufReader<R>>>,
+    filter: LichessGameFilter,
+    /// A tag line read while looking for the end of the previous game
+    pending: Option<String>,
+}
+
+impl<R: Read> LichessGameParser<R> {
+    pub fn new(reader: R) -> Result<Self, std::io::Error> {
+        let decoder = Decoder::new(reader)?;
+        Ok(Self {
+            reader: BufReader::new(decoder),
+            filter: LichessGameFilter::default(),
+            pending: None,
+        })
+    }
+
+    pub fn with_filter(mut self, filter: LichessGameFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// None at the end of the dump
+    fn read_game(&mut self) -> std::io::Result<Option<RawLichessGame>> {
+        let mut tags = Vec::new();
+        let mut text = String::new();
+        let mut in_movetext = false;
+
+        loop {
+            let line = match self.pending.take() {
+                Some(line) => line,
+                None => {
+                    let mut line = String::new();
+                    if self.reader.read_line(&mut line)? == 0 {
+                        break;
+                    }
+                    line
+                }
+            };
+
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && !trimmed.starts_with("[%") {
+                if in_movetext {
+                    self.pending = Some(line);
+                    break;
+                }
+                tags.extend(parse_tag(trimmed));
+            } else if trimmed.is_empty() {
+                if in_movetext {
+                    break;
+                }
+            } else {
+                in_movetext = true;
+            }
+            text.push_str(&line);
+        }
+
+        if text.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(RawLichessGame { tags, text }))
+    }
+}
+
+/// The tags and the full text of a game, its moves aren't parsed yet
+struct RawLichessGame {
+    tags: Vec<(String, String)>,
+    text: String,
+}
+
+impl LichessGameParser<File> {
+    pub fn from_path(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(path)?;
+        Ok(Self::new(file)?)
+    }
+}
+
+impl<R: Read> Iterator for LichessGameParser<R> {
+    type Item = std::io::Result<LichessGame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let RawLichessGame { tags, text } = match self.read_game() {
+                Ok(Some(game)) => game,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            if !self.filter.accepts(
+                elo(&tags, "WhiteElo"),
+                elo(&tags, "BlackElo"),
+                &time_control(&tags),
+            ) {
+                continue;
+            }
+
+            let game = parse_pgn(&text)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+                .and_then(|games| {
+                    games.into_iter().next().ok_or_else(|| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, "Empty game")
+                    })
+                });
+            return Some(game.map(LichessGame::from));
+        }
+    }
+}
+
+/// Lichess' speed categories, based on the estimated duration of a game
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "strum",
+    derive(strum::EnumIter, strum::EnumIs, strum::EnumCount)
+)]
+pub enum LichessSpeed {
+    UltraBullet,
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Correspondence,
+}
+
+impl LichessSpeed {
+    /// The estimated duration is the base time plus 40 times the increment
+    pub fn from_time_control(time_control: &TimeControl) -> Self {
+        let TimeControl::Clock(config) = time_control else {
+            return Self::Correspondence;
+        };
+        match (config.white_ms + 40 * config.white_inc_ms) / 1000 {
+            0..30 => Self::UltraBullet,
+            30..180 => Self::Bullet,
+            180..480 => Self::Blitz,
+            480..1500 => Self::Rapid,
+            _ => Self::Classical,
+        }
+    }
+}
+
+/// Filters for a [`LichessGameParser`], all filters have to match
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LichessGameFilter {
+    rating: Option<RangeInclusive<u16>>,
+    speeds: Vec<LichessSpeed>,
+    time_controls: Vec<TimeControl>,
+}
+
+impl LichessGameFilter {
+    /// Both players have to be rated within the range
+    pub fn with_rating(mut self, rating: RangeInclusive<u16>) -> Self {
+        self.rating = Some(rating);
+        self
+    }
+
+    /// Allows the speed, can be called multiple times
+    pub fn with_speed(mut self, speed: LichessSpeed) -> Self {
+        self.speeds.push(speed);
+        self
+    }
+
+    /// Allows the exact time control, can be called multiple times
+    pub fn with_time_control(mut self, time_control: TimeControl) -> Self {
+        self.time_controls.push(time_control);
+        self
+    }
+
+    pub fn matches(&self, game: &LichessGame) -> bool {
+        self.accepts(game.white_elo, game.black_elo, &game.time_control)
+    }
+
+    fn accepts(
+        &self,
+        white_elo: Option<u16>,
+        black_elo: Option<u16>,
+        time_control: &TimeControl,
+    ) -> bool {
+        let rated = |elo: Option<u16>| {
+            self.rating
+                .as_ref()
+                .is_none_or(|range| elo.is_some_and(|elo| range.contains(&elo)))
+        };
+        rated(white_elo)
+            && rated(black_elo)
+            && (self.speeds.is_empty()
+                || self
+                    .speeds
+                    .contains(&LichessSpeed::from_time_control(time_control)))
+            && (self.time_controls.is_empty() || self.time_controls.contains(time_control))
+    }
+}
+
+/// A game of a Lichess database dump with its tags and move annotations
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LichessGame {
+    pub pgn: PgnGame,
+    pub white_elo: Option<u16>,
+    pub black_elo: Option<u16>,
+    pub time_control: TimeControl,
+    /// The Termination tag, e.g. `Normal`, `Time forfeit` or `Abandoned`
+    pub termination: Option<String>,
+    /// Milliseconds since UNIX epoch, from the UTCDate and UTCTime tags
+    pub started_at_ms: Option<u64>,
+    /// Remaining time of the moving side after each move, from `[%clk]`
+    pub clocks: Vec<Option<u64>>,
+    /// Evaluation after each move, from `[%eval]`, only present for analysed games
+    pub evals: Vec<Option<Score>>,
+}
+
+impl From<PgnGame> for LichessGame {
+    fn from(pgn: PgnGame) -> Self {
+        let tags = &pgn.headers.extra;
+        let started_at_ms = tag(tags, "UTCDate")
+            .zip(tag(tags, "UTCTime"))
+            .and_then(|(date, time)| unix_ms(date, time));
+        let clocks = pgn
+            .comments
+            .iter()
+            .map(|comment| command(comment, "clk").and_then(parse_clock))
+            .collect();
+        let evals = pgn
+            .comments
+            .iter()
+            .map(|comment| command(comment, "eval").and_then(parse_eval))
+            .collect();
+
+        Self {
+            white_elo: elo(tags, "WhiteElo"),
+            black_elo: elo(tags, "BlackElo"),
+            time_control: time_control(tags),
+            termination: tag(tags, "Termination").map(String::from),
+            started_at_ms,
+            clocks,
+            evals,
+            pgn,
+        }
+    }
+}
+
+impl LichessGame {
+    pub fn speed(&self) -> LichessSpeed {
+        LichessSpeed::from_time_control(&self.time_control)
+    }
+
+    /// The replayed game, ended like the Result and Termination tags describe
+    pub fn to_game(&self) -> PgnResult<Game> {
+        let mut game = self.pgn.to_game()?;
+        if !game.is_over()
+            && let Some(outcome) = self.outcome(&game)
+        {
+            game.force_outcome(outcome);
+        }
+        Ok(game)
+    }
+
+    /// A record that can be restored into a [`Session`](crate::prelude::Session).
+    ///
+    /// Move times are derived from the `[%clk]` annotations and count from the start of the game.
+    pub fn to_record(&self) -> PgnResult<SessionRecord> {
+        let game = self.to_game()?;
+        let starting_position = match &self.pgn.fen {
+            Some(fen) => StartingPosition::Fen(fen.clone()),
+            None => StartingPosition::Default,
+        };
+        let config = SessionConfig {
+            starting_position,
+            time_control: self.time_control.clone(),
+            pgn: self.pgn.headers.clone(),
+            ..Default::default()
+        };
+
+        let mut move_times = Vec::new();
+        let mut played_at_ms = self.started_at_ms.unwrap_or_default();
+        for ply in 0..self.pgn.moves.len() {
+            let think_ms = self.think_ms(ply);
+            played_at_ms += think_ms.unwrap_or_default();
+            move_times.push(MoveTime {
+                played_at_ms,
+                think_ms,
+                remaining_ms: self.clocks.get(ply).copied().flatten(),
+            });
+        }
+
+        Ok(SessionRecord::from_moves(
+            config,
+            self.pgn.moves.clone(),
+            move_times,
+            self.started_at_ms,
+            game.outcome(),
+        ))
+    }
+
+    /// How long the move took, the first move of each side doesn't run the clock on Lichess
+    fn think_ms(&self, ply: usize) -> Option<u64> {
+        let TimeControl::Clock(config) = &self.time_control else {
+            return None;
+        };
+        let remaining = self.clocks.get(ply).copied().flatten()?;
+        if ply < 2 {
+            return Some(0);
+        }
+        let before = self.clocks.get(ply - 2).copied().flatten()?;
+        // Clock times are rounded to seconds, the side still had time left when it moved
+        let think_ms = (before + config.white_inc_ms).saturating_sub(remaining);
+        Some(think_ms.min(before.saturating_sub(1)))
+    }
+
+    /// The outcome the tags describe for a game that didn't end on the board
+    fn outcome(&self, game: &Game) -> Option<GameOutcome> {
+        let termination = self.termination.as_deref();
+        let winner = match self.pgn.result.as_deref()? {
+            "1-0" => Color::White,
+            "0-1" => Color::Black,
+            "1/2-1/2" => {
+                let reason = match termination {
+                    Some("Time forfeit") => DrawReason::TimeoutVsInsufficient,
+                    Some("Rules infraction") => DrawReason::Adjudication,
+                    _ if game.repetition_count() >= 3 => DrawReason::ThreefoldRepetition,
+                    _ if game.position().half_moves >= 100 => DrawReason::FiftyMoveRule,
+                    _ => DrawReason::Agreement,
+                };
+                return Some(GameOutcome::Draw(reason));
+            }
+            _ => return None,
+        };
+        let reason = match termination {
+            Some("Time forfeit") => DecisiveReason::Timeout,
+            Some("Rules infraction") => DecisiveReason::Adjudication,
+            _ => DecisiveReason::Resignation,
+        };
+        Some(GameOutcome::Decisive { winner, reason })
+    }
+}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let (key, value) = line
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((key.to_string(), value.replace("\\\"", "\"")))
+}
+
+fn tag<'a>(tags: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value.as_str())
+}
+
+fn elo(tags: &[(String, String)], key: &str) -> Option<u16> {
+    tag(tags, key)?.parse().ok()
+}
+
+/// Lichess writes `300+3` for games with a clock and `-` for correspondence games
+fn time_control(tags: &[(String, String)]) -> TimeControl {
+    tag(tags, "TimeControl")
+        .and_then(|tag| tag.split_once('+'))
+        .and_then(|(base, increment)| {
+            Some((base.parse::<u64>().ok()?, increment.parse::<u64>().ok()?))
+        })
+        .map_or(TimeControl::Unlimited, |(base, increment)| {
+            TimeControl::Clock(ChessClockConfig::fischer(base * 1000, increment * 1000))
+        })
+}
+
+/// The argument of a command like `[%clk 0:03:00]`
+fn command<'a>(comment: &'a str, name: &str) -> Option<&'a str> {
+    let start = comment.find(&format!("[%{name} "))? + name.len() + 3;
+    let end = start + comment[start..].find(']')?;
+    Some(comment[start..end].trim())
+}
+
+/// Parses `h:mm:ss`, the seconds may have a fraction
+fn parse_clock(clock: &str) -> Option<u64> {
+    let mut parts = clock.rsplit(':');
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next().map_or(Some(0), |m| m.parse().ok())?;
+    let hours: u64 = parts.next().map_or(Some(0), |h| h.parse().ok())?;
+    Some((hours * 3600 + minutes * 60) * 1000 + (seconds * 1000.0).round() as u64)
+}
+
+/// Parses `0.35`, `-1.20`, `#3` or `#-3`, a trailing search depth like `0.35,20` is ignored
+fn parse_eval(eval: &str) -> Option<Score> {
+    let eval = eval.split(',').next()?;
+    if let Some(mate) = eval.strip_prefix('#') {
+        let moves: i32 = mate.parse().ok()?;
+        let winner = if moves < 0 {
+            Color::Black
+        } else {
+            Color::White
+        };
+        return Some(Score::Mate {
+            winner,
+            moves: moves.unsigned_abs(),
+        });
+    }
+    let pawns: f64 = eval.parse().ok()?;
+    Some(Score::Cp((pawns * 100.0).round() as i32))
+}
+
+/// Converts the `2013.01.01` and `00:00:35` of the UTCDate and UTCTime tags
+fn unix_ms(date: &str, time: &str) -> Option<u64> {
+    let mut date = date.split('.').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar, years start in March
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = u64::try_from(era * 146097 + day_of_era - 719468).ok()?;
+
+    Some((days * 86400 + hours * 3600 + minutes * 60 + seconds) * 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::score::Score;
+    use crate::game::outcome::{DecisiveReason, GameOutcome};
+    use crate::lichess::games::{LichessGameFilter, LichessGameParser, LichessSpeed};
+    use crate::prelude::Color;
+    use crate::session::clock::ChessClockConfig;
+    use crate::session::config::TimeControl;
+    use std::io::Cursor;
+
+    const DUMP: &str = r#"[Event "Rated Blitz game"]
+[Site "https://lichess.org/j1dkb5dw"]
+[White "BFG9k"]
+[Black "mamalak"]
+[Result "1-0"]
+[UTCDate "2013.01.01"]
+[UTCTime "00:00:35"]
+[WhiteElo "1639"]
+[BlackElo "1403"]
+[ECO "C00"]
+[Opening "French Defense: Normal Variation"]
+[TimeControl "180+2"]
+[Termination "Time forfeit"]
+
+1. e4 { [%eval 0.17] [%clk 0:03:00] } 1... e6 { [%eval 0.13] [%clk 0:03:00] } 2. d4 { [%eval 0.04] [%clk 0:02:58] } 2... b6 { [%eval #-3] [%clk 0:02:51] } 1-0
+
+[Event "Rated Bullet game"]
+[Site "https://lichess.org/a9tcp02g"]
+[White "Desmond_Wilson"]
+[Black "savinka59"]
+[Result "1-0"]
+[UTCDate "2013.01.01"]
+[UTCTime "00:01:12"]
+[WhiteElo "1226"]
+[BlackElo "1090"]
+[TimeControl "60+0"]
+[Termination "Normal"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+
+[Event "Rated Correspondence game"]
+[Site "https://lichess.org/b3tjt2lj"]
+[White "anon"]
+[Black "anon2"]
+[Result "1/2-1/2"]
+[WhiteElo "1500"]
+[BlackElo "1500"]
+[TimeControl "-"]
+[Termination "Normal"]
+
+1. d4 d5 1/2-1/2
+
+"#;
+
+    fn parser() -> LichessGameParser<Cursor<Vec<u8>>> {
+        let compressed = zstd::encode_all(DUMP.as_bytes(), 1).unwrap();
+        LichessGameParser::new(Cursor::new(compressed)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_dump() {
+        let games: Vec<_> = parser().map(Result::unwrap).collect();
+        assert_eq!(games.len(), 3);
+
+        let blitz = &games[0];
+        assert_eq!(blitz.white_elo, Some(1639));
+        assert_eq!(blitz.pgn.headers.eco.as_deref(), Some("C00"));
+        assert_eq!(blitz.speed(), LichessSpeed::Blitz);
+        assert_eq!(
+            blitz.time_control,
+            TimeControl::Clock(ChessClockConfig::fischer(180_000, 2_000))
+        );
+        assert_eq!(blitz.started_at_ms, Some(1_356_998_435_000));
+        assert_eq!(blitz.clocks[3], Some(171_000));
+        assert_eq!(blitz.evals[0], Some(Score::Cp(17)));
+        assert_eq!(
+            blitz.evals[3],
+            Some(Score::Mate {
+                winner: Color::Black,
+                moves: 3
+            })
+        );
+
+        let record = blitz.to_record().unwrap();
+        assert_eq!(record.move_times[2].think_ms, Some(4_000));
+        assert_eq!(record.move_times[3].think_ms, Some(11_000));
+        let session = record.restore().unwrap();
+        assert_eq!(session.san_history(), ["e4", "e6", "d4", "b6"]);
+        assert_eq!(
+            session.game().outcome(),
+            Some(GameOutcome::Decisive {
+                winner: Color::White,
+                reason: DecisiveReason::Timeout
+            })
+        );
+
+        // The tags can't overrule the mate on the board
+        let bullet = games[1].to_game().unwrap();
+        assert_eq!(
+            bullet.outcome(),
+            Some(GameOutcome::Decisive {
+                winner: Color::White,
+                reason: DecisiveReason::Checkmate
+            })
+        );
+        assert_eq!(games[2].speed(), LichessSpeed::Correspondence);
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = LichessGameFilter::default().with_rating(1000..=1500);
+        let games: Vec<_> = parser().with_filter(filter).map(Result::unwrap).collect();
+        assert_eq!(games.len(), 2);
+
+        let filter = LichessGameFilter::default()
+            .with_speed(LichessSpeed::Blitz)
+            .with_speed(LichessSpeed::Correspondence);
+        let sites: Vec<_> = parser()
+            .with_filter(filter)
+            .map(|game| game.unwrap().pgn.headers.site.unwrap())
+            .collect();
+        assert_eq!(
+            sites,
+            [
+                "https://lichess.org/j1dkb5dw",
+                "https://lichess.org/b3tjt2lj"
+            ]
+        );
+
+        let filter = LichessGameFilter::default()
+            .with_time_control(TimeControl::Clock(ChessClockConfig::fischer(60_000, 0)));
+        assert_eq!(parser().with_filter(filter).count(), 1);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::openings::Opening;
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! opening_tags {
+    ($($variant:ident => $tag:literal,)+) => {
+        /// The opening family of a puzzle's source game as tagged by Lichess.
+        ///
+        /// Variation tags and families added by Lichess later are kept verbatim in [`LichessOpeningTag::Other`].
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(
+            feature = "bit-codec",
+            derive(bit_codec::BitEncode, bit_codec::BitDecode)
+        )]
+        #[cfg_attr(feature = "bit-codec", bits(disc = 8))]
+        pub enum LichessOpeningTag {
+            $($variant,)+
+            Other(String),
+        }
+
+        impl LichessOpeningTag {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $tag,)+
+                    Self::Other(tag) => tag,
+                }
+            }
+        }
+
+        impl FromStr for LichessOpeningTag {
+            type Err = std::convert::Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(match s {
+                    $($tag => Self::$variant,)+
+                    other => Self::Other(other.to_string()),
+                })
+            }
+        }
+    };
+}
+
+opening_tags! {
+    AlekhineDefense => "Alekhine_Defense",
+    AmarGambit => "Amar_Gambit",
+    AmarOpening => "Amar_Opening",
+    AmazonAttack => "Amazon_Attack",
+    AnderssensOpening => "Anderssens_Opening",
+    AustralianDefense => "Australian_Defense",
+    BarnesDefense => "Barnes_Defense",
+    BarnesOpening => "Barnes_Opening",
+    BenkoGambit => "Benko_Gambit",
+    BenkoGambitAccepted => "Benko_Gambit_Accepted",
+    BenkoGambitDeclined => "Benko_Gambit_Declined",
+    BenoniDefense => "Benoni_Defense",
+    BirdOpening => "Bird_Opening",
+    BishopsOpening => "Bishops_Opening",
+    BlackmarDiemerGambit => "Blackmar-Diemer_Gambit",
+    BlumenfeldCountergambit => "Blumenfeld_Countergambit",
+    BogoIndianDefense => "Bogo-Indian_Defense",
+    BorgDefense => "Borg_Defense",
+    BudapestDefense => "Budapest_Defense",
+    CanardOpening => "Canard_Opening",
+    CarrDefense => "Carr_Defense",
+    CaroKannDefense => "Caro-Kann_Defense",
+    CatalanOpening => "Catalan_Opening",
+    CenterGame => "Center_Game",
+    ClemenzOpening => "Clemenz_Opening",
+    ColleSystem => "Colle_System",
+    CrabOpening => "Crab_Opening",
+    CzechDefense => "Czech_Defense",
+    DanishGambit => "Danish_Gambit",
+    DanishGambitAccepted => "Danish_Gambit_Accepted",
+    DanishGambitDeclined => "Danish_Gambit_Declined",
+    DutchDefense => "Dutch_Defense",
+    EastIndianDefense => "East_Indian_Defense",
+    ElephantGambit => "Elephant_Gambit",
+    EnglundGambit => "Englund_Gambit",
+    EnglishDefense => "English_Defense",
+    EnglishOpening => "English_Opening",
+    EnglundGambitDeclined => "Englund_Gambit_Declined",
+    FourKnightsGame => "Four_Knights_Game",
+    FrenchDefense => "French_Defense",
+    GedultsOpening => "Gedults_Opening",
+    GiuocoPiano => "Giuoco_Piano",
+    GlobalOpening => "Global_Opening",
+    GoldsmithDefense => "Goldsmith_Defense",
+    GrobOpening => "Grob_Opening",
+    GruenfeldDefense => "Gruenfeld_Defense",
+    GuatemalaDefense => "Guatemala_Defense",
+    GunderamDefense => "Gunderam_Defense",
+    HippopotamusDefense => "Hippopotamus_Defense",
+    HorwitzDefense => "Horwitz_Defense",
+    HungarianOpening => "Hungarian_Opening",
+    IndianDefense => "Indian_Defense",
+    ItalianGame => "Italian_Game",
+    KadasOpening => "Kadas_Opening",
+    KangarooDefense => "Kangaroo_Defense",
+    KingsGambit => "Kings_Gambit",
+    KingsGambitAccepted => "Kings_Gambit_Accepted",
+    KingsGambitDeclined => "Kings_Gambit_Declined",
+    KingsIndianAttack => "Kings_Indian_Attack",
+    KingsIndianDefense => "Kings_Indian_Defense",
+    KingsKnightOpening => "Kings_Knight_Opening",
+    KingsPawnGame => "Kings_Pawn_Game",
+    LatvianGambit => "Latvian_Gambit",
+    LatvianGambitAccepted => "Latvian_Gambit_Accepted",
+    LemmingDefense => "Lemming_Defense",
+    LionDefense => "Lion_Defense",
+    LondonSystem => "London_System",
+    MiesesOpening => "Mieses_Opening",
+    MikenasDefense => "Mikenas_Defense",
+    ModernDefense => "Modern_Defense",
+    MontevideoDefense => "Montevideo_Defense",
+    NeoGruenfeldDefense => "Neo-Gruenfeld_Defense",
+    NimzoIndianDefense => "Nimzo-Indian_Defense",
+    NimzoLarsenAttack => "Nimzo-Larsen_Attack",
+    NimzowitschDefense => "Nimzowitsch_Defense",
+    OldBenoniDefense => "Old_Benoni_Defense",
+    OldIndianDefense => "Old_Indian_Defense",
+    OwenDefense => "Owen_Defense",
+    PetrovsDefense => "Petrovs_Defense",
+    PhilidorDefense => "Philidor_Defense",
+    PircDefense => "Pirc_Defense",
+    PolishDefense => "Polish_Defense",
+    PolishOpening => "Polish_Opening",
+    PonzianiOpening => "Ponziani_Opening",
+    PortugueseOpening => "Portuguese_Opening",
+    PseudoQueensIndianDefense => "Pseudo_Queens_Indian_Defense",
+    PterodactylDefense => "Pterodactyl_Defense",
+    QueensGambit => "Queens_Gambit",
+    QueensGambitAccepted => "Queens_Gambit_Accepted",
+    QueensGambitDeclined => "Queens_Gambit_Declined",
+    QueensGambitRefused => "Queens_Gambit_Refused",
+    QueensIndianAccelerated => "Queens_Indian_Accelerated",
+    QueensIndianDefense => "Queens_Indian_Defense",
+    QueensPawnGame => "Queens_Pawn_Game",
+    RapportJobavaSystem => "Rapport-Jobava_System",
+    RatDefense => "Rat_Defense",
+    RichterVeresovAttack => "Richter-Veresov_Attack",
+    RobatschDefense => "Robatsch_Defense",
+    RussianGame => "Russian_Game",
+    RuyLopez => "Ruy_Lopez",
+    SaragossaOpening => "Saragossa_Opening",
+    ScandinavianDefense => "Scandinavian_Defense",
+    ScotchGame => "Scotch_Game",
+    SemiSlavDefense => "Semi-Slav_Defense",
+    SicilianDefense => "Sicilian_Defense",
+    SlavDefense => "Slav_Defense",
+    SlavIndian => "Slav_Indian",
+    SodiumAttack => "Sodium_Attack",
+    StGeorgeDefense => "St_George_Defense",
+    TarraschDefense => "Tarrasch_Defense",
+    TennisonGambit => "Tennison_Gambit",
+    ThreeKnightsOpening => "Three_Knights_Opening",
+    TorreAttack => "Torre_Attack",
+    TrompowskyAttack => "Trompowsky_Attack",
+    ValenciaOpening => "Valencia_Opening",
+    VanGeetOpening => "Van_Geet_Opening",
+    VantKruijsOpening => "Vant_Kruijs_Opening",
+    ViennaGame => "Vienna_Game",
+    WadeDefense => "Wade_Defense",
+    WareDefense => "Ware_Defense",
+    WareOpening => "Ware_Opening",
+    YusupovRubinsteinSystem => "Yusupov-Rubinstein_System",
+    ZukertortDefense => "Zukertort_Defense",
+    ZukertortOpening => "Zukertort_Opening",
+}
+
+impl LichessOpeningTag {
+    /// The family and variation tags Lichess assigns to games of the opening
+    pub fn from_opening(opening: &Opening) -> Vec<Self> {
+        let mut tags = vec![tag_name(opening.family()).parse().unwrap()];
+        if let Some(variation) = opening.variation() {
+            // Sub-variations after the comma aren't tagged
+            let variation = variation.split(',').next().unwrap_or_default();
+            let tag = format!("{}_{}", tag_name(opening.family()), tag_name(variation));
+            tags.push(tag.parse().unwrap());
+        }
+        tags
+    }
+
+    /// If the tag is a known opening family
+    pub fn is_family(&self) -> bool {
+        !matches!(self, Self::Other(_))
+    }
+}
+
+/// Lichess tags replace spaces with underscores and drop punctuation and accents
+fn tag_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '\'' | ':' | ',' | '.'))
+        .map(|c| match c {
+            ' ' => '_',
+            'á' | 'ä' => 'a',
+            'é' => 'e',
+            'ö' => 'o',
+            'ü' => 'u',
+            c => c,
+        })
+        .collect()
+}
+
+impl fmt::Display for LichessOpeningTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lichess::opening::LichessOpeningTag;
+    use crate::openings;
+
+    #[test]
+    fn test_from_opening() {
+        let tags = |name: &str| {
+            let opening = openings::all().iter().find(|o| o.name == name).unwrap();
+            LichessOpeningTag::from_opening(opening)
+        };
+        assert_eq!(tags("Kádas Opening"), [LichessOpeningTag::KadasOpening]);
+        assert_eq!(
+            tags("Italian Game: Two Knights Defense, Fried Liver Attack"),
+            [
+                LichessOpeningTag::ItalianGame,
+                LichessOpeningTag::Other("Italian_Game_Two_Knights_Defense".to_string())
+            ]
+        );
+    }
+}
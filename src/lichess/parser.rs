@@ -71,6 +71,8 @@ pub struct RawLichessPuzzleEntry {
     pub rating: usize,
     #[serde(rename = "RatingDeviation")]
     pub rating_deviation: usize,
+    #[serde(rename = "Popularity")]
+    pub popularity: i32,
     #[serde(rename = "NbPlays")]
     pub times_played: usize,
     #[serde(rename = "Themes")]
@@ -88,6 +90,7 @@ pub struct LichessPuzzleEntry {
     pub moves: Vec<NaivePromotionMove>,
     pub rating: usize,
     pub rating_deviation: usize,
+    pub popularity: i32,
     pub times_played: usize,
     pub themes: Vec<String>,
     pub game_url: String,
@@ -121,6 +124,7 @@ impl TryFrom<RawLichessPuzzleEntry> for LichessPuzzleEntry {
             moves,
             rating: raw.rating,
             rating_deviation: raw.rating_deviation,
+            popularity: raw.popularity,
             times_played: raw.times_played,
             themes,
             game_url: raw.game_url,
@@ -128,3 +132,20 @@ impl TryFrom<RawLichessPuzzleEntry> for LichessPuzzleEntry {
         })
     }
 }
+
+impl LichessPuzzleEntry {
+    /// The id of the source game from URLs like `https://lichess.org/787zsVup/black#48`
+    pub fn game_id(&self) -> &str {
+        self.game_url
+            .trim_start_matches("https://")
+            .trim_start_matches("lichess.org/")
+            .split(['/', '#'])
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// The ply after the `#` of the game URL
+    pub fn game_ply(&self) -> Option<u16> {
+        self.game_url.rsplit_once('#')?.1.parse().ok()
+    }
+}
@@ -1,6 +1,8 @@
 use crate::core::puzzle::Puzzle;
+use crate::lichess::opening::LichessOpeningTag;
 use crate::lichess::themes::LichessPuzzleTheme;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(
     feature = "bit-codec",
     derive(bit_codec::BitEncode, bit_codec::BitDecode)
@@ -14,4 +16,14 @@ pub struct LichessPuzzle {
     #[cfg_attr(feature = "bit-codec", bits(9))]
     pub rating_deviation: u16,
     pub times_played: u32,
+    /// Theme tags unknown to this version, kept verbatim
+    pub unknown_themes: Vec<String>,
+    /// From -100 to 100
+    pub popularity: i8,
+    /// Id of the Lichess game the puzzle was taken from, empty if unknown
+    pub game_id: String,
+    /// The ply of the source game the puzzle starts at
+    pub game_ply: u16,
+    /// The opening family followed by the variation
+    pub opening_tags: Vec<LichessOpeningTag>,
 }
@@ -0,0 +1,530 @@
+use crate::core::position::Position;
+use crate::core::puzzle::Puzzle;
+use crate::lichess::themes::LichessPuzzleTheme;
+use crate::moves::generator::MoveGenerator;
+use crate::prelude::{ChessBoard, ChessMove, Color, Piece, Square};
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Derives the themes of a puzzle from its position and solution, using the Lichess vocabulary.
+///
+/// Themes that need an engine evaluation (e.g. crushing, advantage) are never detected.
+pub fn detect(puzzle: &Puzzle) -> Vec<LichessPuzzleTheme> {
+    let start = puzzle.position().make_move(*puzzle.last_move());
+    let solver = start.side_to_move;
+    let solution = puzzle.solution();
+
+    // The position before each move of the solution, followed by the final position
+    let mut positions = vec![start];
+    for &mv in solution {
+        let next = positions[positions.len() - 1].make_move(mv);
+        positions.push(next);
+    }
+
+    let mut themes = Vec::new();
+    let solver_moves = solution.len().div_ceil(2);
+    themes.push(match solver_moves {
+        0 | 1 => LichessPuzzleTheme::OneMove,
+        2 => LichessPuzzleTheme::Short,
+        3 => LichessPuzzleTheme::Long,
+        _ => LichessPuzzleTheme::VeryLong,
+    });
+
+    let last = positions[positions.len() - 1];
+    if last.is_checkmate() {
+        themes.push(LichessPuzzleTheme::Mate);
+        if let Some(theme) = mate_in(solver_moves) {
+            themes.push(theme);
+        }
+        if let Some(&mv) = solution.last() {
+            themes.extend(mate_patterns(&last.board, mv, solver.opposite()));
+        }
+    }
+
+    let moves = solution
+        .iter()
+        .zip(&positions)
+        .step_by(2)
+        .map(|(&mv, before)| (mv, before, before.make_move(mv)));
+    for (i, (mv, before, after)) in moves.enumerate() {
+        let is_last = i + 1 == solver_moves;
+        if !is_last && is_fork(&after.board, mv.to(), solver) {
+            themes.push(LichessPuzzleTheme::Fork);
+        }
+        if let Some(theme) = line_theme(&after.board, mv.to(), solver) {
+            themes.push(theme);
+        }
+        themes.extend(discovered(&before.board, &after.board, mv, solver));
+        if let Some(piece) = mv.flags().promotion_piece() {
+            themes.push(LichessPuzzleTheme::Promotion);
+            if piece != Piece::Queen {
+                themes.push(LichessPuzzleTheme::UnderPromotion);
+            }
+        }
+        if mv.flags().is_en_passant() {
+            themes.push(LichessPuzzleTheme::EnPassant);
+        }
+        if mv.flags().is_kingside_castle() || mv.flags().is_queenside_castle() {
+            themes.push(LichessPuzzleTheme::Castling);
+        }
+        if is_advanced_pawn_move(&before.board, mv, solver) {
+            themes.push(LichessPuzzleTheme::AdvancedPawn);
+        }
+    }
+
+    if is_sacrifice(solution, &positions, solver) {
+        themes.push(LichessPuzzleTheme::Sacrifice);
+    }
+    themes.extend(phase(&start.board));
+
+    let mut unique = Vec::with_capacity(themes.len());
+    for theme in themes {
+        if !unique.contains(&theme) {
+            unique.push(theme);
+        }
+    }
+    unique
+}
+
+fn mate_in(moves: usize) -> Option<LichessPuzzleTheme> {
+    match moves {
+        1 => Some(LichessPuzzleTheme::MateIn1),
+        2 => Some(LichessPuzzleTheme::MateIn2),
+        3 => Some(LichessPuzzleTheme::MateIn3),
+        4 => Some(LichessPuzzleTheme::MateIn4),
+        5 => Some(LichessPuzzleTheme::MateIn5),
+        _ => None,
+    }
+}
+
+fn mate_patterns(board: &ChessBoard, mv: ChessMove, mated: Color) -> Vec<LichessPuzzleTheme> {
+    let generator = MoveGenerator::get();
+    let Some(king) = board.piece_bb(Piece::King, mated).get_lowest_set() else {
+        return vec![];
+    };
+    let mater = mated.opposite();
+    let checkers = generator.attackers(board, king, mater);
+    let mating_piece = board.piece_at(mv.to()).map(|(piece, _)| piece);
+    let own = board.color_bb(mated);
+    let mut themes = Vec::new();
+
+    // The king is stuck on its back rank behind its own pieces
+    let back_rank = match mated {
+        Color::White => 1,
+        Color::Black => 8,
+    };
+    let forward = if mated == Color::White { 1 } else { -1 };
+    let rank_checker = checkers
+        .iter()
+        .any(|sq| sq.rank() == back_rank && is_major(board, sq));
+    let front_blocked = (-1..=1)
+        .filter_map(|file| king.jump(file, forward))
+        .all(|sq| own.is_set(sq));
+    if king.rank() == back_rank && rank_checker && front_blocked {
+        themes.push(LichessPuzzleTheme::BackRankMate);
+    }
+
+    // Every flight square is taken by the king's own pieces
+    if mating_piece == Some(Piece::Knight) && neighbors(king).all(|sq| own.is_set(sq)) {
+        themes.push(LichessPuzzleTheme::SmotheredMate);
+    }
+
+    // Rook or queen mates on the edge file, a knight covers the flight squares the king's own piece doesn't block
+    let edge_file = king.file() == 1 || king.file() == 8;
+    let corner_rank = king.rank() == 1 || king.rank() == 8;
+    if edge_file
+        && !corner_rank
+        && mv.to().file() == king.file()
+        && matches!(mating_piece, Some(Piece::Rook | Piece::Queen))
+    {
+        let inward = if king.file() == 1 { 1 } else { -1 };
+        let blocker = king.jump(inward, 0).is_some_and(|sq| own.is_set(sq));
+        let knight = king
+            .jump(3 * inward, 0)
+            .is_some_and(|sq| board.piece_at(sq) == Some((Piece::Knight, mater)));
+        if blocker && knight {
+            themes.push(LichessPuzzleTheme::AnastasiaMate);
+        }
+    }
+
+    // Rook next to the cornered king, protected by a knight
+    if edge_file && corner_rank && mating_piece == Some(Piece::Rook) {
+        let adjacent = (mv.to().file() as i8 - king.file() as i8).abs()
+            + (mv.to().rank() as i8 - king.rank() as i8).abs()
+            == 1;
+        let protected = generator
+            .attackers(board, mv.to(), mater)
+            .iter()
+            .any(|sq| board.piece_at(sq) == Some((Piece::Knight, mater)));
+        if adjacent && protected {
+            themes.push(LichessPuzzleTheme::ArabianMate);
+        }
+    }
+
+    // Two bishops control the king and all of its flight squares, on crossing or on parallel diagonals
+    let zone = || std::iter::once(king).chain(neighbors(king));
+    let only_bishops = zone().all(|sq| {
+        generator
+            .attackers(board, sq, mater)
+            .iter()
+            .all(|attacker| board.piece_at(attacker) == Some((Piece::Bishop, mater)))
+    });
+    let diagonals: Vec<bool> = board
+        .piece_bb(Piece::Bishop, mater)
+        .iter()
+        .filter_map(|bishop| {
+            let attacks = generator.attacks_from(board, bishop);
+            let target = zone().find(|&sq| attacks.is_set(sq))?;
+            Some(is_rising_diagonal(bishop, target))
+        })
+        .collect();
+    if let [a, b] = diagonals.as_slice()
+        && only_bishops
+    {
+        if a != b {
+            themes.push(LichessPuzzleTheme::BodenMate);
+        } else {
+            themes.push(LichessPuzzleTheme::DoubleBishopMate);
+        }
+    }
+
+    themes
+}
+
+/// The moved piece attacks two pieces it can win: the king, more valuable or undefended pieces
+fn is_fork(board: &ChessBoard, square: Square, solver: Color) -> bool {
+    let generator = MoveGenerator::get();
+    let Some((piece, _)) = board.piece_at(square) else {
+        return false;
+    };
+    if piece == Piece::King {
+        return false;
+    }
+
+    let opponent = solver.opposite();
+    let targets = generator.attacks_from(board, square) & board.color_bb(opponent);
+    let forked = targets
+        .iter()
+        .filter(|&target| {
+            let Some((victim, _)) = board.piece_at(target) else {
+                return false;
+            };
+            victim != Piece::Pawn
+                && (victim == Piece::King
+                    || victim.value() > piece.value()
+                    || generator.attackers(board, target, opponent).is_empty())
+        })
+        .count();
+    forked >= 2
+}
+
+/// Pin or skewer created by the moved slider: two opponent pieces behind each other on its line
+fn line_theme(board: &ChessBoard, square: Square, solver: Color) -> Option<LichessPuzzleTheme> {
+    let (piece, _) = board.piece_at(square)?;
+    let directions: &[(i8, i8)] = match piece {
+        Piece::Bishop => &BISHOP_DIRECTIONS,
+        Piece::Rook => &ROOK_DIRECTIONS,
+        Piece::Queen => &[ROOK_DIRECTIONS, BISHOP_DIRECTIONS].concat(),
+        _ => return None,
+    };
+
+    for &(file, rank) in directions {
+        let mut ray = std::iter::successors(square.jump(file, rank), |sq| sq.jump(file, rank))
+            .filter_map(|sq| board.piece_at(sq));
+        let (Some((front, front_color)), Some((back, back_color))) = (ray.next(), ray.next())
+        else {
+            continue;
+        };
+        if front_color == solver || back_color == solver {
+            continue;
+        }
+        if worth(front) < worth(back) && front != Piece::Pawn {
+            return Some(LichessPuzzleTheme::Pin);
+        }
+        if worth(front) > worth(back) && back != Piece::Pawn {
+            return Some(LichessPuzzleTheme::Skewer);
+        }
+    }
+    None
+}
+
+/// Attacks of the solver's other pieces that were unblocked by the move
+fn discovered(
+    before: &ChessBoard,
+    after: &ChessBoard,
+    mv: ChessMove,
+    solver: Color,
+) -> Vec<LichessPuzzleTheme> {
+    let generator = MoveGenerator::get();
+    let opponent = solver.opposite();
+    let mut themes = Vec::new();
+
+    let targets = after.color_bb(opponent) & !after.piece_bb(Piece::Pawn, opponent);
+    let mut attackers = after.color_bb(solver);
+    attackers.clear(mv.to());
+    for square in attackers {
+        let new = generator.attacks_from(after, square)
+            & !generator.attacks_from(before, square)
+            & targets;
+        if new.is_empty() {
+            continue;
+        }
+        themes.push(LichessPuzzleTheme::DiscoveredAttack);
+        if !(new & after.piece_bb(Piece::King, opponent)).is_empty() {
+            themes.push(LichessPuzzleTheme::DiscoveredCheck);
+        }
+    }
+
+    if let Some(king) = after.piece_bb(Piece::King, opponent).get_lowest_set()
+        && generator.attackers(after, king, solver).count_set() >= 2
+    {
+        themes.push(LichessPuzzleTheme::DoubleCheck);
+    }
+    themes
+}
+
+fn is_advanced_pawn_move(board: &ChessBoard, mv: ChessMove, solver: Color) -> bool {
+    let relative_rank = match solver {
+        Color::White => mv.to().rank(),
+        Color::Black => 9 - mv.to().rank(),
+    };
+    board.piece_at(mv.from()) == Some((Piece::Pawn, solver)) && relative_rank >= 6
+}
+
+/// The solver is down at least two pawns of material after one of the opponent's replies
+fn is_sacrifice(solution: &[ChessMove], positions: &[Position], solver: Color) -> bool {
+    if solution.iter().step_by(2).any(ChessMove::is_promotion) {
+        return false;
+    }
+    let initial = positions[0].board.material_balance(solver);
+    positions
+        .iter()
+        .skip(2)
+        .step_by(2)
+        .any(|pos| pos.board.material_balance(solver) - initial <= -2 * Piece::Pawn.value())
+}
+
+fn phase(board: &ChessBoard) -> Vec<LichessPuzzleTheme> {
+    let count = |pieces: &[Piece]| -> u8 {
+        pieces
+            .iter()
+            .map(|&piece| board.pieces_bb(piece).count_set())
+            .sum()
+    };
+    let minors = count(&[Piece::Knight, Piece::Bishop]);
+    let majors = count(&[Piece::Rook, Piece::Queen]);
+    if minors + majors > 6 {
+        return vec![LichessPuzzleTheme::Middlegame];
+    }
+
+    let knights = count(&[Piece::Knight]);
+    let bishops = count(&[Piece::Bishop]);
+    let rooks = count(&[Piece::Rook]);
+    let queens = count(&[Piece::Queen]);
+    let endgame = match (knights, bishops, rooks, queens) {
+        (0, 0, 0, 0) => Some(LichessPuzzleTheme::PawnEndgame),
+        (_, 0, 0, 0) => Some(LichessPuzzleTheme::KnightEndgame),
+        (0, _, 0, 0) => Some(LichessPuzzleTheme::BishopEndgame),
+        (0, 0, _, 0) => Some(LichessPuzzleTheme::RookEndgame),
+        (0, 0, 0, _) => Some(LichessPuzzleTheme::QueenEndgame),
+        (0, 0, _, _) => Some(LichessPuzzleTheme::QueenRookEndgame),
+        _ => None,
+    };
+    std::iter::once(LichessPuzzleTheme::Endgame)
+        .chain(endgame)
+        .collect()
+}
+
+/// Material value with the king above everything else
+fn worth(piece: Piece) -> i32 {
+    match piece {
+        Piece::King => i32::MAX,
+        piece => piece.value(),
+    }
+}
+
+fn is_major(board: &ChessBoard, square: Square) -> bool {
+    matches!(
+        board.piece_at(square),
+        Some((Piece::Rook | Piece::Queen, _))
+    )
+}
+
+/// If the two squares are on a diagonal in the direction of a1-h8 rather than a8-h1
+fn is_rising_diagonal(from: Square, to: Square) -> bool {
+    (to.file() > from.file()) == (to.rank() > from.rank())
+}
+
+fn neighbors(square: Square) -> impl Iterator<Item = Square> {
+    (-1..=1)
+        .flat_map(|file| (-1..=1).map(move |rank| (file, rank)))
+        .filter(|&offset| offset != (0, 0))
+        .filter_map(move |(file, rank)| square.jump(file, rank))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::puzzle::Puzzle;
+    use crate::lichess::themes::{LichessPuzzleTheme, detect};
+
+    fn themes(fen: &str, moves: &[&str]) -> Vec<LichessPuzzleTheme> {
+        detect(&Puzzle::from_uci(fen, moves))
+    }
+
+    #[test]
+    fn test_mate_patterns() {
+        let back_rank =
+            Puzzle::from_uci("6k1/1p3ppp/8/8/8/8/5PPP/R5K1 b - - 0 1", &["b7b6", "a1a8"]);
+        let themes = detect(&back_rank);
+        assert!(themes.contains(&LichessPuzzleTheme::MateIn1));
+        assert!(themes.contains(&LichessPuzzleTheme::BackRankMate));
+        assert!(themes.contains(&LichessPuzzleTheme::OneMove));
+        assert!(themes.contains(&LichessPuzzleTheme::RookEndgame));
+
+        let smothered = Puzzle::from_uci("6rk/p5pp/8/4N3/8/8/8/6K1 b - - 0 1", &["a7a6", "e5f7"]);
+        let themes = detect(&smothered);
+        assert!(themes.contains(&LichessPuzzleTheme::SmotheredMate));
+        assert!(!themes.contains(&LichessPuzzleTheme::BackRankMate));
+    }
+
+    #[test]
+    fn test_fork() {
+        let puzzle = Puzzle::from_uci(
+            "r3k3/8/8/8/2N5/8/8/4K3 b - - 0 1",
+            &["e8d7", "c4b6", "d7c7", "b6a8"],
+        );
+        let themes = detect(&puzzle);
+        assert!(themes.contains(&LichessPuzzleTheme::Fork));
+        assert!(themes.contains(&LichessPuzzleTheme::Short));
+        assert!(themes.contains(&LichessPuzzleTheme::Endgame));
+        assert!(!themes.contains(&LichessPuzzleTheme::Mate));
+        assert!(!themes.contains(&LichessPuzzleTheme::Sacrifice));
+    }
+
+    #[test]
+    fn test_named_mates() {
+        let cases = [
+            (
+                "8/p3N1pk/8/R7/8/8/8/K7 b - - 0 1",
+                ["a7a6", "a5h5"],
+                LichessPuzzleTheme::AnastasiaMate,
+            ),
+            (
+                "7k/R7/5N2/2p5/8/8/8/K7 b - - 0 1",
+                ["c5c4", "a7h7"],
+                LichessPuzzleTheme::ArabianMate,
+            ),
+            (
+                "2kr4/3p3p/8/8/5B2/8/4B3/4K3 b - - 0 1",
+                ["h7h6", "e2a6"],
+                LichessPuzzleTheme::BodenMate,
+            ),
+            (
+                "6bk/p7/8/8/8/3B4/8/2B1K3 b - - 0 1",
+                ["a7a6", "c1b2"],
+                LichessPuzzleTheme::DoubleBishopMate,
+            ),
+        ];
+        for (fen, moves, theme) in cases {
+            let themes = themes(fen, &moves);
+            assert!(themes.contains(&LichessPuzzleTheme::MateIn1), "{fen}");
+            assert!(themes.contains(&theme), "{fen}: {themes:?}");
+        }
+
+        let themes = themes(
+            "4k3/8/7p/8/8/8/8/RR2K3 b - - 0 1",
+            &["h6h5", "a1a7", "e8f8", "b1b8"],
+        );
+        assert!(themes.contains(&LichessPuzzleTheme::MateIn2));
+        assert!(themes.contains(&LichessPuzzleTheme::Short));
+    }
+
+    #[test]
+    fn test_line_themes() {
+        let pin = themes("4k3/3n3p/8/8/8/8/8/4KB2 b - - 0 1", &["h7h6", "f1b5"]);
+        assert!(pin.contains(&LichessPuzzleTheme::Pin));
+        let skewer = themes("3q4/7p/8/3k4/8/8/8/R5K1 b - - 0 1", &["h7h6", "a1d1"]);
+        assert!(skewer.contains(&LichessPuzzleTheme::Skewer));
+
+        let attack = themes("4q1k1/7p/8/8/4N3/8/8/4R1K1 b - - 0 1", &["h7h6", "e4c5"]);
+        assert!(attack.contains(&LichessPuzzleTheme::DiscoveredAttack));
+        assert!(!attack.contains(&LichessPuzzleTheme::DiscoveredCheck));
+        let check = themes("4k3/7p/8/8/4B3/8/8/4R1K1 b - - 0 1", &["h7h6", "e4b7"]);
+        assert!(check.contains(&LichessPuzzleTheme::DiscoveredCheck));
+        let double = themes("4k3/7p/8/8/4N3/8/8/4R1K1 b - - 0 1", &["h7h6", "e4f6"]);
+        assert!(double.contains(&LichessPuzzleTheme::DoubleCheck));
+    }
+
+    #[test]
+    fn test_special_moves() {
+        let promotion = themes("7k/1P5p/8/8/8/8/8/6K1 b - - 0 1", &["h7h6", "b7b8q"]);
+        assert!(promotion.contains(&LichessPuzzleTheme::Promotion));
+        assert!(promotion.contains(&LichessPuzzleTheme::AdvancedPawn));
+        assert!(!promotion.contains(&LichessPuzzleTheme::UnderPromotion));
+        let under = themes("7k/1P5p/8/8/8/8/8/6K1 b - - 0 1", &["h7h6", "b7b8n"]);
+        assert!(under.contains(&LichessPuzzleTheme::UnderPromotion));
+
+        let en_passant = themes("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1", &["d7d5", "e5d6"]);
+        assert!(en_passant.contains(&LichessPuzzleTheme::EnPassant));
+        let castling = themes("4k3/7p/8/8/8/8/8/4K2R b K - 0 1", &["h7h6", "e1g1"]);
+        assert!(castling.contains(&LichessPuzzleTheme::Castling));
+        let sacrifice = themes("1k6/7p/8/8/8/8/8/R5K1 b - - 0 1", &["h7h6", "a1a8", "b8a8"]);
+        assert!(sacrifice.contains(&LichessPuzzleTheme::Sacrifice));
+    }
+
+    #[test]
+    fn test_length_and_phase() {
+        let shuffle = [
+            "h7h6", "a1a2", "e8d8", "a2a3", "d8e8", "a3a4", "e8d8", "a4a5",
+        ];
+        let fen = "4k3/7p/8/8/8/8/8/R3K3 b - - 0 1";
+        assert!(themes(fen, &shuffle[..6]).contains(&LichessPuzzleTheme::Long));
+        assert!(themes(fen, &shuffle).contains(&LichessPuzzleTheme::VeryLong));
+
+        let cases = [
+            (
+                "4k3/7p/8/8/8/8/P7/4K3 b - - 0 1",
+                "a2a3",
+                LichessPuzzleTheme::PawnEndgame,
+            ),
+            (
+                "4k3/7p/8/8/8/8/8/N3K3 b - - 0 1",
+                "a1b3",
+                LichessPuzzleTheme::KnightEndgame,
+            ),
+            (
+                "4k3/7p/8/8/8/8/8/B3K3 b - - 0 1",
+                "a1b2",
+                LichessPuzzleTheme::BishopEndgame,
+            ),
+            (
+                "4k3/7p/8/8/8/8/8/R3K3 b - - 0 1",
+                "a1a2",
+                LichessPuzzleTheme::RookEndgame,
+            ),
+            (
+                "4k3/7p/8/8/8/8/8/Q3K3 b - - 0 1",
+                "a1a2",
+                LichessPuzzleTheme::QueenEndgame,
+            ),
+            (
+                "4k3/7p/8/8/8/8/8/QR2K3 b - - 0 1",
+                "a1a2",
+                LichessPuzzleTheme::QueenRookEndgame,
+            ),
+        ];
+        for (fen, mv, theme) in cases {
+            let themes = themes(fen, &["h7h6", mv]);
+            assert!(themes.contains(&LichessPuzzleTheme::Endgame), "{fen}");
+            assert!(themes.contains(&theme), "{fen}: {themes:?}");
+        }
+
+        let middlegame = themes(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+            &["e7e5", "g1f3"],
+        );
+        assert!(middlegame.contains(&LichessPuzzleTheme::Middlegame));
+        assert!(!middlegame.contains(&LichessPuzzleTheme::Endgame));
+    }
+}
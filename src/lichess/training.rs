@@ -0,0 +1,54 @@
+use crate::core::puzzle::PuzzlePlayer;
+
+pub mod glicko;
+pub mod repetition;
+#[cfg(feature = "lichess-puzzle-archive")]
+pub mod scheduler;
+
+/// How an attempt at a puzzle went
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PuzzleResult {
+    pub solved: bool,
+    pub times_failed: usize,
+    pub hints_used: usize,
+    pub solve_ms: u64,
+}
+
+impl PuzzleResult {
+    pub fn from_player(player: &PuzzlePlayer, solve_ms: u64) -> Self {
+        Self {
+            solved: player.is_solved(),
+            times_failed: player.times_failed(),
+            hints_used: player.hints_used(),
+            solve_ms,
+        }
+    }
+
+    fn is_clean(&self) -> bool {
+        self.solved && self.times_failed == 0 && self.hints_used == 0
+    }
+
+    /// The game score against the puzzle, clean solves slower than `slow_ms` count as partial wins
+    pub fn score(&self, slow_ms: u64) -> f64 {
+        match (self.is_clean(), self.solve_ms > slow_ms) {
+            (false, _) => 0.0,
+            (true, true) => 0.75,
+            (true, false) => 1.0,
+        }
+    }
+
+    /// The SM-2 recall quality (0-5), anything below 3 counts as failed
+    pub fn quality(&self, slow_ms: u64) -> u8 {
+        if !self.solved || self.hints_used > 0 && self.times_failed > 0 {
+            0
+        } else if !self.is_clean() {
+            2
+        } else if self.solve_ms > slow_ms {
+            4
+        } else {
+            5
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::f64::consts::PI;
+
+/// Converts between the Glicko and the Glicko-2 scale
+const SCALE: f64 = 173.7178;
+/// Constrains the change of the volatility over time
+const TAU: f64 = 0.5;
+const EPSILON: f64 = 0.000001;
+
+/// A Glicko-2 rating, the way Lichess rates players and puzzles
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Glicko2Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Glicko2Rating {
+    fn default() -> Self {
+        Self {
+            rating: 1500.0,
+            deviation: 350.0,
+            volatility: 0.06,
+        }
+    }
+}
+
+impl Glicko2Rating {
+    /// Rates a single game against the opponent, the score is 1 for a win, 0.5 for a draw and 0 for a loss
+    pub fn update(&mut self, opponent_rating: f64, opponent_deviation: f64, score: f64) {
+        let mu = (self.rating - 1500.0) / SCALE;
+        let phi = self.deviation / SCALE;
+        let mu_j = (opponent_rating - 1500.0) / SCALE;
+        let phi_j = opponent_deviation / SCALE;
+
+        let g = 1.0 / (1.0 + 3.0 * phi_j.powi(2) / PI.powi(2)).sqrt();
+        let expected = 1.0 / (1.0 + (-g * (mu - mu_j)).exp());
+        let v = 1.0 / (g.powi(2) * expected * (1.0 - expected));
+        let delta = v * g * (score - expected);
+
+        let volatility = new_volatility(phi, v, delta, self.volatility);
+        let phi_star = (phi.powi(2) + volatility.powi(2)).sqrt();
+        let new_phi = 1.0 / (1.0 / phi_star.powi(2) + 1.0 / v).sqrt();
+        let new_mu = mu + new_phi.powi(2) * g * (score - expected);
+
+        self.rating = new_mu * SCALE + 1500.0;
+        self.deviation = (new_phi * SCALE).min(350.0);
+        self.volatility = volatility;
+    }
+
+    /// The chance (0-1) to beat the opponent
+    pub fn expected_score(&self, opponent_rating: f64, opponent_deviation: f64) -> f64 {
+        let phi_j = opponent_deviation / SCALE;
+        let g = 1.0 / (1.0 + 3.0 * phi_j.powi(2) / PI.powi(2)).sqrt();
+        1.0 / (1.0 + (-g * (self.rating - opponent_rating) / SCALE).exp())
+    }
+}
+
+/// Iterative volatility update of step 5 of the Glicko-2 paper, using the Illinois algorithm
+fn new_volatility(phi: f64, v: f64, delta: f64, volatility: f64) -> f64 {
+    let a = volatility.powi(2).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        ex * (delta.powi(2) - phi.powi(2) - v - ex) / (2.0 * (phi.powi(2) + v + ex).powi(2))
+            - (x - a) / TAU.powi(2)
+    };
+
+    let mut big_a = a;
+    let mut big_b = if delta.powi(2) > phi.powi(2) + v {
+        (delta.powi(2) - phi.powi(2) - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    while (big_b - big_a).abs() > EPSILON {
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0.0 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+    (big_a / 2.0).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lichess::training::glicko::Glicko2Rating;
+
+    #[test]
+    fn test_update() {
+        let mut rating = Glicko2Rating {
+            rating: 1500.0,
+            deviation: 200.0,
+            volatility: 0.06,
+        };
+        rating.update(1400.0, 30.0, 1.0);
+        assert!(rating.rating > 1500.0);
+        assert!(rating.deviation < 200.0);
+
+        let before = rating.rating;
+        rating.update(1700.0, 300.0, 0.0);
+        assert!(rating.rating < before);
+        assert!((0.0..1.0).contains(&rating.expected_score(1700.0, 300.0)));
+    }
+}
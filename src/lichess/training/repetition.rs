@@ -0,0 +1,125 @@
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+const MIN_EASE: f64 = 1.3;
+
+/// A puzzle scheduled for review with the SM-2 algorithm
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReviewCard {
+    pub puzzle_id: String,
+    pub ease: f64,
+    pub interval_days: u32,
+    /// Successful reviews in a row
+    pub repetitions: u32,
+    pub due_ms: u64,
+}
+
+impl ReviewCard {
+    pub fn new(puzzle_id: impl Into<String>, unix_ms: u64) -> Self {
+        Self {
+            puzzle_id: puzzle_id.into(),
+            ease: 2.5,
+            interval_days: 0,
+            repetitions: 0,
+            due_ms: unix_ms,
+        }
+    }
+
+    /// Reschedules the card, the quality ranges from 0 (blackout) to 5 (perfect recall)
+    pub fn review(&mut self, quality: u8, unix_ms: u64) {
+        let quality = quality.min(5);
+        if quality < 3 {
+            self.repetitions = 0;
+            self.interval_days = 1;
+        } else {
+            self.interval_days = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval_days as f64 * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        }
+
+        let miss = (5 - quality) as f64;
+        self.ease = (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE);
+        self.due_ms = unix_ms + self.interval_days as u64 * DAY_MS;
+    }
+}
+
+/// Failed puzzles which are repeated in growing intervals until they are solved reliably
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RepetitionQueue {
+    cards: Vec<ReviewCard>,
+}
+
+impl RepetitionQueue {
+    /// Reviews the card of the puzzle, failed puzzles without a card get one.
+    /// Returns false if a solved puzzle isn't in the queue.
+    pub fn record(&mut self, puzzle_id: &str, quality: u8, unix_ms: u64) -> bool {
+        let card = match self.cards.iter_mut().find(|c| c.puzzle_id == puzzle_id) {
+            Some(card) => card,
+            None if quality < 3 => {
+                self.cards.push(ReviewCard::new(puzzle_id, unix_ms));
+                self.cards.last_mut().unwrap()
+            }
+            None => return false,
+        };
+        card.review(quality, unix_ms);
+        true
+    }
+
+    /// The card that is overdue the longest
+    pub fn next_due(&self, unix_ms: u64) -> Option<&ReviewCard> {
+        self.due(unix_ms).min_by_key(|card| card.due_ms)
+    }
+
+    pub fn due(&self, unix_ms: u64) -> impl Iterator<Item = &ReviewCard> {
+        self.cards.iter().filter(move |card| card.due_ms <= unix_ms)
+    }
+
+    pub fn remove(&mut self, puzzle_id: &str) -> Option<ReviewCard> {
+        let index = self.cards.iter().position(|c| c.puzzle_id == puzzle_id)?;
+        Some(self.cards.remove(index))
+    }
+}
+
+// Accessors
+impl RepetitionQueue {
+    pub fn cards(&self) -> &[ReviewCard] {
+        &self.cards
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lichess::training::repetition::{DAY_MS, RepetitionQueue};
+
+    #[test]
+    fn test_intervals() {
+        let mut queue = RepetitionQueue::default();
+        assert!(!queue.record("abc", 5, 0));
+        assert!(queue.record("abc", 1, 0));
+        assert_eq!(queue.next_due(DAY_MS).unwrap().puzzle_id, "abc");
+        assert!(queue.next_due(DAY_MS - 1).is_none());
+
+        queue.record("abc", 5, DAY_MS);
+        queue.record("abc", 5, 2 * DAY_MS);
+        let card = &queue.cards()[0];
+        assert_eq!(card.interval_days, 6);
+        assert_eq!(card.due_ms, 8 * DAY_MS);
+
+        queue.record("abc", 0, 8 * DAY_MS);
+        assert_eq!(queue.cards()[0].interval_days, 1);
+        assert_eq!(queue.cards()[0].repetitions, 0);
+    }
+}
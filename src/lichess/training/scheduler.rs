@@ -0,0 +1,170 @@
+use crate::lichess::archive::LichessPuzzleArchive;
+use crate::lichess::archive::query::PuzzleQuery;
+use crate::lichess::puzzle::LichessPuzzle;
+use crate::lichess::themes::LichessPuzzleTheme;
+use crate::lichess::training::PuzzleResult;
+use crate::lichess::training::glicko::Glicko2Rating;
+use crate::lichess::training::repetition::RepetitionQueue;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrainingConfig {
+    /// Puzzles are picked within this distance of the target rating, the window widens if none is found
+    pub rating_window: u16,
+    /// Added to the user's rating to get the target rating, negative for easier puzzles
+    pub difficulty_offset: i16,
+    /// Themes every new puzzle has to have
+    pub themes: Vec<LichessPuzzleTheme>,
+    /// Clean solves slower than this count as partial successes
+    pub slow_ms: u64,
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        Self {
+            rating_window: 100,
+            difficulty_offset: 0,
+            themes: vec![],
+            slow_ms: 60_000,
+        }
+    }
+}
+
+/// Picks puzzles matching the user's rating and repeats failed ones
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PuzzleScheduler {
+    config: TrainingConfig,
+    rating: Glicko2Rating,
+    queue: RepetitionQueue,
+    seed: u64,
+}
+
+impl PuzzleScheduler {
+    /// The seed determines which puzzles are picked
+    pub fn new(config: TrainingConfig, seed: u64) -> Self {
+        Self {
+            config,
+            rating: Glicko2Rating::default(),
+            queue: RepetitionQueue::default(),
+            seed,
+        }
+    }
+
+    pub fn with_rating(mut self, rating: Glicko2Rating) -> Self {
+        self.rating = rating;
+        self
+    }
+
+    /// Due reviews first, otherwise a new puzzle around the target rating
+    pub fn next_puzzle(
+        &mut self,
+        archive: &LichessPuzzleArchive,
+        unix_ms: u64,
+    ) -> std::io::Result<Option<LichessPuzzle>> {
+        while let Some(card) = self.queue.next_due(unix_ms) {
+            let id = card.puzzle_id.clone();
+            match archive.get_by_id(&id)? {
+                Some(puzzle) => return Ok(Some(puzzle)),
+                None => {
+                    self.queue.remove(&id);
+                }
+            }
+        }
+
+        self.seed = self.seed.wrapping_add(1);
+        let target = (self.rating.rating as i32 + self.config.difficulty_offset as i32)
+            .clamp(0, u16::MAX as i32) as u16;
+        let mut window = self.config.rating_window.max(1);
+        loop {
+            let mut query = PuzzleQuery::default()
+                .with_rating(target.saturating_sub(window)..=target.saturating_add(window));
+            for &theme in &self.config.themes {
+                query = query.with_theme(theme);
+            }
+            if let Some(puzzle) = archive.random_seeded(&query, self.seed)? {
+                return Ok(Some(puzzle));
+            }
+            if window == u16::MAX {
+                return Ok(None);
+            }
+            window = window.saturating_mul(2);
+        }
+    }
+
+    /// Updates the rating and schedules a review if the puzzle wasn't solved cleanly
+    pub fn record(&mut self, puzzle: &LichessPuzzle, result: &PuzzleResult, unix_ms: u64) {
+        self.rating.update(
+            puzzle.rating as f64,
+            puzzle.rating_deviation as f64,
+            result.score(self.config.slow_ms),
+        );
+        self.queue
+            .record(&puzzle.id, result.quality(self.config.slow_ms), unix_ms);
+    }
+}
+
+// Accessors
+impl PuzzleScheduler {
+    pub fn config(&self) -> &TrainingConfig {
+        &self.config
+    }
+
+    pub fn rating(&self) -> &Glicko2Rating {
+        &self.rating
+    }
+
+    pub fn queue(&self) -> &RepetitionQueue {
+        &self.queue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::position::Position;
+    use crate::lichess::archive::LichessPuzzleArchive;
+    use crate::lichess::parser::LichessPuzzleEntry;
+    use crate::lichess::training::PuzzleResult;
+    use crate::lichess::training::scheduler::{PuzzleScheduler, TrainingConfig};
+
+    #[test]
+    fn test_repeats_failed_puzzles() {
+        let entries = (0..50).map(|i| {
+            Ok(LichessPuzzleEntry {
+                id: format!("p{i}"),
+                pos: Position::default(),
+                moves: vec!["e2e4".parse().unwrap(), "e7e5".parse().unwrap()],
+                rating: 1000 + i * 20,
+                rating_deviation: 80,
+                popularity: 90,
+                times_played: 0,
+                themes: vec!["short".into()],
+                game_url: String::new(),
+                opening_tags: vec![],
+            })
+        });
+        let archive = LichessPuzzleArchive::try_from_iter(entries).unwrap();
+        let mut scheduler = PuzzleScheduler::new(TrainingConfig::default(), 7);
+
+        let puzzle = scheduler.next_puzzle(&archive, 0).unwrap().unwrap();
+        assert!((1400..=1600).contains(&puzzle.rating));
+
+        let failed = PuzzleResult {
+            solved: false,
+            times_failed: 1,
+            hints_used: 0,
+            solve_ms: 10_000,
+        };
+        scheduler.record(&puzzle, &failed, 0);
+        assert!(scheduler.rating().rating < 1500.0);
+        assert_eq!(scheduler.queue().len(), 1);
+
+        let day = 24 * 60 * 60 * 1000;
+        assert_eq!(
+            scheduler.next_puzzle(&archive, day).unwrap().unwrap().id,
+            puzzle.id
+        );
+    }
+}
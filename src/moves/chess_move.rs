@@ -28,6 +28,11 @@ impl ChessMove {
         )
     }
 
+    /// A piece dropped from the pocket onto an empty square, the piece is stored in place of the origin square
+    pub fn drop(piece: Piece, to: Square) -> Self {
+        Self::from_flags(Square::new(piece as u8), to, MoveFlags::Drop)
+    }
+
     pub fn promotions(from: Square, to: Square, capture: bool) -> [Self; 4] {
         [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
             .map(|piece| Self::new(from, to, MoveKind::Promotion { piece, capture }))
@@ -50,13 +55,33 @@ impl ChessMove {
     }
 
     pub fn is_capture(&self) -> bool {
-        (self.0 & 0b0100) != 0
+        self.flags().is_capture()
     }
 
     pub fn is_promotion(&self) -> bool {
         (self.0 & 0b1000) != 0
     }
 
+    pub fn is_drop(&self) -> bool {
+        self.flags().is_drop()
+    }
+
+    /// The dropped piece, None if this isn't a drop
+    pub fn drop_piece(&self) -> Option<Piece> {
+        if !self.is_drop() {
+            return None;
+        }
+        Piece::from_bits(self.from().index())
+    }
+
+    /// Long algebraic notation as used by UCI, e.g. `e2e4` or `e7e8q`
+    pub fn to_uci(&self) -> String {
+        match self.flags().promotion_piece() {
+            Some(piece) => format!("{self}{}", piece.char().to_ascii_lowercase()),
+            None => self.to_string(),
+        }
+    }
+
     pub fn from_position(
         position: &Position,
         from: Square,
@@ -73,9 +98,13 @@ impl ChessMove {
     }
 }
 
+/// Formats like `e2e4`, drops like `N@f3`
 impl Display for ChessMove {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}", self.from(), self.to())
+        match self.drop_piece() {
+            Some(piece) => write!(f, "{}@{}", piece.char(), self.to()),
+            None => write!(f, "{}{}", self.from(), self.to()),
+        }
     }
 }
 
@@ -88,6 +117,7 @@ pub enum MoveFlags {
     QueenCastle = 3,
     Capture = 4,
     EnPassant = 5,
+    Drop = 6,
     KnightPromotion = 8,
     BishopPromotion = 9,
     RookPromotion = 10,
@@ -100,7 +130,7 @@ pub enum MoveFlags {
 
 impl MoveFlags {
     pub fn is_capture(&self) -> bool {
-        ((*self as u8) & 0b0100) != 0
+        ((*self as u8) & 0b0100) != 0 && !self.is_drop()
     }
 
     pub fn is_promotion(&self) -> bool {
@@ -128,6 +158,10 @@ impl MoveFlags {
     pub fn is_en_passant(&self) -> bool {
         matches!(self, Self::EnPassant)
     }
+
+    pub fn is_drop(&self) -> bool {
+        matches!(self, Self::Drop)
+    }
 }
 
 impl From<u8> for MoveFlags {
@@ -139,6 +173,7 @@ impl From<u8> for MoveFlags {
             3 => MoveFlags::QueenCastle,
             4 => MoveFlags::Capture,
             5 => MoveFlags::EnPassant,
+            6 => MoveFlags::Drop,
             8 => MoveFlags::KnightPromotion,
             9 => MoveFlags::BishopPromotion,
             10 => MoveFlags::RookPromotion,
@@ -160,7 +195,12 @@ pub enum MoveKind {
     CastleQueen,
     Capture,
     EnPassant,
-    Promotion { piece: Piece, capture: bool },
+    /// The dropped piece is part of the move, see [`ChessMove::drop`]
+    Drop,
+    Promotion {
+        piece: Piece,
+        capture: bool,
+    },
 }
 
 impl From<MoveFlags> for MoveKind {
@@ -172,6 +212,7 @@ impl From<MoveFlags> for MoveKind {
             MoveFlags::QueenCastle => MoveKind::CastleQueen,
             MoveFlags::Capture => MoveKind::Capture,
             MoveFlags::EnPassant => MoveKind::EnPassant,
+            MoveFlags::Drop => MoveKind::Drop,
             MoveFlags::KnightPromotion => MoveKind::Promotion {
                 piece: Piece::Knight,
                 capture: false,
@@ -217,6 +258,7 @@ impl From<MoveKind> for MoveFlags {
             MoveKind::CastleQueen => MoveFlags::QueenCastle,
             MoveKind::Capture => MoveFlags::Capture,
             MoveKind::EnPassant => MoveFlags::EnPassant,
+            MoveKind::Drop => MoveFlags::Drop,
             MoveKind::Promotion { piece, capture } => match (piece, capture) {
                 (Piece::Knight, false) => MoveFlags::KnightPromotion,
                 (Piece::Bishop, false) => MoveFlags::BishopPromotion,
@@ -253,21 +253,41 @@ impl MoveGenerator {
         let Some(king_sq) = king_bb.get_lowest_set() else {
             return BitBoard::empty();
         };
+        self.attackers(&pos.board, king_sq, color.opposite())
+    }
+}
 
-        let by = color.opposite();
-        let board = &pos.board;
+// Attacks
+impl MoveGenerator {
+    /// All pieces of the given color attacking the square
+    pub fn attackers(&self, board: &ChessBoard, square: Square, by: Color) -> BitBoard {
         let occupied = board.occupied_bb();
 
-        let pawns = self.table.pawn_attacks(king_sq, color) & board.piece_bb(Piece::Pawn, by);
-        let knights = self.table.knight_attacks(king_sq) & board.piece_bb(Piece::Knight, by);
-        let king = self.table.king_attacks(king_sq) & board.piece_bb(Piece::King, by);
+        let pawns =
+            self.table.pawn_attacks(square, by.opposite()) & board.piece_bb(Piece::Pawn, by);
+        let knights = self.table.knight_attacks(square) & board.piece_bb(Piece::Knight, by);
+        let king = self.table.king_attacks(square) & board.piece_bb(Piece::King, by);
 
         let diag = board.piece_bb(Piece::Bishop, by) | board.piece_bb(Piece::Queen, by);
-        let bishops = self.table.bishop_attacks(king_sq, occupied) & diag;
+        let bishops = self.table.bishop_attacks(square, occupied) & diag;
 
         let ortho = board.piece_bb(Piece::Rook, by) | board.piece_bb(Piece::Queen, by);
-        let rooks = self.table.rook_attacks(king_sq, occupied) & ortho;
+        let rooks = self.table.rook_attacks(square, occupied) & ortho;
 
         pawns | knights | king | bishops | rooks
     }
+
+    /// The squares attacked by the piece on the given square, empty if there is none
+    pub fn attacks_from(&self, board: &ChessBoard, square: Square) -> BitBoard {
+        let occupied = board.occupied_bb();
+        match board.piece_at(square) {
+            Some((Piece::Pawn, color)) => self.table.pawn_attacks(square, color),
+            Some((Piece::Knight, _)) => self.table.knight_attacks(square),
+            Some((Piece::Bishop, _)) => self.table.bishop_attacks(square, occupied),
+            Some((Piece::Rook, _)) => self.table.rook_attacks(square, occupied),
+            Some((Piece::Queen, _)) => self.table.queen_attacks(square, occupied),
+            Some((Piece::King, _)) => self.table.king_attacks(square),
+            None => BitBoard::empty(),
+        }
+    }
 }
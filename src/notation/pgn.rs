@@ -1,7 +1,14 @@
-use crate::game::outcome::GameOutcome;
-use crate::prelude::{Color, Session};
-use crate::session::config::StartingPosition;
+use crate::analysis::annotation::{GameAnnotation, MoveClassification};
+use crate::core::position::Position;
+use crate::error::{PgnError, PgnResult};
+use crate::game::Game;
+use crate::game::outcome::{DecisiveReason, DrawReason, GameOutcome};
+use crate::moves::generator::MoveGenerator;
+use crate::notation::san::san_to_move;
+use crate::prelude::{ChessMove, Color, Session};
+use crate::session::config::{StartingPosition, TimeControl};
 use std::fmt::Write;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Default, Eq, PartialEq)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
@@ -17,61 +24,242 @@ pub struct PgnHeaders {
     pub round: Option<String>,
     pub white: Option<String>,
     pub black: Option<String>,
+    pub eco: Option<String>,
+    pub opening: Option<String>,
     pub extra: Vec<(String, String)>,
 }
 
+impl PgnHeaders {
+    /// Fills the missing ECO and Opening tags from the opening the game reached.
+    /// The opening table is a subset of the Lichess one, so the tags may name a parent of the opening Lichess reports.
+    ///
+    /// ECO and Opening tags in the extra tags are moved to their fields first, so they aren't written twice.
+    fn with_game_opening(mut self, game: &Game) -> Self {
+        for (key, value) in std::mem::take(&mut self.extra) {
+            match key.as_str() {
+                "ECO" => {
+                    self.eco.get_or_insert(value);
+                }
+                "Opening" => {
+                    self.opening.get_or_insert(value);
+                }
+                _ => self.extra.push((key, value)),
+            }
+        }
+        if let Some(opening) = game.opening() {
+            self.eco.get_or_insert_with(|| opening.eco.to_string());
+            self.opening.get_or_insert_with(|| opening.name.to_string());
+        }
+        self
+    }
+}
+
 pub fn session_pgn(session: &Session) -> String {
     let mut pgn = String::new();
-    let h = &session.config().pgn;
+    let fen = match &session.config().starting_position {
+        StartingPosition::Default => None,
+        StartingPosition::Fen(fen) => Some(fen.as_str()),
+    };
     let result = outcome_pgn(session.game().outcome());
+    let time_control = match &session.config().time_control {
+        TimeControl::Unlimited => None,
+        time_control => Some(time_control.pgn_tag()),
+    };
+    let headers = session
+        .config()
+        .pgn
+        .clone()
+        .with_game_opening(session.game());
+    write_headers(
+        &mut pgn,
+        &headers,
+        fen,
+        time_control.as_deref(),
+        session.game().outcome(),
+    );
+
+    let mut movetext = Movetext::new(&mut pgn, session.game().start_position());
+    for (i, san) in session.san_history().iter().enumerate() {
+        movetext.push_move(san);
+
+        let Some(time) = session.move_times().get(i) else {
+            continue;
+        };
+        let mut comment = String::new();
+        if let Some(remaining_ms) = time.remaining_ms {
+            write!(&mut comment, "[%clk {}]", pgn_duration(remaining_ms)).unwrap();
+        }
+        if let Some(think_ms) = time.think_ms {
+            if !comment.is_empty() {
+                comment.push(' ');
+            }
+            write!(&mut comment, "[%emt {}]", pgn_duration(think_ms)).unwrap();
+        }
+        if !comment.is_empty() {
+            movetext.push_comment(&comment);
+        }
+    }
+    if let Some(reason) = session.adjudication_reason() {
+        movetext.push_comment(&format!("Adjudication: {reason}"));
+    }
+    movetext.finish(result);
+
+    pgn
+}
+
+/// A PGN of the game with `[%eval]` comments and NAGs for every annotated move
+pub fn annotated_pgn(game: &Game, headers: &PgnHeaders, annotation: &GameAnnotation) -> String {
+    let mut pgn = String::new();
+    let start = game.start_position();
+    let fen = (*start != Position::default()).then(|| start.to_string());
+    let result = outcome_pgn(game.outcome());
+    let headers = headers.clone().with_game_opening(game);
+    write_headers(&mut pgn, &headers, fen.as_deref(), None, game.outcome());
+
+    let mut movetext = Movetext::new(&mut pgn, start);
+    for annotated in &annotation.moves {
+        movetext.push_move(&annotated.san);
+        if let Some(nag) = annotated.classification.nag() {
+            movetext.push_nag(nag);
+        }
+
+        let mut comment = format!("[%eval {}]", annotated.eval_after);
+        if let (Some(label), Some(best_san)) = (
+            classification_label(annotated.classification),
+            &annotated.best_san,
+        ) {
+            write!(&mut comment, " {label}. {best_san} was best.").unwrap();
+        }
+        movetext.push_comment(&comment);
+    }
+    movetext.finish(result);
+
+    pgn
+}
+
+fn classification_label(classification: MoveClassification) -> Option<&'static str> {
+    match classification {
+        MoveClassification::Inaccuracy => Some("Inaccuracy"),
+        MoveClassification::Mistake => Some("Mistake"),
+        MoveClassification::Blunder => Some("Blunder"),
+        _ => None,
+    }
+}
 
-    write_tag(&mut pgn, "Event", h.event.as_deref().unwrap_or("?"));
-    write_tag(&mut pgn, "Site", h.site.as_deref().unwrap_or("?"));
-    write_tag(&mut pgn, "Date", h.date.as_deref().unwrap_or("????.??.??"));
-    write_tag(&mut pgn, "Round", h.round.as_deref().unwrap_or("?"));
-    write_tag(&mut pgn, "White", h.white.as_deref().unwrap_or("?"));
-    write_tag(&mut pgn, "Black", h.black.as_deref().unwrap_or("?"));
-    write_tag(&mut pgn, "Result", result);
+fn write_headers(
+    pgn: &mut String,
+    h: &PgnHeaders,
+    fen: Option<&str>,
+    time_control: Option<&str>,
+    outcome: Option<GameOutcome>,
+) {
+    let result = outcome_pgn(outcome);
+    write_tag(pgn, "Event", h.event.as_deref().unwrap_or("?"));
+    write_tag(pgn, "Site", h.site.as_deref().unwrap_or("?"));
+    write_tag(pgn, "Date", h.date.as_deref().unwrap_or("????.??.??"));
+    write_tag(pgn, "Round", h.round.as_deref().unwrap_or("?"));
+    write_tag(pgn, "White", h.white.as_deref().unwrap_or("?"));
+    write_tag(pgn, "Black", h.black.as_deref().unwrap_or("?"));
+    write_tag(pgn, "Result", result);
 
-    if let StartingPosition::Fen(fen) = &session.config().starting_position {
-        write_tag(&mut pgn, "SetUp", "1");
-        write_tag(&mut pgn, "FEN", fen);
+    if let Some(fen) = fen {
+        write_tag(pgn, "SetUp", "1");
+        write_tag(pgn, "FEN", fen);
+    }
+
+    if let Some(eco) = &h.eco {
+        write_tag(pgn, "ECO", eco);
+    }
+
+    if let Some(opening) = &h.opening {
+        write_tag(pgn, "Opening", opening);
+    }
+
+    if let Some(time_control) = time_control {
+        write_tag(pgn, "TimeControl", time_control);
+    }
+
+    if let Some(termination) = termination_pgn(outcome) {
+        write_tag(pgn, "Termination", termination);
     }
 
     for (key, value) in &h.extra {
-        write_tag(&mut pgn, key, value);
+        write_tag(pgn, key, value);
     }
 
     pgn.push('\n');
+}
 
-    let mut line_len = 0;
-    for (i, san) in session.san_history().iter().enumerate() {
+/// Writes move text tokens and wraps lines at 80 characters
+struct Movetext<'a> {
+    pgn: &'a mut String,
+    line_len: usize,
+    empty: bool,
+    color: Color,
+    number: u16,
+    /// Black moves need their move number after comments or at the start
+    needs_number: bool,
+}
+
+impl<'a> Movetext<'a> {
+    fn new(pgn: &'a mut String, start: &Position) -> Self {
+        Self {
+            pgn,
+            line_len: 0,
+            empty: true,
+            color: start.side_to_move,
+            number: start.full_moves,
+            needs_number: true,
+        }
+    }
+
+    fn push_move(&mut self, san: &str) {
         let mut token = String::new();
-        if i % 2 == 0 {
-            write!(&mut token, "{}. ", i / 2 + 1).unwrap();
+        if self.color == Color::White {
+            write!(&mut token, "{}. ", self.number).unwrap();
+        } else if self.needs_number {
+            write!(&mut token, "{}... ", self.number).unwrap();
         }
         token.push_str(san);
+        self.push(&token);
 
-        if line_len + token.len() + 1 > 80 && line_len > 0 {
-            pgn.push('\n');
-            line_len = 0;
-        } else if line_len > 0 {
-            pgn.push(' ');
-            line_len += 1;
+        self.needs_number = false;
+        if self.color == Color::Black {
+            self.number += 1;
         }
+        self.color = self.color.opposite();
+    }
 
-        pgn.push_str(&token);
-        line_len += token.len();
+    fn push_nag(&mut self, nag: u8) {
+        self.push(&format!("${nag}"));
     }
 
-    if !session.san_history().is_empty() {
-        pgn.push(' ');
+    fn push_comment(&mut self, comment: &str) {
+        self.push(&format!("{{ {comment} }}"));
+        self.needs_number = true;
     }
-    pgn.push_str(result);
 
-    pgn.push('\n');
+    fn push(&mut self, token: &str) {
+        if self.line_len + token.len() + 1 > 80 && self.line_len > 0 {
+            self.pgn.push('\n');
+            self.line_len = 0;
+        } else if self.line_len > 0 {
+            self.pgn.push(' ');
+            self.line_len += 1;
+        }
 
-    pgn
+        self.pgn.push_str(token);
+        self.line_len += token.len();
+        self.empty = false;
+    }
+
+    fn finish(self, result: &str) {
+        if !self.empty {
+            self.pgn.push(' ');
+        }
+        self.pgn.push_str(result);
+        self.pgn.push('\n');
+    }
 }
 
 pub fn outcome_pgn(outcome: Option<GameOutcome>) -> &'static str {
@@ -85,12 +273,307 @@ pub fn outcome_pgn(outcome: Option<GameOutcome>) -> &'static str {
             ..
         }) => "0-1",
         Some(GameOutcome::Draw(_)) => "1/2-1/2",
-        None => "*",
+        Some(GameOutcome::Aborted(_)) | None => "*",
     }
 }
 
+/// Formats like `1:05:09`, the format of the `[%clk]` and `[%emt]` commands
+fn pgn_duration(ms: u64) -> String {
+    let seconds = ms / 1000;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// The value of the Termination tag, None while the game is ongoing
+pub fn termination_pgn(outcome: Option<GameOutcome>) -> Option<&'static str> {
+    let termination = match outcome? {
+        GameOutcome::Decisive {
+            reason: DecisiveReason::Timeout,
+            ..
+        }
+        | GameOutcome::Draw(DrawReason::TimeoutVsInsufficient) => "time forfeit",
+        GameOutcome::Decisive {
+            reason: DecisiveReason::Adjudication,
+            ..
+        }
+        | GameOutcome::Draw(DrawReason::Adjudication) => "adjudication",
+        GameOutcome::Aborted(_) => "abandoned",
+        _ => "normal",
+    };
+    Some(termination)
+}
+
 fn write_tag(pgn: &mut String, key: &str, value: &str) {
     writeln!(pgn, "[{key} \"{value}\"]").unwrap();
 }
 
-// ToDo: Parse from PGN
+/// A game read from PGN, its moves were validated against the starting position
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    pub headers: PgnHeaders,
+    /// The FEN tag if the game didn't start from the default position
+    pub fen: Option<String>,
+    /// The game termination marker, falls back to the Result tag
+    pub result: Option<String>,
+    pub moves: Vec<ChessMove>,
+    /// The comments following each move of the main line, empty if there are none
+    pub comments: Vec<String>,
+}
+
+impl PgnGame {
+    pub fn start_position(&self) -> PgnResult<Position> {
+        match &self.fen {
+            Some(fen) => Ok(Position::from_str(fen)?),
+            None => Ok(Position::default()),
+        }
+    }
+
+    pub fn to_game(&self) -> PgnResult<Game> {
+        let mut game = Game::from_position(self.start_position()?);
+        for (ply, mv) in self.moves.iter().enumerate() {
+            game.play_move(*mv).map_err(|_| PgnError::IllegalMove {
+                ply,
+                san: mv.to_uci(),
+            })?;
+        }
+        Ok(game)
+    }
+}
+
+/// Reads all games of a PGN file or string.
+///
+/// NAGs and variations are skipped, only the main line and its comments are kept.
+pub fn parse_pgn(text: &str) -> PgnResult<Vec<PgnGame>> {
+    let mut parser = PgnParser {
+        chars: text.chars().collect(),
+        index: 0,
+    };
+    let mut games = Vec::new();
+    while let Some(game) = parser.next_game()? {
+        games.push(game);
+    }
+    Ok(games)
+}
+
+struct PgnParser {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl PgnParser {
+    fn next_game(&mut self) -> PgnResult<Option<PgnGame>> {
+        let mut game = PgnGame::default();
+        let mut tags = Vec::new();
+        let mut sans = Vec::new();
+        let mut started = false;
+
+        while let Some(c) = self.peek() {
+            match c {
+                c if c.is_whitespace() => self.index += 1,
+                '[' if !sans.is_empty() => break,
+                '[' => {
+                    tags.push(self.read_tag()?);
+                    started = true;
+                }
+                '{' => {
+                    let comment = self.read_comment()?;
+                    if let Some(last) = game.comments.last_mut() {
+                        if !last.is_empty() {
+                            last.push(' ');
+                        }
+                        last.push_str(&comment);
+                    }
+                }
+                ';' | '%' => self.skip_line(),
+                '(' => self.skip_variation()?,
+                ')' => self.index += 1,
+                '$' => {
+                    self.read_word();
+                }
+                _ => {
+                    let word = self.read_word();
+                    started = true;
+                    if matches!(word.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                        game.result = Some(word);
+                        break;
+                    }
+                    let san = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                    if !san.is_empty() {
+                        sans.push(san.to_string());
+                        game.comments.push(String::new());
+                    }
+                }
+            }
+        }
+
+        if !started {
+            return Ok(None);
+        }
+
+        for (key, value) in tags {
+            match key.as_str() {
+                "Event" => game.headers.event = Some(value),
+                "Site" => game.headers.site = Some(value),
+                "Date" => game.headers.date = Some(value),
+                "Round" => game.headers.round = Some(value),
+                "White" => game.headers.white = Some(value),
+                "Black" => game.headers.black = Some(value),
+                "ECO" => game.headers.eco = Some(value),
+                "Opening" => game.headers.opening = Some(value),
+                "Result" => {
+                    game.result.get_or_insert(value);
+                }
+                "FEN" => game.fen = Some(value),
+                "SetUp" => {}
+                _ => game.headers.extra.push((key, value)),
+            }
+        }
+
+        let mut pos = game.start_position()?;
+        for (ply, san) in sans.into_iter().enumerate() {
+            let legal_moves = MoveGenerator::get().generate(&pos);
+            let mv = san_to_move(&pos, &san, &legal_moves)
+                .map_err(|_| PgnError::IllegalMove { ply, san })?;
+            pos = pos.make_move(mv);
+            game.moves.push(mv);
+        }
+
+        Ok(Some(game))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn read_tag(&mut self) -> PgnResult<(String, String)> {
+        let start = self.index;
+        self.skip_until(']', "tag")?;
+        let tag: String = self.chars[start + 1..self.index - 1].iter().collect();
+
+        let (key, value) = tag
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| PgnError::InvalidTag(tag.clone()))?;
+        let value = value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .ok_or_else(|| PgnError::InvalidTag(tag.clone()))?;
+        Ok((key.to_string(), value.replace("\\\"", "\"")))
+    }
+
+    fn read_comment(&mut self) -> PgnResult<String> {
+        let start = self.index;
+        self.skip_until('}', "comment")?;
+        let comment: String = self.chars[start + 1..self.index - 1].iter().collect();
+        Ok(comment.trim().to_string())
+    }
+
+    fn read_word(&mut self) -> String {
+        let start = self.index;
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || "{}()[];".contains(c) {
+                break;
+            }
+            self.index += 1;
+        }
+        self.chars[start..self.index].iter().collect()
+    }
+
+    /// Skips past the terminator, quoted strings are skipped as a whole
+    fn skip_until(&mut self, terminator: char, what: &'static str) -> PgnResult<()> {
+        let mut quoted = false;
+        self.index += 1;
+        while let Some(c) = self.peek() {
+            self.index += 1;
+            match c {
+                '\\' if quoted => self.index += 1,
+                '"' if terminator == ']' => quoted = !quoted,
+                c if c == terminator && !quoted => return Ok(()),
+                _ => {}
+            }
+        }
+        Err(PgnError::Unterminated(what))
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.peek() {
+            self.index += 1;
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn skip_variation(&mut self) -> PgnResult<()> {
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                '{' => {
+                    self.skip_until('}', "comment")?;
+                    continue;
+                }
+                _ => {}
+            }
+            self.index += 1;
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+        Err(PgnError::Unterminated("variation"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notation::pgn::parse_pgn;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_parse_pgn() {
+        let pgn = r#"[Event "Casual Game"]
+[White "Anderssen"]
+[Black "Kieseritzky"]
+[Result "1-0"]
+[ECO "C33"]
+
+1. e4 e5 2. f4 exf4 { King's Gambit } 3. Bc4 Qh4+ $2 (3... Nf6 4. Nc3) 4. Kf1
+b5?! 5. Bxb5 1-0
+
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7 2. e5 *
+"#;
+        let games = parse_pgn(pgn).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let first = &games[0];
+        assert_eq!(first.headers.white.as_deref(), Some("Anderssen"));
+        assert_eq!(first.headers.eco.as_deref(), Some("C33"));
+        assert!(first.headers.extra.is_empty());
+        assert_eq!(first.result.as_deref(), Some("1-0"));
+        assert_eq!(first.moves.len(), 9);
+        assert_eq!((first.moves[6].from(), first.moves[6].to()), (E1, F1));
+        assert_eq!(first.comments[3], "King's Gambit");
+        assert!(first.comments[4].is_empty());
+
+        let second = &games[1];
+        assert_eq!(second.moves.len(), 3);
+        assert_eq!(second.result.as_deref(), Some("*"));
+        let game = second.to_game().unwrap();
+        assert_eq!(
+            game.position().to_string(),
+            "8/3k4/8/4P3/8/8/8/4K3 b - - 0 2"
+        );
+
+        assert!(parse_pgn("1. e4 e5 2. Ke3 *").is_err());
+        assert!(parse_pgn("{ unterminated").is_err());
+    }
+}
@@ -2,10 +2,17 @@ use crate::core::position::Position;
 use crate::error::{ChessError, ChessResult};
 use crate::moves::generator::MoveGenerator;
 use crate::moves::list::MoveList;
-use crate::prelude::{ChessMove, Piece};
+use crate::prelude::{ChessMove, Piece, Square};
+use std::str::FromStr;
 
 pub fn move_to_san(pos: &Position, mv: ChessMove, legal_moves: &MoveList) -> ChessResult<String> {
     let flags = mv.flags();
+    if mv.is_drop() {
+        // Drops are written like `N@f3`, just like their UCI notation
+        let mut san = mv.to_string();
+        push_check_suffix(&mut san, &pos.make_move(mv));
+        return Ok(san);
+    }
     if flags.is_kingside_castle() {
         return Ok("O-O".to_string());
     }
@@ -48,7 +55,7 @@ pub fn move_to_san(pos: &Position, mv: ChessMove, legal_moves: &MoveList) -> Che
             {
                 san.push(mv.from().rank_char());
             } else {
-                san.push(mv.from().rank_char());
+                san.push(mv.from().file_char());
                 san.push(mv.from().rank_char());
             }
         }
@@ -66,17 +73,158 @@ pub fn move_to_san(pos: &Position, mv: ChessMove, legal_moves: &MoveList) -> Che
         san.push(promo.char());
     }
 
-    let new_pos = pos.make_move(mv);
-    if MoveGenerator::get().is_in_check(&new_pos, new_pos.side_to_move) {
-        let new_moves = MoveGenerator::get().generate(&new_pos);
+    push_check_suffix(&mut san, &pos.make_move(mv));
+    Ok(san)
+}
+
+/// Mates are detected without considering drops, as the pockets aren't part of the position
+fn push_check_suffix(san: &mut String, new_pos: &Position) {
+    if MoveGenerator::get().is_in_check(new_pos, new_pos.side_to_move) {
+        let new_moves = MoveGenerator::get().generate(new_pos);
         if new_moves.is_empty() {
             san.push('#');
         } else {
             san.push('+');
         }
     }
+}
 
-    Ok(san)
+/// Finds the legal move described by the SAN string.
+///
+/// Check and annotation suffixes (`+`, `#`, `!`, `?`) are ignored, castling may be written with zeros.
+/// Drops aren't part of the legal moves and can't be parsed.
+pub fn san_to_move(pos: &Position, san: &str, legal_moves: &MoveList) -> ChessResult<ChessMove> {
+    let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    if !san.is_ascii() {
+        return Err(ChessError::IllegalMove);
+    }
+    if let Some(kingside) = match san {
+        "O-O" | "0-0" => Some(true),
+        "O-O-O" | "0-0-0" => Some(false),
+        _ => None,
+    } {
+        return legal_moves
+            .iter()
+            .copied()
+            .find(|mv| {
+                if kingside {
+                    mv.flags().is_kingside_castle()
+                } else {
+                    mv.flags().is_queenside_castle()
+                }
+            })
+            .ok_or(ChessError::IllegalMove);
+    }
+
+    let (piece, rest) = match san.chars().next().and_then(san_piece) {
+        Some(piece) => (piece, &san[1..]),
+        None => (Piece::Pawn, san),
+    };
+    let (rest, promotion) = match rest.chars().last().and_then(san_piece) {
+        Some(promotion) => (
+            rest[..rest.len() - 1].trim_end_matches('='),
+            Some(promotion),
+        ),
+        None => (rest, None),
+    };
+    let split = rest.len().checked_sub(2).ok_or(ChessError::IllegalMove)?;
+    let to = Square::from_str(&rest[split..]).map_err(|_| ChessError::IllegalMove)?;
+
+    // The origin file and rank, as far as the SAN string names them
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in rest[..split].chars().filter(|&c| c != 'x') {
+        match c {
+            'a'..='h' => from_file = Some(c as u8 - b'a' + 1),
+            '1'..='8' => from_rank = Some(c as u8 - b'0'),
+            _ => return Err(ChessError::IllegalMove),
+        }
+    }
+
+    let mut candidates = legal_moves.iter().copied().filter(|mv| {
+        let flags = mv.flags();
+        mv.to() == to
+            && flags.promotion_piece() == promotion
+            && !flags.is_kingside_castle()
+            && !flags.is_queenside_castle()
+            && pos.board.piece_at(mv.from()).map(|(piece, _)| piece) == Some(piece)
+            && from_file.is_none_or(|file| mv.from().file() == file)
+            && from_rank.is_none_or(|rank| mv.from().rank() == rank)
+    });
+    match (candidates.next(), candidates.next()) {
+        (Some(mv), None) => Ok(mv),
+        _ => Err(ChessError::IllegalMove),
+    }
+}
+
+/// Pieces are written in uppercase, lowercase letters are files
+fn san_piece(c: char) -> Option<Piece> {
+    match c {
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
 }
 
-// ToDo: Parse from SAN
+#[cfg(test)]
+mod tests {
+    use crate::core::position::Position;
+    use crate::moves::generator::MoveGenerator;
+    use crate::notation::san::{move_to_san, san_to_move};
+    use crate::prelude::*;
+
+    #[test]
+    fn test_disambiguation() {
+        let pos: Position = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1".parse().unwrap();
+        let legal_moves = MoveGenerator::get().generate(&pos);
+        let san = |from, to| {
+            let mv = *legal_moves
+                .iter()
+                .find(|mv| mv.from() == from && mv.to() == to)
+                .unwrap();
+            move_to_san(&pos, mv, &legal_moves).unwrap()
+        };
+        assert_eq!(san(A1, B2), "Qa1b2");
+        assert_eq!(san(A3, A2), "Q3a2");
+        assert_eq!(san(C1, B1), "Qcb1");
+    }
+
+    #[test]
+    fn test_san_round_trip() {
+        let fens = [
+            "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1",
+            "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        ];
+        for fen in fens {
+            let pos: Position = fen.parse().unwrap();
+            let legal_moves = MoveGenerator::get().generate(&pos);
+            for &mv in legal_moves.iter() {
+                let san = move_to_san(&pos, mv, &legal_moves).unwrap();
+                assert_eq!(san_to_move(&pos, &san, &legal_moves).unwrap(), mv, "{san}");
+            }
+        }
+
+        let pos: Position = "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1"
+            .parse()
+            .unwrap();
+        let legal_moves = MoveGenerator::get().generate(&pos);
+        let parse = |san| san_to_move(&pos, san, &legal_moves).map(|mv| mv.to_uci());
+        assert_eq!(parse("0-0!?").unwrap(), "e1g1");
+        assert_eq!(parse("bxa8Q+").unwrap(), "b7a8q");
+        assert_eq!(parse("exd6").unwrap(), "e5d6");
+        assert_eq!(parse("Rab1").unwrap(), "a1b1");
+        assert!(parse("Kd3").is_err());
+        assert!(parse("N@f3").is_err());
+
+        // Ambiguous moves need their disambiguation
+        let pos: Position = fens[0].parse().unwrap();
+        let legal_moves = MoveGenerator::get().generate(&pos);
+        assert!(san_to_move(&pos, "Qb2", &legal_moves).is_err());
+        assert!(san_to_move(&pos, "Qab2", &legal_moves).is_err());
+        assert!(san_to_move(&pos, "Qa1b2", &legal_moves).is_ok());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::core::position::Position;
+use crate::core::zobrist::ZobristKeys;
+use crate::moves::generator::MoveGenerator;
+use crate::notation::san::san_to_move;
+use crate::prelude::ChessMove;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Named openings in the format of the Lichess `chess-openings` tables (CC0), one per ECO volume:
+/// ECO code, name and moves, separated by tabs.
+///
+/// This is a reduced subset of about 150 of the roughly 3.5k upstream lines, mostly the main families
+/// and their best known variations. Games are classified under the closest opening of the subset,
+/// which is often coarser than the one Lichess reports. Replacing the files with the full upstream
+/// tables needs no code changes.
+const TABLES: [&str; 5] = [
+    include_str!("openings/a.tsv"),
+    include_str!("openings/b.tsv"),
+    include_str!("openings/c.tsv"),
+    include_str!("openings/d.tsv"),
+    include_str!("openings/e.tsv"),
+];
+
+static OPENINGS: LazyLock<OpeningTable> = LazyLock::new(OpeningTable::build);
+
+/// A named opening, identified by the position after its moves
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Opening {
+    pub eco: &'static str,
+    /// Like `Sicilian Defense: Najdorf Variation`
+    pub name: &'static str,
+    /// The moves in SAN, like `1. e4 c5 2. Nf3`
+    pub pgn: &'static str,
+    pub moves: Vec<ChessMove>,
+}
+
+impl Opening {
+    /// The name without the variation, like `Sicilian Defense`
+    pub fn family(&self) -> &'static str {
+        self.name
+            .split_once(':')
+            .map_or(self.name, |(family, _)| family)
+    }
+
+    /// The part of the name after the family, like `Najdorf Variation`
+    pub fn variation(&self) -> Option<&'static str> {
+        self.name
+            .split_once(':')
+            .map(|(_, variation)| variation.trim())
+    }
+}
+
+struct OpeningTable {
+    openings: Vec<Opening>,
+    by_key: HashMap<u64, usize>,
+}
+
+impl OpeningTable {
+    fn build() -> Self {
+        let mut openings = Vec::new();
+        let mut by_key = HashMap::new();
+        let lines = TABLES
+            .iter()
+            .flat_map(|table| table.lines().skip(1))
+            .filter(|line| !line.is_empty());
+        for line in lines {
+            let mut columns = line.split('\t');
+            let (Some(eco), Some(name), Some(pgn)) =
+                (columns.next(), columns.next(), columns.next())
+            else {
+                panic!("Invalid opening table line: {line}");
+            };
+
+            let mut pos = Position::default();
+            let mut moves = Vec::new();
+            for san in pgn.split_whitespace().filter(|token| !token.ends_with('.')) {
+                let legal_moves = MoveGenerator::get().generate(&pos);
+                let mv = san_to_move(&pos, san, &legal_moves)
+                    .unwrap_or_else(|_| panic!("Illegal move {san} in opening {name}"));
+                pos = pos.make_move(mv);
+                moves.push(mv);
+            }
+
+            by_key.entry(key(&pos)).or_insert(openings.len());
+            openings.push(Opening {
+                eco,
+                name,
+                pgn,
+                moves,
+            });
+        }
+        Self { openings, by_key }
+    }
+}
+
+/// All openings of the table, ordered by ECO code.
+/// The table is a reduced subset of the Lichess openings, see [`classify`].
+pub fn all() -> &'static [Opening] {
+    &OPENINGS.openings
+}
+
+/// The opening whose moves lead to the position, no matter in which order they were played.
+///
+/// Only a subset of the Lichess `chess-openings` table is compiled in, so positions deep into a
+/// variation may be unknown even though Lichess names them.
+pub fn classify(pos: &Position) -> Option<&'static Opening> {
+    let index = *OPENINGS.by_key.get(&key(pos))?;
+    Some(&OPENINGS.openings[index])
+}
+
+/// The Zobrist hash without the en passant square, so positions after a double pawn push match their transpositions
+fn key(pos: &Position) -> u64 {
+    pos.hash ^ pos.en_passant_square.map_or(0, ZobristKeys::ep_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Game;
+    use crate::openings;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_classify() {
+        for volume in ['A', 'B', 'C', 'D', 'E'] {
+            assert!(openings::all().iter().any(|o| o.eco.starts_with(volume)));
+        }
+
+        let mut game = Game::new();
+        assert_eq!(game.opening(), None);
+        for (from, to) in [(E2, E4), (C7, C5), (G1, F3), (D7, D6), (D2, D4)] {
+            game.play(from, to).unwrap();
+        }
+        let opening = game.opening().unwrap();
+        assert_eq!(opening.eco, "B50");
+        assert_eq!(opening.family(), "Sicilian Defense");
+        assert_eq!(opening.variation(), Some("Modern Variations"));
+
+        // 1. c4 e6 2. d4 Nf6 3. Nc3 Bb4 transposes into the Nimzo-Indian
+        let mut game = Game::new();
+        for (from, to) in [(C2, C4), (E7, E6), (D2, D4), (G8, F6), (B1, C3), (F8, B4)] {
+            game.play(from, to).unwrap();
+        }
+        assert_eq!(game.opening().unwrap().name, "Nimzo-Indian Defense");
+
+        // Unknown moves keep the last known opening
+        game.play(H2, H4).unwrap();
+        assert_eq!(game.opening().unwrap().eco, "E20");
+    }
+}
@@ -1,18 +1,23 @@
+use crate::analysis::annotation::GameAnnotation;
 use crate::core::position::Position;
 use crate::error::{ChessError, SessionError, SessionResult};
 use crate::game::Game;
-use crate::game::outcome::GameOutcome;
-use crate::prelude::{ChessMove, Color};
-use crate::session::action::SessionAction;
-use crate::session::clock::ChessClock;
+use crate::game::outcome::{AbortReason, DecisiveReason, DrawReason, GameOutcome};
+use crate::game::pocket::Pocket;
+use crate::moves::generator::MoveGenerator;
+use crate::prelude::{ChessMove, Color, Piece};
+use crate::session::action::{Premove, SessionAction};
+use crate::session::clock::{ChessClock, MoveTime};
 use crate::session::config::{SessionConfig, StartingPosition, TimeControl};
 use crate::session::event::SessionEvent;
 use std::str::FromStr;
 
 pub mod action;
+pub mod bughouse;
 pub mod clock;
 pub mod config;
 pub mod event;
+pub mod snapshot;
 
 /// A wrapper for chess games that provides a more useful interface for using a game in practice such as
 /// - Validating the color that's trying to move
@@ -28,9 +33,22 @@ pub struct Session {
     game: Game,
     config: SessionConfig,
     draw_offer: Option<Color>,
+    takeback_request: Option<Color>,
     clock: Option<ChessClock>,
+    /// The clock state before every move, used to restore the clock on takebacks
+    clock_history: Vec<ChessClock>,
     /// History of moves played in SAN
     san_history: Vec<String>,
+    /// The time data of every move played
+    move_times: Vec<MoveTime>,
+    /// Milliseconds since UNIX epoch
+    started_at_ms: Option<u64>,
+    /// Milliseconds since UNIX epoch
+    last_move_at_ms: Option<u64>,
+    /// Why an arbiter or adjudication rule ended the game
+    adjudication_reason: Option<String>,
+    /// Queued premoves of the side that's not to move
+    premoves: Vec<Premove>,
 }
 
 impl Session {
@@ -43,37 +61,74 @@ impl Session {
         };
         let game = Game::from_position(position).with_mode(config.mode);
 
-        let clock = match config.time_control {
+        let clock = match &config.time_control {
             TimeControl::Unlimited => None,
-            TimeControl::Clock(config) => Some(ChessClock::from_config(&config)),
+            TimeControl::Clock(config) => Some(ChessClock::from_config(config)),
         };
 
         Ok(Self {
             game,
             config: config.clone(),
             draw_offer: None,
+            takeback_request: None,
             clock,
+            clock_history: vec![],
             san_history: vec![],
+            move_times: vec![],
+            started_at_ms: None,
+            last_move_at_ms: None,
+            adjudication_reason: None,
+            premoves: vec![],
         })
     }
 
+    /// Marks the start of the game, the first move timeout of white counts from here
+    pub fn start(&mut self, unix_ms: u64) {
+        self.started_at_ms.get_or_insert(unix_ms);
+    }
+
+    /// Applies the action of the given color, a single action can cause multiple events
+    /// e.g. a move that ends the game yields both the move and the game over event.
     pub fn act(
         &mut self,
         color: Color,
         action: SessionAction,
         unix_ms: u64,
-    ) -> SessionResult<SessionEvent> {
+    ) -> SessionResult<Vec<SessionEvent>> {
+        self.act_with_lag(color, action, unix_ms, 0)
+    }
+
+    /// Like [`Session::act`] but credits the network lag the client reported back to its clock,
+    /// as far as the clock's lag compensation allows.
+    pub fn act_with_lag(
+        &mut self,
+        color: Color,
+        action: SessionAction,
+        unix_ms: u64,
+        lag_ms: u64,
+    ) -> SessionResult<Vec<SessionEvent>> {
         if self.game.is_over() {
             return Err(SessionError::GameOver);
         }
+        if let Some(event) = self.tick(unix_ms) {
+            return Ok(vec![event]);
+        }
+        let is_move = matches!(
+            action,
+            SessionAction::Move(_) | SessionAction::MoveFromTo { .. }
+        );
+        if is_move && self.clock.as_ref().is_some_and(|clock| clock.is_paused()) {
+            return Err(SessionError::ClockPaused);
+        }
 
+        let mut events = Vec::new();
         match action {
             SessionAction::Move(mv) => {
                 if color != self.game.position().side_to_move {
                     return Err(SessionError::NotMovingColor);
                 }
-                self.process_move(mv, unix_ms)?;
-                Ok(self.outcome_event())
+                self.process_move(mv, unix_ms, lag_ms, &mut events)?;
+                self.play_premove(unix_ms, &mut events)?;
             }
             SessionAction::MoveFromTo {
                 from,
@@ -81,49 +136,64 @@ impl Session {
                 promotion,
             } => {
                 if color != self.game.position().side_to_move {
-                    return Err(SessionError::NotMovingColor);
+                    let premove = Premove {
+                        from,
+                        to,
+                        promotion,
+                    };
+                    self.queue_premove(color, premove)?;
+                    events.push(SessionEvent::PremoveQueued { by: color, premove });
+                    return Ok(events);
                 }
                 let mv = self
                     .game
                     .find_move(from, to, promotion)
                     .ok_or(ChessError::IllegalMove)?;
-                self.process_move(mv, unix_ms)?;
-                Ok(self.outcome_event())
+                self.process_move(mv, unix_ms, lag_ms, &mut events)?;
+                self.play_premove(unix_ms, &mut events)?;
+            }
+            SessionAction::CancelPremoves => {
+                if color == self.turn() {
+                    return Ok(events);
+                }
+                self.discard_premoves(color, &mut events);
             }
             SessionAction::Resign => {
                 self.game.resign(color);
-                Ok(self.outcome_event())
+            }
+            SessionAction::Abort => {
+                if self.game.history().len() >= 2 {
+                    return Err(SessionError::AbortNotAllowed);
+                }
+                self.game.abort(AbortReason::Requested);
             }
             SessionAction::OfferDraw => {
                 if self.draw_offer == Some(color) {
-                    Err(SessionError::DrawAlreadyOffered)
+                    return Err(SessionError::DrawAlreadyOffered);
                 } else if self.draw_offer == Some(color.opposite()) {
                     self.game.agree_draw();
-                    Ok(self.outcome_event())
                 } else {
                     self.draw_offer = Some(color);
-                    Ok(SessionEvent::DrawOffered { by: color })
+                    events.push(SessionEvent::DrawOffered { by: color });
                 }
             }
             SessionAction::AcceptDraw => {
                 if self.draw_offer == Some(color.opposite()) {
                     self.game.agree_draw();
-                    Ok(self.outcome_event())
                 } else {
-                    Err(SessionError::NoDrawOffer)
+                    return Err(SessionError::NoDrawOffer);
                 }
             }
             SessionAction::DeclineDraw => {
                 if self.draw_offer == Some(color.opposite()) {
                     self.draw_offer = None;
-                    Ok(SessionEvent::DrawOfferDeclined { by: color })
+                    events.push(SessionEvent::DrawOfferDeclined { by: color });
                 } else {
-                    Err(SessionError::NoDrawOffer)
+                    return Err(SessionError::NoDrawOffer);
                 }
             }
             SessionAction::ClaimDraw => {
                 self.game.claim_draw()?;
-                Ok(self.outcome_event())
             }
             SessionAction::ClaimTimeout => {
                 let turn = self.game.position().side_to_move;
@@ -131,36 +201,294 @@ impl Session {
                     && clock.is_out_of_time(turn, unix_ms)
                 {
                     self.game.timeout(turn);
-                    Ok(self.outcome_event())
                 } else {
-                    Err(SessionError::NotOutOfTime)
+                    return Err(SessionError::NotOutOfTime);
+                }
+            }
+            SessionAction::RequestTakeback => {
+                if self.takeback_request == Some(color) {
+                    return Err(SessionError::TakebackAlreadyRequested);
+                }
+                self.takeback_plies(color)?;
+                self.takeback_request = Some(color);
+                events.push(SessionEvent::TakebackRequested { by: color });
+            }
+            SessionAction::AcceptTakeback => {
+                let Some(requester) = self.takeback_request.filter(|by| *by != color) else {
+                    return Err(SessionError::NoTakebackRequest);
+                };
+                let plies = self.takeback_plies(requester)?;
+                self.take_back(plies, unix_ms, &mut events);
+                events.push(SessionEvent::TakebackAccepted { by: color, plies });
+            }
+            SessionAction::DeclineTakeback => {
+                if self.takeback_request == Some(color.opposite()) {
+                    self.takeback_request = None;
+                    events.push(SessionEvent::TakebackDeclined { by: color });
+                } else {
+                    return Err(SessionError::NoTakebackRequest);
                 }
             }
         }
-    }
 
-    fn outcome_event(&self) -> SessionEvent {
         if let Some(outcome) = self.game.outcome() {
-            SessionEvent::GameOver(outcome)
+            events.push(SessionEvent::GameOver(outcome));
+        }
+        Ok(events)
+    }
+
+    /// Ends the game by decision of an arbiter or adjudication rule, a draw if there is no winner
+    pub fn adjudicate(
+        &mut self,
+        winner: Option<Color>,
+        reason: impl Into<String>,
+    ) -> SessionResult<SessionEvent> {
+        if self.game.is_over() {
+            return Err(SessionError::GameOver);
+        }
+        let outcome = match winner {
+            Some(winner) => GameOutcome::Decisive {
+                winner,
+                reason: DecisiveReason::Adjudication,
+            },
+            None => GameOutcome::Draw(DrawReason::Adjudication),
+        };
+        self.game.force_outcome(outcome);
+        self.adjudication_reason = Some(reason.into());
+        Ok(SessionEvent::GameOver(outcome))
+    }
+
+    /// A new session with the same config in which the players switch colors
+    pub fn rematch(&self) -> SessionResult<Session> {
+        if !self.game.is_over() {
+            return Err(SessionError::GameNotOver);
+        }
+        let mut config = self.config.clone();
+        std::mem::swap(&mut config.pgn.white, &mut config.pgn.black);
+        Session::from_config(&config)
+    }
+
+    /// Stops the clock, e.g. for adjournments or disconnects, moves are rejected until it's resumed
+    pub fn pause(&mut self, unix_ms: u64) -> SessionResult<SessionEvent> {
+        if self.game.is_over() {
+            return Err(SessionError::GameOver);
+        }
+        let clock = self.clock.as_mut().ok_or(SessionError::NoClock)?;
+        if !clock.pause(unix_ms) {
+            return Err(SessionError::ClockPaused);
+        }
+        Ok(SessionEvent::ClockPaused)
+    }
+
+    pub fn resume(&mut self, unix_ms: u64) -> SessionResult<SessionEvent> {
+        if self.game.is_over() {
+            return Err(SessionError::GameOver);
+        }
+        let clock = self.clock.as_mut().ok_or(SessionError::NoClock)?;
+        if !clock.resume(unix_ms) {
+            return Err(SessionError::ClockNotPaused);
+        }
+        Ok(SessionEvent::ClockResumed)
+    }
+
+    /// Ends the game if the side to move ran out of time or missed its first move window.
+    /// Servers should call this once the [`Session::next_deadline_ms`] passed.
+    pub fn tick(&mut self, unix_ms: u64) -> Option<SessionEvent> {
+        if self.game.is_over() {
+            return None;
+        }
+
+        if self
+            .first_move_deadline_ms()
+            .is_some_and(|deadline| unix_ms >= deadline)
+        {
+            self.game.abort(AbortReason::FirstMoveTimeout);
         } else {
-            SessionEvent::BoardUpdate
+            let turn = self.game.position().side_to_move;
+            let clock = self.clock.as_ref()?;
+            if !clock.is_out_of_time(turn, unix_ms) {
+                return None;
+            }
+            self.game.timeout(turn);
         }
+
+        self.game.outcome().map(SessionEvent::GameOver)
     }
 
-    fn process_move(&mut self, mv: ChessMove, unix_ms: u64) -> SessionResult<()> {
+    /// The next point in time at which [`Session::tick`] would end the game if no move is made
+    pub fn next_deadline_ms(&self) -> Option<u64> {
+        if self.game.is_over() {
+            return None;
+        }
+        let flag_deadline = self
+            .clock
+            .as_ref()
+            .and_then(|clock| clock.flag_deadline_ms());
+        match (self.first_move_deadline_ms(), flag_deadline) {
+            (Some(first), Some(flag)) => Some(first.min(flag)),
+            (first, flag) => first.or(flag),
+        }
+    }
+
+    /// Until when the side to move has to make its first move, None if it already moved or there is no limit
+    pub fn first_move_deadline_ms(&self) -> Option<u64> {
+        let timeout_ms = self.config.first_move_timeout_ms?;
+        let window_start = match self.game.history().len() {
+            0 => self.started_at_ms?,
+            1 => self.last_move_at_ms?,
+            _ => return None,
+        };
+        Some(window_start + timeout_ms)
+    }
+
+    fn process_move(
+        &mut self,
+        mv: ChessMove,
+        unix_ms: u64,
+        lag_ms: u64,
+        events: &mut Vec<SessionEvent>,
+    ) -> SessionResult<()> {
         let color = self.game.position().side_to_move;
+        let captured = if mv.flags().is_en_passant() {
+            Some(Piece::Pawn)
+        } else {
+            self.game
+                .position()
+                .board
+                .piece_at_with_color(mv.to(), color.opposite())
+        };
         let san = self.game.play_move_get_san(mv)?;
-        self.san_history.push(san);
-        self.draw_offer = None;
+        self.san_history.push(san.clone());
+        if let Some(clock) = &self.clock {
+            self.clock_history.push(clock.clone());
+        }
+        let think_ms = self
+            .clock
+            .as_ref()
+            .and_then(|clock| clock.turn_elapsed_ms(unix_ms))
+            .or_else(|| {
+                let turn_start = self.last_move_at_ms.or(self.started_at_ms)?;
+                Some(unix_ms.saturating_sub(turn_start))
+            });
+        self.takeback_request = None;
+        self.last_move_at_ms = Some(unix_ms);
 
-        if let Some(clock) = &mut self.clock
-            && clock.switch(unix_ms)
-        {
-            self.game.timeout(color);
+        match self.draw_offer.take() {
+            Some(by) if by == color => events.push(SessionEvent::DrawOfferWithdrawn { by }),
+            Some(_) => events.push(SessionEvent::DrawOfferDeclined { by: color }),
+            None => {}
+        }
+
+        let mut clock_after = None;
+        if let Some(clock) = &mut self.clock {
+            if clock.switch_with_lag(unix_ms, lag_ms) {
+                self.game.timeout(color);
+            }
+            clock_after = Some(clock.remaining_ms(color, unix_ms));
+        }
+        self.move_times.push(MoveTime {
+            played_at_ms: unix_ms,
+            think_ms,
+            remaining_ms: clock_after,
+        });
+
+        let pos = self.game.position();
+        events.push(SessionEvent::MovePlayed {
+            color,
+            mv,
+            san,
+            captured,
+            check: MoveGenerator::get().is_in_check(pos, pos.side_to_move),
+            clock_after,
+        });
+        if let Some(clock) = &self.clock {
+            events.push(SessionEvent::ClockUpdate {
+                white_ms: clock.remaining_ms(Color::White, unix_ms),
+                black_ms: clock.remaining_ms(Color::Black, unix_ms),
+            });
         }
 
         Ok(())
     }
+
+    /// Premoves are only checked for the piece on the origin square, the rest is validated once they're played
+    fn queue_premove(&mut self, color: Color, premove: Premove) -> SessionResult<()> {
+        if self.config.premove_charge_ms.is_none() {
+            return Err(SessionError::NotMovingColor);
+        }
+        if self
+            .game
+            .position()
+            .board
+            .piece_at_with_color(premove.from, color)
+            .is_none()
+        {
+            return Err(ChessError::IllegalMove.into());
+        }
+        self.premoves.push(premove);
+        Ok(())
+    }
+
+    /// Plays the next premove of the side to move, all premoves are discarded if it became illegal
+    fn play_premove(&mut self, unix_ms: u64, events: &mut Vec<SessionEvent>) -> SessionResult<()> {
+        // The opponent just moved, so the premoves belong to the side to move
+        let by = self.turn();
+        if self.game.is_over() {
+            self.discard_premoves(by, events);
+            return Ok(());
+        }
+        let Some(premove) = self.premoves.first().copied() else {
+            return Ok(());
+        };
+        let Some(mv) = self
+            .game
+            .find_move(premove.from, premove.to, premove.promotion)
+        else {
+            self.discard_premoves(by, events);
+            return Ok(());
+        };
+
+        self.premoves.remove(0);
+        let charge_ms = self.config.premove_charge_ms.unwrap_or_default();
+        if let Some(clock) = &mut self.clock {
+            clock.restart_turn(unix_ms.saturating_sub(charge_ms));
+        }
+        self.process_move(mv, unix_ms, 0, events)
+    }
+
+    fn discard_premoves(&mut self, by: Color, events: &mut Vec<SessionEvent>) {
+        for premove in self.premoves.drain(..) {
+            events.push(SessionEvent::PremoveDiscarded { by, premove });
+        }
+    }
+
+    /// How many plies have to be taken back until it's the requesting side's turn again
+    fn takeback_plies(&self, requester: Color) -> SessionResult<u8> {
+        let plies = if self.turn() == requester { 2 } else { 1 };
+        if self.game.history().len() < plies as usize {
+            return Err(SessionError::NoMoveToTakeBack);
+        }
+        Ok(plies)
+    }
+
+    fn take_back(&mut self, plies: u8, unix_ms: u64, events: &mut Vec<SessionEvent>) {
+        // The premoves belong to the side that's not to move before undoing
+        self.discard_premoves(self.turn().opposite(), events);
+        for _ in 0..plies {
+            self.game.undo_move();
+            self.san_history.pop();
+            self.move_times.pop();
+            if let Some(clock) = self.clock_history.pop() {
+                self.clock = Some(clock);
+            }
+        }
+        if let Some(clock) = &mut self.clock {
+            clock.restart_turn(unix_ms);
+        }
+        self.takeback_request = None;
+        self.draw_offer = None;
+        self.last_move_at_ms = Some(unix_ms);
+    }
 }
 
 // Accessors
@@ -177,18 +505,39 @@ impl Session {
         self.draw_offer
     }
 
+    pub fn takeback_request(&self) -> Option<Color> {
+        self.takeback_request
+    }
+
     pub fn clock(&self) -> Option<&ChessClock> {
         self.clock.as_ref()
     }
 
+    pub fn premoves(&self) -> &[Premove] {
+        &self.premoves
+    }
+
+    pub fn adjudication_reason(&self) -> Option<&str> {
+        self.adjudication_reason.as_deref()
+    }
+
     pub fn san_history(&self) -> &[String] {
         &self.san_history
     }
 
+    pub fn move_times(&self) -> &[MoveTime] {
+        &self.move_times
+    }
+
     pub fn pgn(&self) -> String {
         crate::notation::pgn::session_pgn(self)
     }
 
+    /// The PGN of this session's game with the evaluations and classifications of an annotation
+    pub fn annotated_pgn(&self, annotation: &GameAnnotation) -> String {
+        crate::notation::pgn::annotated_pgn(&self.game, &self.config.pgn, annotation)
+    }
+
     pub fn turn(&self) -> Color {
         self.game.position().side_to_move
     }
@@ -197,12 +546,25 @@ impl Session {
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
 pub struct SessionRecord {
     pub config: SessionConfig,
     pub draw_offer: Option<Color>,
+    pub takeback_request: Option<Color>,
     pub clock: Option<ChessClock>,
+    pub clock_history: Vec<ChessClock>,
+    pub started_at_ms: Option<u64>,
+    pub last_move_at_ms: Option<u64>,
     pub moves: Vec<ChessMove>,
+    pub move_times: Vec<MoveTime>,
     pub outcome: Option<GameOutcome>,
+    pub adjudication_reason: Option<String>,
+    pub premoves: Vec<Premove>,
+    /// The pieces left to drop, only used in modes with drops
+    pub pocket: Pocket,
 }
 
 impl Session {
@@ -211,36 +573,98 @@ impl Session {
         SessionRecord {
             config: self.config.clone(),
             draw_offer: self.draw_offer,
-            clock: self.clock,
+            takeback_request: self.takeback_request,
+            clock: self.clock.clone(),
+            clock_history: self.clock_history.clone(),
+            started_at_ms: self.started_at_ms,
+            last_move_at_ms: self.last_move_at_ms,
             moves: self.game.history().to_vec(),
+            move_times: self.move_times.clone(),
             outcome: self.game.outcome(),
+            adjudication_reason: self.adjudication_reason.clone(),
+            premoves: self.premoves.clone(),
+            pocket: *self.game.pocket(),
         }
     }
 }
 
 impl SessionRecord {
+    /// A record of a game played elsewhere, the clock is replayed from the times the moves were played at
+    pub fn from_moves(
+        config: SessionConfig,
+        moves: Vec<ChessMove>,
+        move_times: Vec<MoveTime>,
+        started_at_ms: Option<u64>,
+        outcome: Option<GameOutcome>,
+    ) -> Self {
+        let mut clock = match &config.time_control {
+            TimeControl::Unlimited => None,
+            TimeControl::Clock(config) => Some(ChessClock::from_config(config)),
+        };
+        let mut clock_history = Vec::new();
+        for time in &move_times {
+            if let Some(clock) = &mut clock {
+                clock_history.push(clock.clone());
+                clock.switch(time.played_at_ms);
+            }
+        }
+
+        Self {
+            config,
+            draw_offer: None,
+            takeback_request: None,
+            clock,
+            clock_history,
+            started_at_ms,
+            last_move_at_ms: move_times.last().map(|time| time.played_at_ms),
+            moves,
+            move_times,
+            outcome,
+            adjudication_reason: None,
+            premoves: vec![],
+            pocket: Pocket::default(),
+        }
+    }
+
     pub fn restore(self) -> SessionResult<Session> {
         let mut session = Session::from_config(&self.config)?;
+        // Pauses and lag credit aren't recorded, so the moves are replayed without the clock
+        session.clock = None;
         for mv in self.moves {
-            session.process_move(mv, 0)?;
+            // Dropped pieces were received from outside the session, e.g. the partner board in Bughouse
+            if let Some(piece) = mv.drop_piece() {
+                let color = session.game.position().side_to_move;
+                session.game.add_to_pocket(color, piece);
+            }
+            session.process_move(mv, 0, 0, &mut Vec::new())?;
         }
+        session.game = std::mem::take(&mut session.game).with_pocket(self.pocket);
         if let Some(outcome) = self.outcome {
             session.game.force_outcome(outcome);
         }
         session.draw_offer = self.draw_offer;
+        session.takeback_request = self.takeback_request;
         session.clock = self.clock;
+        session.clock_history = self.clock_history;
+        session.move_times = self.move_times;
+        session.adjudication_reason = self.adjudication_reason;
+        session.premoves = self.premoves;
+        session.started_at_ms = self.started_at_ms;
+        session.last_move_at_ms = self.last_move_at_ms;
         Ok(session)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::error::SessionError;
     use crate::game::mode::GameMode;
-    use crate::game::outcome::{DecisiveReason, GameOutcome};
+    use crate::game::outcome::{AbortReason, DecisiveReason, DrawReason, GameOutcome};
     use crate::prelude::*;
-    use crate::session::action::SessionAction;
-    use crate::session::clock::ChessClockConfig;
+    use crate::session::action::{Premove, SessionAction};
+    use crate::session::clock::{ChessClockConfig, MoveTime};
     use crate::session::config::{SessionConfig, StartingPosition, TimeControl};
+    use crate::session::event::SessionEvent;
 
     fn test_config() -> SessionConfig {
         SessionConfig {
@@ -248,6 +672,8 @@ mod tests {
             starting_position: StartingPosition::Default,
             time_control: TimeControl::Unlimited,
             pgn: Default::default(),
+            first_move_timeout_ms: None,
+            premove_charge_ms: None,
         }
     }
 
@@ -471,6 +897,7 @@ mod tests {
             black_ms: 300_000,
             white_inc_ms: 2_000,
             black_inc_ms: 2_000,
+            ..Default::default()
         });
 
         let mut session = Session::from_config(&config).unwrap();
@@ -518,4 +945,550 @@ mod tests {
             restored_clock.remaining_ms(Color::Black, 10000)
         );
     }
+
+    #[test]
+    fn test_move_times_and_clock_comments() {
+        let mut config = test_config();
+        config.time_control = TimeControl::Clock(ChessClockConfig::fischer(300_000, 2_000));
+        let mut session = Session::from_config(&config).unwrap();
+        for (color, from, to, unix_ms) in
+            [(Color::White, E2, E4, 5000), (Color::Black, E7, E5, 8000)]
+        {
+            let action = SessionAction::MoveFromTo {
+                from,
+                to,
+                promotion: None,
+            };
+            session.act(color, action, unix_ms).unwrap();
+        }
+
+        assert_eq!(
+            session.move_times()[1],
+            MoveTime {
+                played_at_ms: 8000,
+                think_ms: Some(3000),
+                remaining_ms: Some(299_000),
+            }
+        );
+        let restored = session.record().restore().unwrap();
+        assert_eq!(restored.move_times(), session.move_times());
+        assert!(
+            session
+                .pgn()
+                .contains("1. e4 { [%clk 0:05:00] } 1... e5 { [%clk 0:04:59] [%emt 0:00:03] }")
+        );
+    }
+
+    #[test]
+    fn test_pgn_time_control_tag() {
+        let session = Session::from_config(&test_config()).unwrap();
+        assert!(!session.pgn().contains("[TimeControl "));
+
+        let mut config = test_config();
+        config.time_control = TimeControl::Clock(ChessClockConfig::fischer(300_000, 2_000));
+        let session = Session::from_config(&config).unwrap();
+        assert!(session.pgn().contains("[TimeControl \"300+2\"]"));
+    }
+
+    #[test]
+    fn test_pgn_opening_tags() {
+        let mut session = Session::from_config(&test_config()).unwrap();
+        for (color, from, to) in [(Color::White, E2, E4), (Color::Black, E7, E5)] {
+            let action = SessionAction::MoveFromTo {
+                from,
+                to,
+                promotion: None,
+            };
+            session.act(color, action, 0).unwrap();
+        }
+        assert!(
+            session
+                .pgn()
+                .contains("[ECO \"C20\"]\n[Opening \"King's Pawn Game\"]")
+        );
+
+        // Tags given as extra tags take precedence and aren't written twice
+        let mut config = test_config();
+        config.pgn.extra = vec![
+            ("ECO".to_string(), "C44".to_string()),
+            ("Annotator".to_string(), "Me".to_string()),
+        ];
+        let mut session = Session::from_config(&config).unwrap();
+        session
+            .act(
+                Color::White,
+                SessionAction::MoveFromTo {
+                    from: E2,
+                    to: E4,
+                    promotion: None,
+                },
+                0,
+            )
+            .unwrap();
+        let pgn = session.pgn();
+        assert_eq!(pgn.matches("[ECO ").count(), 1);
+        assert!(pgn.contains("[ECO \"C44\"]\n[Opening \"King's Pawn Game\"]"));
+        assert!(pgn.contains("[Annotator \"Me\"]"));
+    }
+
+    #[test]
+    fn test_restore_paused_game() {
+        let mut config = test_config();
+        config.time_control = TimeControl::Clock(ChessClockConfig::fischer(10_000, 0));
+        let mut session = Session::from_config(&config).unwrap();
+        let play = |session: &mut Session, color, from, to, unix_ms| {
+            let action = SessionAction::MoveFromTo {
+                from,
+                to,
+                promotion: None,
+            };
+            session.act(color, action, unix_ms).unwrap();
+        };
+
+        play(&mut session, Color::White, E2, E4, 1_000);
+        play(&mut session, Color::Black, E7, E5, 2_000);
+        session.pause(3_000).unwrap();
+        session.resume(60_000).unwrap();
+        play(&mut session, Color::White, G1, F3, 61_000);
+        play(&mut session, Color::Black, B8, C6, 62_000);
+        assert_eq!(session.game().outcome(), None);
+
+        let restored = session.record().restore().unwrap();
+        assert_eq!(restored.game().outcome(), None);
+        assert_eq!(restored.game().position(), session.game().position());
+        assert_eq!(restored.clock(), session.clock());
+        assert_eq!(restored.move_times(), session.move_times());
+    }
+
+    #[test]
+    fn test_move_events() {
+        let mut config = test_config();
+        config.time_control = TimeControl::Clock(ChessClockConfig {
+            white_ms: 60_000,
+            black_ms: 60_000,
+            white_inc_ms: 1_000,
+            black_inc_ms: 1_000,
+            ..Default::default()
+        });
+        let mut session = Session::from_config(&config).unwrap();
+
+        let play = |session: &mut Session, color, from, to, unix_ms| {
+            session
+                .act(
+                    color,
+                    SessionAction::MoveFromTo {
+                        from,
+                        to,
+                        promotion: None,
+                    },
+                    unix_ms,
+                )
+                .unwrap()
+        };
+
+        play(&mut session, Color::White, F2, F3, 0);
+        session
+            .act(Color::White, SessionAction::OfferDraw, 1_000)
+            .unwrap();
+        let events = play(&mut session, Color::Black, E7, E5, 2_000);
+        assert_eq!(
+            events[0],
+            SessionEvent::DrawOfferDeclined { by: Color::Black }
+        );
+        assert_eq!(
+            events[2],
+            SessionEvent::ClockUpdate {
+                white_ms: 60_000,
+                black_ms: 59_000
+            }
+        );
+
+        session
+            .act(Color::White, SessionAction::OfferDraw, 3_000)
+            .unwrap();
+        let events = play(&mut session, Color::White, G2, G4, 4_000);
+        assert_eq!(
+            events[0],
+            SessionEvent::DrawOfferWithdrawn { by: Color::White }
+        );
+
+        let events = play(&mut session, Color::Black, D8, H4, 5_000);
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            &events[0],
+            SessionEvent::MovePlayed {
+                color: Color::Black,
+                san,
+                captured: None,
+                check: true,
+                clock_after: Some(59_000),
+                ..
+            } if san == "Qh4#"
+        ));
+        assert_eq!(
+            events[2],
+            SessionEvent::GameOver(GameOutcome::Decisive {
+                winner: Color::Black,
+                reason: DecisiveReason::Checkmate
+            })
+        );
+    }
+
+    #[test]
+    fn test_takeback() {
+        let mut config = test_config();
+        config.time_control = TimeControl::Clock(ChessClockConfig {
+            white_ms: 60_000,
+            black_ms: 60_000,
+            white_inc_ms: 0,
+            black_inc_ms: 0,
+            ..Default::default()
+        });
+        let mut session = Session::from_config(&config).unwrap();
+        let play = |session: &mut Session, color, from, to, unix_ms| {
+            session
+                .act(
+                    color,
+                    SessionAction::MoveFromTo {
+                        from,
+                        to,
+                        promotion: None,
+                    },
+                    unix_ms,
+                )
+                .unwrap();
+        };
+
+        assert!(matches!(
+            session.act(Color::White, SessionAction::RequestTakeback, 0),
+            Err(SessionError::NoMoveToTakeBack)
+        ));
+
+        play(&mut session, Color::White, E2, E4, 0);
+        play(&mut session, Color::Black, E7, E5, 5_000);
+        let clock_after_e5 = session.clock().unwrap().clone();
+        play(&mut session, Color::White, G1, F3, 15_000);
+
+        // Black asks to take back white's last move, which is a single ply
+        session
+            .act(Color::Black, SessionAction::RequestTakeback, 16_000)
+            .unwrap();
+        assert!(matches!(
+            session.act(Color::Black, SessionAction::AcceptTakeback, 16_000),
+            Err(SessionError::NoTakebackRequest)
+        ));
+        session
+            .act(Color::White, SessionAction::DeclineTakeback, 16_000)
+            .unwrap();
+        assert_eq!(session.takeback_request(), None);
+
+        // White asks to take back its own move
+        session
+            .act(Color::White, SessionAction::RequestTakeback, 17_000)
+            .unwrap();
+        let events = session
+            .act(Color::Black, SessionAction::AcceptTakeback, 20_000)
+            .unwrap();
+        assert_eq!(
+            events,
+            [SessionEvent::TakebackAccepted {
+                by: Color::Black,
+                plies: 1
+            }]
+        );
+        assert_eq!(session.san_history(), ["e4", "e5"]);
+        assert_eq!(session.turn(), Color::White);
+        let clock = session.clock().unwrap();
+        assert_eq!(
+            clock.remaining_ms(Color::White, 20_000),
+            clock_after_e5.remaining_ms(Color::White, 5_000)
+        );
+
+        // Black asks to take back its own move after white already replied, so two plies are undone
+        play(&mut session, Color::White, G1, F3, 22_000);
+        play(&mut session, Color::Black, B8, C6, 23_000);
+        play(&mut session, Color::White, D2, D4, 24_000);
+        session
+            .act(Color::Black, SessionAction::RequestTakeback, 25_000)
+            .unwrap();
+        let restored = session.record().restore().unwrap();
+        assert_eq!(restored.takeback_request(), Some(Color::Black));
+
+        let events = session
+            .act(Color::White, SessionAction::AcceptTakeback, 26_000)
+            .unwrap();
+        assert_eq!(
+            events,
+            [SessionEvent::TakebackAccepted {
+                by: Color::White,
+                plies: 2
+            }]
+        );
+        assert_eq!(session.san_history(), ["e4", "e5", "Nf3"]);
+        assert_eq!(session.turn(), Color::Black);
+    }
+
+    #[test]
+    fn test_first_move_abort_and_pause() {
+        let mut config = test_config();
+        config.first_move_timeout_ms = Some(30_000);
+        config.time_control = TimeControl::Clock(ChessClockConfig::fischer(60_000, 0));
+        let e4 = SessionAction::MoveFromTo {
+            from: E2,
+            to: E4,
+            promotion: None,
+        };
+
+        let mut session = Session::from_config(&config).unwrap();
+        session.start(0);
+        assert_eq!(session.first_move_deadline_ms(), Some(30_000));
+        session.act(Color::White, e4, 10_000).unwrap();
+        assert_eq!(session.first_move_deadline_ms(), Some(40_000));
+
+        assert_eq!(session.pause(12_000).unwrap(), SessionEvent::ClockPaused);
+        assert!(matches!(
+            session.pause(13_000),
+            Err(SessionError::ClockPaused)
+        ));
+        let e5 = SessionAction::MoveFromTo {
+            from: E7,
+            to: E5,
+            promotion: None,
+        };
+        assert!(matches!(
+            session.act(Color::Black, e5, 14_000),
+            Err(SessionError::ClockPaused)
+        ));
+        assert_eq!(session.resume(20_000).unwrap(), SessionEvent::ClockResumed);
+        let remaining = session.clock().unwrap().remaining_ms(Color::Black, 20_000);
+        assert_eq!(remaining, 58_000);
+
+        // Black never makes its first move, so the game is aborted instead of lost
+        let events = session
+            .act(Color::Black, SessionAction::OfferDraw, 40_000)
+            .unwrap();
+        assert_eq!(
+            events,
+            [SessionEvent::GameOver(GameOutcome::Aborted(
+                AbortReason::FirstMoveTimeout
+            ))]
+        );
+        assert_eq!(session.first_move_deadline_ms(), Some(40_000));
+        assert!(session.game().is_over());
+    }
+
+    #[test]
+    fn test_tick_ends_game_on_flag() {
+        let mut config = test_config();
+        config.starting_position =
+            StartingPosition::Fen("7k/8/8/8/8/8/8/R6K w - - 0 1".to_string());
+        config.time_control = TimeControl::Clock(ChessClockConfig::fischer(60_000, 0));
+        config.first_move_timeout_ms = Some(30_000);
+        let ra2 = SessionAction::MoveFromTo {
+            from: A1,
+            to: A2,
+            promotion: None,
+        };
+
+        let mut session = Session::from_config(&config).unwrap();
+        assert_eq!(session.next_deadline_ms(), None);
+        session.start(0);
+        assert_eq!(session.next_deadline_ms(), Some(30_000));
+        session.act(Color::White, ra2, 1_000).unwrap();
+        assert_eq!(session.next_deadline_ms(), Some(31_000));
+
+        // Black is past its first move window, the flag deadline is what remains
+        let mut flagged = session.clone();
+        flagged
+            .act(
+                Color::Black,
+                SessionAction::MoveFromTo {
+                    from: H8,
+                    to: G8,
+                    promotion: None,
+                },
+                2_000,
+            )
+            .unwrap();
+        assert_eq!(flagged.next_deadline_ms(), Some(62_000));
+        assert_eq!(flagged.tick(61_999), None);
+        // Black only has its king left, so white running out of time is a draw
+        assert_eq!(
+            flagged.tick(62_000),
+            Some(SessionEvent::GameOver(GameOutcome::Draw(
+                DrawReason::TimeoutVsInsufficient
+            )))
+        );
+        assert_eq!(flagged.tick(63_000), None);
+        assert_eq!(flagged.next_deadline_ms(), None);
+
+        assert_eq!(session.tick(30_999), None);
+        assert_eq!(
+            session.tick(31_000),
+            Some(SessionEvent::GameOver(GameOutcome::Aborted(
+                AbortReason::FirstMoveTimeout
+            )))
+        );
+    }
+
+    #[test]
+    fn test_abort_adjudicate_and_rematch() {
+        let mut config = test_config();
+        config.pgn.white = Some("Alice".to_string());
+        config.pgn.black = Some("Bob".to_string());
+        let play = |session: &mut Session, color, from, to| {
+            session
+                .act(
+                    color,
+                    SessionAction::MoveFromTo {
+                        from,
+                        to,
+                        promotion: None,
+                    },
+                    0,
+                )
+                .unwrap();
+        };
+
+        let mut session = Session::from_config(&config).unwrap();
+        play(&mut session, Color::White, E2, E4);
+        let mut aborted = session.clone();
+        assert_eq!(
+            aborted.act(Color::Black, SessionAction::Abort, 0).unwrap(),
+            [SessionEvent::GameOver(GameOutcome::Aborted(
+                AbortReason::Requested
+            ))]
+        );
+
+        play(&mut session, Color::Black, E7, E5);
+        assert!(matches!(
+            session.act(Color::White, SessionAction::Abort, 0),
+            Err(SessionError::AbortNotAllowed)
+        ));
+        assert!(matches!(session.rematch(), Err(SessionError::GameNotOver)));
+
+        let event = session
+            .adjudicate(Some(Color::Black), "Illegal device")
+            .unwrap();
+        assert_eq!(
+            event,
+            SessionEvent::GameOver(GameOutcome::Decisive {
+                winner: Color::Black,
+                reason: DecisiveReason::Adjudication,
+            })
+        );
+        let pgn = session.pgn();
+        assert!(pgn.contains("[Termination \"adjudication\"]"));
+        assert!(pgn.contains("{ Adjudication: Illegal device } 0-1"));
+        let restored = session.record().restore().unwrap();
+        assert_eq!(restored.adjudication_reason(), Some("Illegal device"));
+
+        let rematch = session.rematch().unwrap();
+        assert!(rematch.game().history().is_empty());
+        assert_eq!(rematch.config().pgn.white.as_deref(), Some("Bob"));
+        assert_eq!(rematch.config().pgn.black.as_deref(), Some("Alice"));
+    }
+
+    #[test]
+    fn test_premoves() {
+        let mut config = test_config();
+        config.time_control = TimeControl::Clock(ChessClockConfig::fischer(60_000, 0));
+        config.premove_charge_ms = Some(100);
+        let from_to = |from, to| SessionAction::MoveFromTo {
+            from,
+            to,
+            promotion: None,
+        };
+
+        let mut session = Session::from_config(&config).unwrap();
+        session.act(Color::White, from_to(E2, E4), 0).unwrap();
+        assert!(matches!(
+            session.act(Color::White, SessionAction::Move(ChessMove::default()), 0),
+            Err(SessionError::NotMovingColor)
+        ));
+        assert!(matches!(
+            session.act(Color::White, from_to(E3, E4), 0),
+            Err(SessionError::Chess(_))
+        ));
+
+        session.act(Color::White, from_to(G1, F3), 1_000).unwrap();
+        session.act(Color::White, from_to(E4, D5), 1_000).unwrap();
+        assert_eq!(session.premoves().len(), 2);
+
+        let events = session.act(Color::Black, from_to(E7, E5), 5_000).unwrap();
+        assert_eq!(session.san_history(), ["e4", "e5", "Nf3"]);
+        assert_eq!(session.premoves().len(), 1);
+        assert!(matches!(
+            events.as_slice(),
+            [
+                SessionEvent::MovePlayed { .. },
+                SessionEvent::ClockUpdate { .. },
+                SessionEvent::MovePlayed {
+                    color: Color::White,
+                    ..
+                },
+                SessionEvent::ClockUpdate { .. },
+            ]
+        ));
+        let clock = session.clock().unwrap();
+        assert_eq!(clock.remaining_ms(Color::White, 5_000), 59_900);
+
+        // Black doesn't play d5, so the queued capture is discarded
+        let events = session.act(Color::Black, from_to(D7, D6), 6_000).unwrap();
+        assert!(session.premoves().is_empty());
+        assert_eq!(
+            events.last(),
+            Some(&SessionEvent::PremoveDiscarded {
+                by: Color::White,
+                premove: Premove {
+                    from: E4,
+                    to: D5,
+                    promotion: None
+                }
+            })
+        );
+        assert_eq!(session.turn(), Color::White);
+    }
+
+    #[test]
+    fn test_takeback_discards_premoves() {
+        let mut config = test_config();
+        config.premove_charge_ms = Some(0);
+        let mut session = Session::from_config(&config).unwrap();
+        let from_to = |from, to| SessionAction::MoveFromTo {
+            from,
+            to,
+            promotion: None,
+        };
+        session.act(Color::White, from_to(E2, E4), 0).unwrap();
+        session.act(Color::Black, from_to(E7, E5), 0).unwrap();
+        session.act(Color::White, from_to(D2, D4), 0).unwrap();
+        session.act(Color::White, from_to(G1, F3), 0).unwrap();
+        assert_eq!(session.premoves().len(), 1);
+
+        session
+            .act(Color::Black, SessionAction::RequestTakeback, 0)
+            .unwrap();
+        let events = session
+            .act(Color::White, SessionAction::AcceptTakeback, 0)
+            .unwrap();
+        assert!(session.premoves().is_empty());
+        assert_eq!(
+            events,
+            [
+                SessionEvent::PremoveDiscarded {
+                    by: Color::White,
+                    premove: Premove {
+                        from: G1,
+                        to: F3,
+                        promotion: None
+                    }
+                },
+                SessionEvent::TakebackAccepted {
+                    by: Color::White,
+                    plies: 2
+                },
+            ]
+        );
+    }
 }
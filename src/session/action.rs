@@ -7,7 +7,7 @@ use crate::prelude::{ChessMove, Piece, Square};
     feature = "bit-codec",
     derive(bit_codec::BitEncode, bit_codec::BitDecode)
 )]
-#[cfg_attr(feature = "bit-codec", bits(disc = 3))]
+#[cfg_attr(feature = "bit-codec", bits(disc = 4))]
 pub enum SessionAction {
     Move(ChessMove),
     MoveFromTo {
@@ -21,4 +21,26 @@ pub enum SessionAction {
     DeclineDraw,
     ClaimDraw,
     ClaimTimeout,
+    /// Ask the opponent to take back moves so it's the requesting side's turn again
+    RequestTakeback,
+    AcceptTakeback,
+    DeclineTakeback,
+    /// End the game without a result, only possible before both sides moved
+    Abort,
+    /// Discard all queued premoves of the acting side
+    CancelPremoves,
+}
+
+/// A move submitted during the opponent's turn, played right after the opponent moved
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub struct Premove {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<Piece>,
 }
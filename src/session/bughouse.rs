@@ -0,0 +1,381 @@
+use crate::error::{SessionError, SessionResult};
+use crate::game::mode::GameMode;
+use crate::game::outcome::{DecisiveReason, DrawReason, GameOutcome};
+use crate::prelude::{Color, Piece, Session};
+use crate::session::action::SessionAction;
+use crate::session::config::SessionConfig;
+use crate::session::event::SessionEvent;
+
+/// The two teams of a Bughouse match.
+///
+/// Team A plays white on the first board and black on the second board, team B the other way around.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub enum Team {
+    A,
+    B,
+}
+
+impl Team {
+    /// The team playing the given color on the given board (0 or 1)
+    pub fn of(board: usize, color: Color) -> Self {
+        match (board, color) {
+            (0, Color::White) | (1, Color::Black) => Self::A,
+            _ => Self::B,
+        }
+    }
+
+    /// The color the team plays on the given board (0 or 1)
+    pub fn color_on(&self, board: usize) -> Color {
+        if Self::of(board, Color::White) == *self {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub struct BughouseOutcome {
+    /// None if the match was drawn or aborted
+    pub winner: Option<Team>,
+    /// The board whose game decided the match
+    pub board: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+#[cfg_attr(feature = "bit-codec", bits(disc = 2))]
+pub enum BughouseEvent {
+    /// An event of the session on the given board
+    Board {
+        board: u8,
+        event: SessionEvent,
+    },
+    /// A captured piece was passed to the pocket of the partner on the given board
+    PieceTransferred {
+        board: u8,
+        color: Color,
+        piece: Piece,
+    },
+    MatchOver(BughouseOutcome),
+}
+
+/// Two linked sessions in which captured pieces are passed to the partner on the other board.
+///
+/// The clocks run independently and the match ends as soon as the game on one board ends.
+/// Drops are played with [`SessionAction::Move`] and [`ChessMove::drop`](crate::prelude::ChessMove::drop).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BughouseMatch {
+    boards: [Session; 2],
+    outcome: Option<BughouseOutcome>,
+}
+
+impl BughouseMatch {
+    /// Both boards use the config in Bughouse mode, premoves aren't supported
+    pub fn new(config: &SessionConfig) -> SessionResult<Self> {
+        let config = SessionConfig {
+            mode: GameMode::Bughouse,
+            premove_charge_ms: None,
+            ..config.clone()
+        };
+        Ok(Self {
+            boards: [
+                Session::from_config(&config)?,
+                Session::from_config(&config)?,
+            ],
+            outcome: None,
+        })
+    }
+
+    pub fn start(&mut self, unix_ms: u64) {
+        for session in &mut self.boards {
+            session.start(unix_ms);
+        }
+    }
+
+    /// Applies the action on the given board (0 or 1) and passes captured pieces to the other board.
+    ///
+    /// Takebacks are rejected, since the captured pieces may already have been dropped on the other board.
+    pub fn act(
+        &mut self,
+        board: usize,
+        color: Color,
+        action: SessionAction,
+        unix_ms: u64,
+    ) -> SessionResult<Vec<BughouseEvent>> {
+        if self.outcome.is_some() {
+            return Err(SessionError::GameOver);
+        }
+        if matches!(
+            action,
+            SessionAction::RequestTakeback
+                | SessionAction::AcceptTakeback
+                | SessionAction::DeclineTakeback
+        ) {
+            return Err(SessionError::TakebackNotAllowed);
+        }
+
+        // Promoted pieces go back to the partner as pawns
+        let promoted = self.boards[board].game().promoted_pieces();
+        let session_events = self.boards[board].act(color, action, unix_ms)?;
+
+        let partner = 1 - board;
+        let mut events = Vec::new();
+        for event in session_events {
+            if let SessionEvent::MovePlayed {
+                color,
+                mv,
+                captured: Some(captured),
+                ..
+            } = event
+            {
+                let piece = if promoted.is_set(mv.to()) {
+                    Piece::Pawn
+                } else {
+                    captured
+                };
+                // The partner of the capturing player plays the opposite color
+                let receiver = color.opposite();
+                self.boards[partner].game.add_to_pocket(receiver, piece);
+                events.push(BughouseEvent::PieceTransferred {
+                    board: partner as u8,
+                    color: receiver,
+                    piece,
+                });
+            }
+            events.push(BughouseEvent::Board {
+                board: board as u8,
+                event,
+            });
+        }
+
+        self.check_over(board, &mut events);
+        Ok(events)
+    }
+
+    /// Ends the match if a side on either board ran out of time, see [`Session::tick`]
+    pub fn tick(&mut self, unix_ms: u64) -> Vec<BughouseEvent> {
+        let mut events = Vec::new();
+        if self.outcome.is_some() {
+            return events;
+        }
+        for board in 0..2 {
+            if let Some(event) = self.boards[board].tick(unix_ms) {
+                events.push(BughouseEvent::Board {
+                    board: board as u8,
+                    event,
+                });
+                self.check_over(board, &mut events);
+                break;
+            }
+        }
+        events
+    }
+
+    /// The earliest deadline of both boards, see [`Session::next_deadline_ms`]
+    pub fn next_deadline_ms(&self) -> Option<u64> {
+        if self.outcome.is_some() {
+            return None;
+        }
+        self.boards
+            .iter()
+            .filter_map(Session::next_deadline_ms)
+            .min()
+    }
+
+    /// Ends the other board once the game on the given board is over
+    fn check_over(&mut self, board: usize, events: &mut Vec<BughouseEvent>) {
+        let Some(outcome) = self.boards[board].game().outcome() else {
+            return;
+        };
+
+        let partner = 1 - board;
+        let winner = match outcome {
+            GameOutcome::Decisive { winner, .. } => Some(Team::of(board, winner)),
+            _ => None,
+        };
+        let partner_outcome = match (outcome, winner) {
+            (GameOutcome::Aborted(reason), _) => GameOutcome::Aborted(reason),
+            (_, Some(team)) => GameOutcome::Decisive {
+                winner: team.color_on(partner),
+                reason: DecisiveReason::PartnerBoard,
+            },
+            (_, None) => GameOutcome::Draw(DrawReason::PartnerBoard),
+        };
+        if !self.boards[partner].game().is_over() {
+            self.boards[partner].game.force_outcome(partner_outcome);
+            events.push(BughouseEvent::Board {
+                board: partner as u8,
+                event: SessionEvent::GameOver(partner_outcome),
+            });
+        }
+
+        let outcome = BughouseOutcome {
+            winner,
+            board: board as u8,
+        };
+        self.outcome = Some(outcome);
+        events.push(BughouseEvent::MatchOver(outcome));
+    }
+}
+
+// Accessors
+impl BughouseMatch {
+    /// The session of the given board (0 or 1)
+    pub fn board(&self, board: usize) -> &Session {
+        &self.boards[board]
+    }
+
+    pub fn outcome(&self) -> Option<BughouseOutcome> {
+        self.outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::SessionError;
+    use crate::prelude::*;
+    use crate::session::action::SessionAction;
+    use crate::session::bughouse::{BughouseEvent, BughouseMatch, Team};
+    use crate::session::config::SessionConfig;
+    use crate::session::event::SessionEvent;
+
+    #[test]
+    fn test_captures_are_passed_to_partner() {
+        let mut bughouse = BughouseMatch::new(&SessionConfig::default()).unwrap();
+        let play = |bughouse: &mut BughouseMatch, board, from, to| {
+            let color = bughouse.board(board).turn();
+            let action = SessionAction::MoveFromTo {
+                from,
+                to,
+                promotion: None,
+            };
+            bughouse.act(board, color, action, 0).unwrap()
+        };
+
+        play(&mut bughouse, 0, E2, E4);
+        play(&mut bughouse, 0, D7, D5);
+        let events = play(&mut bughouse, 0, E4, D5);
+        assert_eq!(
+            events[0],
+            BughouseEvent::PieceTransferred {
+                board: 1,
+                color: Color::Black,
+                piece: Piece::Pawn
+            }
+        );
+        assert_eq!(
+            bughouse
+                .board(1)
+                .game()
+                .pocket()
+                .count(Color::Black, Piece::Pawn),
+            1
+        );
+
+        // Black on the second board drops the pawn it received from its partner
+        play(&mut bughouse, 1, E2, E4);
+        let drop = ChessMove::drop(Piece::Pawn, D4);
+        assert_eq!(drop.to_uci(), "P@d4");
+        let drop_on_last_rank = ChessMove::drop(Piece::Pawn, A1);
+        assert!(
+            bughouse
+                .act(1, Color::Black, SessionAction::Move(drop_on_last_rank), 0)
+                .is_err()
+        );
+        let events = bughouse
+            .act(1, Color::Black, SessionAction::Move(drop), 0)
+            .unwrap();
+        assert!(matches!(
+            &events[0],
+            BughouseEvent::Board {
+                board: 1,
+                event: SessionEvent::MovePlayed { san, .. }
+            } if san == "P@d4"
+        ));
+        assert!(bughouse.board(1).game().pocket().is_empty(Color::Black));
+
+        // The match ends once one board ends
+        bughouse
+            .act(0, Color::White, SessionAction::Resign, 0)
+            .unwrap();
+        let outcome = bughouse.outcome().unwrap();
+        assert_eq!(outcome.winner, Some(Team::B));
+        assert_eq!(
+            bughouse.board(1).game().outcome(),
+            Some(GameOutcome::Decisive {
+                winner: Color::White,
+                reason: DecisiveReason::PartnerBoard
+            })
+        );
+        assert!(
+            bughouse
+                .act(1, Color::White, SessionAction::Resign, 0)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_takebacks_are_rejected_and_pockets_recorded() {
+        let mut bughouse = BughouseMatch::new(&SessionConfig::default()).unwrap();
+        let play = |bughouse: &mut BughouseMatch, board, from, to| {
+            let color = bughouse.board(board).turn();
+            let action = SessionAction::MoveFromTo {
+                from,
+                to,
+                promotion: None,
+            };
+            bughouse.act(board, color, action, 0).unwrap()
+        };
+
+        play(&mut bughouse, 0, E2, E4);
+        play(&mut bughouse, 0, D7, D5);
+        play(&mut bughouse, 0, E4, D5);
+        assert!(matches!(
+            bughouse.act(0, Color::Black, SessionAction::RequestTakeback, 0),
+            Err(SessionError::TakebackNotAllowed)
+        ));
+        assert_eq!(bughouse.board(0).game().history().len(), 3);
+
+        // The pocket survives a record, also after the received pieces were dropped
+        play(&mut bughouse, 0, G8, F6);
+        play(&mut bughouse, 0, D5, D6);
+        play(&mut bughouse, 0, E7, D6);
+        play(&mut bughouse, 1, E2, E4);
+        let restored = bughouse.board(1).record().restore().unwrap();
+        assert_eq!(restored.game().pocket().count(Color::White, Piece::Pawn), 1);
+        assert_eq!(restored.game().pocket().count(Color::Black, Piece::Pawn), 1);
+
+        let drop = ChessMove::drop(Piece::Pawn, D4);
+        bughouse
+            .act(1, Color::Black, SessionAction::Move(drop), 0)
+            .unwrap();
+        let restored = bughouse.board(1).record().restore().unwrap();
+        assert_eq!(
+            restored.game().position(),
+            bughouse.board(1).game().position()
+        );
+        assert_eq!(restored.game().pocket(), bughouse.board(1).game().pocket());
+        assert_eq!(restored.game().pocket().count(Color::White, Piece::Pawn), 1);
+        assert!(restored.game().pocket().is_empty(Color::Black));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::prelude::Color;
+use std::fmt::Write;
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -10,58 +11,133 @@ use crate::prelude::Color;
 pub struct ChessClock {
     /// Remaining time in milliseconds
     remaining_ms: [u64; 2],
-    /// Fischer time increment in milliseconds
+    /// Fischer increment or delay in milliseconds, depending on the clock kind
     increment_ms: [u64; 2],
+    /// The time per move of per-move clocks
+    base_ms: [u64; 2],
+    kind: ClockKind,
+    stages: Vec<ClockStage>,
+    /// Moves completed by white at 0, black at 1
+    moves_made: [u32; 2],
+    lag_compensation: Option<LagCompensation>,
+    /// Lag that can still be credited back, white at 0, black at 1
+    lag_quota_ms: [u64; 2],
     /// Milliseconds since UNIX epoch
     last_move_timestamp_ms: Option<u64>,
+    /// Milliseconds since UNIX epoch
+    paused_at_ms: Option<u64>,
     active: Color,
 }
 
 impl ChessClock {
     pub fn new(time_ms: u64, increment_ms: u64) -> Self {
-        Self {
-            remaining_ms: [time_ms, time_ms],
-            increment_ms: [increment_ms, increment_ms],
-            last_move_timestamp_ms: None,
-            active: Color::White,
-        }
+        Self::from_config(&ChessClockConfig::fischer(time_ms, increment_ms))
     }
 
     pub fn from_config(config: &ChessClockConfig) -> Self {
         Self {
             remaining_ms: [config.white_ms, config.black_ms],
             increment_ms: [config.white_inc_ms, config.black_inc_ms],
+            base_ms: [config.white_ms, config.black_ms],
+            kind: config.kind,
+            stages: config.stages.clone(),
+            moves_made: [0, 0],
+            lag_compensation: config.lag_compensation,
+            lag_quota_ms: config
+                .lag_compensation
+                .map_or([0, 0], |lag| [lag.quota_ms, lag.quota_ms]),
             last_move_timestamp_ms: None,
+            paused_at_ms: None,
             active: Color::White,
         }
     }
 
+    /// Ends the turn of the active side, returns true if it ran out of time
     pub fn switch(&mut self, now_ms: u64) -> bool {
+        self.switch_with_lag(now_ms, 0)
+    }
+
+    /// Ends the turn of the active side and credits back the network lag the client reported,
+    /// as far as the lag compensation allows. Returns true if the side ran out of time.
+    pub fn switch_with_lag(&mut self, now_ms: u64, lag_ms: u64) -> bool {
         let index = self.active as usize;
         let mut timeout = false;
+        let now_ms = self.effective_now(now_ms);
 
         if let Some(last) = self.last_move_timestamp_ms {
             let elapsed = now_ms.saturating_sub(last);
-            self.remaining_ms[index] = self.remaining_ms[index].saturating_sub(elapsed);
+            let elapsed = elapsed - self.credit_lag(index, lag_ms.min(elapsed));
+            let charged = self.charged_ms(index, elapsed);
+            self.remaining_ms[index] = self.remaining_ms[index].saturating_sub(charged);
             if self.remaining_ms[index] == 0 {
                 timeout = true;
             } else {
-                self.remaining_ms[index] += self.increment_ms[index];
+                match self.kind {
+                    ClockKind::Fischer => self.remaining_ms[index] += self.increment_ms[index],
+                    ClockKind::SimpleDelay => {}
+                    ClockKind::Bronstein => {
+                        self.remaining_ms[index] += elapsed.min(self.increment_ms[index])
+                    }
+                    ClockKind::Hourglass => self.remaining_ms[1 - index] += charged,
+                    ClockKind::PerMove => self.remaining_ms[index] = self.base_ms[index],
+                }
             }
         }
 
+        self.moves_made[index] += 1;
+        if !timeout {
+            self.apply_stages(index);
+        }
+
         self.last_move_timestamp_ms = Some(now_ms);
         self.active = self.active.opposite();
 
         timeout
     }
 
+    /// Stops the clock, e.g. for adjournments or disconnects. Returns false if it was already paused.
+    pub fn pause(&mut self, now_ms: u64) -> bool {
+        if self.paused_at_ms.is_some() {
+            return false;
+        }
+        self.paused_at_ms = Some(now_ms);
+        true
+    }
+
+    /// Continues the clock without charging the paused time. Returns false if it wasn't paused.
+    pub fn resume(&mut self, now_ms: u64) -> bool {
+        let Some(paused_at) = self.paused_at_ms.take() else {
+            return false;
+        };
+        if let Some(last) = &mut self.last_move_timestamp_ms {
+            *last += now_ms.saturating_sub(paused_at);
+        }
+        true
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at_ms.is_some()
+    }
+
+    /// Lag that can still be credited back to the given side
+    pub fn lag_quota_ms(&self, color: Color) -> u64 {
+        self.lag_quota_ms[color as usize]
+    }
+
+    /// Starts the turn of the active side anew without charging the time that passed, e.g. after a takeback
+    pub fn restart_turn(&mut self, now_ms: u64) {
+        if self.last_move_timestamp_ms.is_some() {
+            self.last_move_timestamp_ms = Some(now_ms);
+        }
+    }
+
     pub fn remaining_ms(&self, color: Color, now_ms: u64) -> u64 {
-        let base = self.remaining_ms[color as usize];
+        let index = color as usize;
+        let base = self.remaining_ms[index];
         if color == self.active {
             if let Some(last) = self.last_move_timestamp_ms {
-                let elapsed = now_ms.saturating_sub(last);
-                base.saturating_sub(elapsed)
+                let elapsed = self.effective_now(now_ms).saturating_sub(last);
+                base.saturating_sub(self.charged_ms(index, elapsed))
             } else {
                 base
             }
@@ -70,6 +146,27 @@ impl ChessClock {
         }
     }
 
+    /// When the active side will run out of time, None if the clock isn't running
+    pub fn flag_deadline_ms(&self) -> Option<u64> {
+        if self.is_paused() {
+            return None;
+        }
+        let last = self.last_move_timestamp_ms?;
+        let index = self.active as usize;
+        let delay_ms = match self.kind {
+            ClockKind::SimpleDelay => self.increment_ms[index],
+            _ => 0,
+        };
+        Some(last + delay_ms + self.remaining_ms[index])
+    }
+
+    /// How long the active side has been thinking, None if the clock isn't running yet
+    pub fn turn_elapsed_ms(&self, now_ms: u64) -> Option<u64> {
+        let last = self.last_move_timestamp_ms?;
+        Some(self.effective_now(now_ms).saturating_sub(last))
+    }
+
+    /// The increment or delay per move, depending on the clock kind
     pub fn increment_ms(&self, color: Color) -> u64 {
         self.increment_ms[color as usize]
     }
@@ -81,9 +178,55 @@ impl ChessClock {
     pub fn active(&self) -> Color {
         self.active
     }
+
+    pub fn kind(&self) -> ClockKind {
+        self.kind
+    }
+
+    pub fn moves_made(&self, color: Color) -> u32 {
+        self.moves_made[color as usize]
+    }
+
+    /// A paused clock doesn't see any time passing after it was paused
+    fn effective_now(&self, now_ms: u64) -> u64 {
+        self.paused_at_ms
+            .map_or(now_ms, |paused_at| paused_at.min(now_ms))
+    }
+
+    /// Returns how much of the lag was credited back
+    fn credit_lag(&mut self, index: usize, lag_ms: u64) -> u64 {
+        let Some(compensation) = self.lag_compensation else {
+            return 0;
+        };
+        let credited = lag_ms
+            .min(compensation.max_per_move_ms)
+            .min(self.lag_quota_ms[index]);
+        self.lag_quota_ms[index] = (self.lag_quota_ms[index] - credited
+            + compensation.quota_gain_ms)
+            .min(compensation.quota_ms);
+        credited
+    }
+
+    /// With a simple delay the clock only starts running once the delay passed
+    fn charged_ms(&self, index: usize, elapsed: u64) -> u64 {
+        match self.kind {
+            ClockKind::SimpleDelay => elapsed.saturating_sub(self.increment_ms[index]),
+            _ => elapsed,
+        }
+    }
+
+    fn apply_stages(&mut self, index: usize) {
+        let moves_made = self.moves_made[index];
+        for stage in self.stages.iter().filter(|s| s.after_moves == moves_made) {
+            self.remaining_ms[index] += stage.add_ms;
+            if let Some(increment_ms) = stage.increment_ms {
+                self.increment_ms[index] = increment_ms;
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -93,6 +236,323 @@ impl ChessClock {
 pub struct ChessClockConfig {
     pub white_ms: u64,
     pub black_ms: u64,
+    /// Increment or delay in milliseconds, depending on the clock kind
     pub white_inc_ms: u64,
+    /// Increment or delay in milliseconds, depending on the clock kind
     pub black_inc_ms: u64,
+    pub kind: ClockKind,
+    /// Additional time periods, e.g. 40 moves in 90 minutes followed by 30 minutes
+    pub stages: Vec<ClockStage>,
+    pub lag_compensation: Option<LagCompensation>,
+}
+
+impl ChessClockConfig {
+    pub fn fischer(time_ms: u64, increment_ms: u64) -> Self {
+        Self::with_kind(ClockKind::Fischer, time_ms, increment_ms)
+    }
+
+    pub fn simple_delay(time_ms: u64, delay_ms: u64) -> Self {
+        Self::with_kind(ClockKind::SimpleDelay, time_ms, delay_ms)
+    }
+
+    pub fn bronstein(time_ms: u64, delay_ms: u64) -> Self {
+        Self::with_kind(ClockKind::Bronstein, time_ms, delay_ms)
+    }
+
+    pub fn hourglass(time_ms: u64) -> Self {
+        Self::with_kind(ClockKind::Hourglass, time_ms, 0)
+    }
+
+    /// A fixed amount of time for every move, e.g. days per move in correspondence chess
+    pub fn per_move(time_ms: u64) -> Self {
+        Self::with_kind(ClockKind::PerMove, time_ms, 0)
+    }
+
+    pub fn with_stage(mut self, stage: ClockStage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn with_lag_compensation(mut self, lag_compensation: LagCompensation) -> Self {
+        self.lag_compensation = Some(lag_compensation);
+        self
+    }
+
+    fn with_kind(kind: ClockKind, time_ms: u64, increment_ms: u64) -> Self {
+        Self {
+            white_ms: time_ms,
+            black_ms: time_ms,
+            white_inc_ms: increment_ms,
+            black_inc_ms: increment_ms,
+            kind,
+            stages: vec![],
+            lag_compensation: None,
+        }
+    }
+
+    /// The value of the PGN `TimeControl` tag, based on white's times.
+    ///
+    /// Follows the PGN spec (`40/5400+30:1800+30`, `300+3`, `*180`, `1/86400`),
+    /// delays aren't covered by the spec and are written as `300d5` (simple) or `300b5` (Bronstein).
+    pub fn pgn_time_control(&self) -> String {
+        match self.kind {
+            ClockKind::Hourglass => return format!("*{}", seconds(self.white_ms)),
+            ClockKind::PerMove => return format!("1/{}", seconds(self.white_ms)),
+            _ => {}
+        }
+
+        let mut periods = Vec::new();
+        let mut moves_before = 0;
+        let mut time_ms = self.white_ms;
+        let mut increment_ms = self.white_inc_ms;
+        let mut stages: Vec<&ClockStage> = self.stages.iter().collect();
+        stages.sort_by_key(|stage| stage.after_moves);
+
+        for stage in stages {
+            periods.push((
+                Some(stage.after_moves - moves_before),
+                time_ms,
+                increment_ms,
+            ));
+            moves_before = stage.after_moves;
+            time_ms = stage.add_ms;
+            increment_ms = stage.increment_ms.unwrap_or(increment_ms);
+        }
+        periods.push((None, time_ms, increment_ms));
+
+        let separator = match self.kind {
+            ClockKind::SimpleDelay => "d",
+            ClockKind::Bronstein => "b",
+            _ => "+",
+        };
+        let mut tag = String::new();
+        for (i, (moves, time_ms, increment_ms)) in periods.into_iter().enumerate() {
+            if i > 0 {
+                tag.push(':');
+            }
+            if let Some(moves) = moves {
+                write!(tag, "{moves}/").unwrap();
+            }
+            tag.push_str(&seconds(time_ms));
+            if increment_ms > 0 {
+                write!(tag, "{separator}{}", seconds(increment_ms)).unwrap();
+            }
+        }
+        tag
+    }
+}
+
+fn seconds(ms: u64) -> String {
+    if ms.is_multiple_of(1000) {
+        (ms / 1000).to_string()
+    } else {
+        format!("{}", ms as f64 / 1000.0)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "strum",
+    derive(strum::EnumIter, strum::EnumIs, strum::EnumCount)
+)]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+#[cfg_attr(feature = "bit-codec", bits(disc = 3))]
+pub enum ClockKind {
+    /// The increment is added after every move
+    #[default]
+    Fischer,
+    /// The clock only starts running after the delay passed (US delay)
+    SimpleDelay,
+    /// The time used is given back after every move, up to the delay
+    Bronstein,
+    /// The time used by one side is added to the other side
+    Hourglass,
+    /// The clock is reset to the base time after every move
+    PerMove,
+}
+
+/// A time period that starts once a side completed the given amount of moves
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub struct ClockStage {
+    pub after_moves: u32,
+    /// Time added to the clock once the stage is reached
+    pub add_ms: u64,
+    /// Replaces the increment (or delay) from then on
+    pub increment_ms: Option<u64>,
+}
+
+/// Limits how much network lag reported by clients is credited back to their clocks
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub struct LagCompensation {
+    /// The most lag credited for a single move
+    pub max_per_move_ms: u64,
+    /// The most lag that can be credited at once, every side starts with a full quota
+    pub quota_ms: u64,
+    /// Quota regained with every move
+    pub quota_gain_ms: u64,
+}
+
+/// The time data of a single ply
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub struct MoveTime {
+    /// Milliseconds since UNIX epoch
+    pub played_at_ms: u64,
+    /// How long the side took for the move, None if the start of its turn is unknown
+    pub think_ms: Option<u64>,
+    /// Remaining time of the moving side after the move, None without a clock
+    pub remaining_ms: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::Color;
+    use crate::session::clock::{ChessClock, ChessClockConfig, ClockStage, LagCompensation};
+
+    /// Plays the given think times for both sides alternating, starting with white
+    fn play(clock: &mut ChessClock, think_times: &[u64]) -> u64 {
+        let mut now = 0;
+        clock.switch(now);
+        for think in think_times {
+            now += think;
+            assert!(!clock.switch(now));
+        }
+        now
+    }
+
+    #[test]
+    fn test_clock_kinds() {
+        let mut delay = ChessClock::from_config(&ChessClockConfig::simple_delay(60_000, 5_000));
+        let now = play(&mut delay, &[3_000, 8_000]);
+        assert_eq!(delay.remaining_ms(Color::Black, now), 60_000);
+        assert_eq!(delay.remaining_ms(Color::White, now), 57_000);
+        assert_eq!(delay.remaining_ms(Color::Black, now + 4_000), 60_000);
+
+        let mut bronstein = ChessClock::from_config(&ChessClockConfig::bronstein(60_000, 5_000));
+        let now = play(&mut bronstein, &[3_000, 8_000]);
+        assert_eq!(bronstein.remaining_ms(Color::Black, now), 60_000);
+        assert_eq!(bronstein.remaining_ms(Color::White, now), 57_000);
+
+        let mut hourglass = ChessClock::from_config(&ChessClockConfig::hourglass(60_000));
+        let now = play(&mut hourglass, &[3_000, 8_000]);
+        assert_eq!(
+            hourglass.remaining_ms(Color::Black, now),
+            60_000 - 3_000 + 8_000
+        );
+        assert_eq!(
+            hourglass.remaining_ms(Color::White, now),
+            60_000 + 3_000 - 8_000
+        );
+
+        let mut per_move = ChessClock::from_config(&ChessClockConfig::per_move(86_400_000));
+        let now = play(&mut per_move, &[50_000_000, 80_000_000]);
+        assert_eq!(per_move.remaining_ms(Color::White, now), 86_400_000);
+        assert!(per_move.is_out_of_time(Color::Black, now + 86_400_000));
+    }
+
+    #[test]
+    fn test_clock_stages() {
+        let config = ChessClockConfig::fischer(5_400_000, 30_000).with_stage(ClockStage {
+            after_moves: 2,
+            add_ms: 1_800_000,
+            increment_ms: Some(10_000),
+        });
+        let mut clock = ChessClock::from_config(&config);
+        let now = play(&mut clock, &[1_000, 2_000, 1_000]);
+
+        assert_eq!(clock.moves_made(Color::White), 2);
+        assert_eq!(clock.moves_made(Color::Black), 2);
+        assert_eq!(
+            clock.remaining_ms(Color::White, now),
+            5_400_000 - 2_000 + 30_000 + 1_800_000
+        );
+        assert_eq!(clock.increment_ms(Color::White), 10_000);
+        assert_eq!(clock.increment_ms(Color::Black), 10_000);
+    }
+
+    #[test]
+    fn test_pgn_time_control() {
+        assert_eq!(
+            ChessClockConfig::fischer(300_000, 3_000).pgn_time_control(),
+            "300+3"
+        );
+        assert_eq!(
+            ChessClockConfig::fischer(60_000, 0).pgn_time_control(),
+            "60"
+        );
+        assert_eq!(
+            ChessClockConfig::hourglass(180_000).pgn_time_control(),
+            "*180"
+        );
+        assert_eq!(
+            ChessClockConfig::per_move(86_400_000).pgn_time_control(),
+            "1/86400"
+        );
+        assert_eq!(
+            ChessClockConfig::simple_delay(300_000, 5_000).pgn_time_control(),
+            "300d5"
+        );
+
+        let classical = ChessClockConfig::fischer(5_400_000, 30_000).with_stage(ClockStage {
+            after_moves: 40,
+            add_ms: 1_800_000,
+            increment_ms: None,
+        });
+        assert_eq!(classical.pgn_time_control(), "40/5400+30:1800+30");
+    }
+
+    #[test]
+    fn test_pause_and_lag_compensation() {
+        let config = ChessClockConfig::fischer(60_000, 0).with_lag_compensation(LagCompensation {
+            max_per_move_ms: 500,
+            quota_ms: 800,
+            quota_gain_ms: 100,
+        });
+        let mut clock = ChessClock::from_config(&config);
+        clock.switch(0);
+
+        assert!(clock.pause(1_000));
+        assert!(!clock.pause(2_000));
+        assert_eq!(clock.remaining_ms(Color::Black, 30_000), 59_000);
+        assert!(clock.resume(31_000));
+        assert!(!clock.is_paused());
+        assert_eq!(clock.remaining_ms(Color::Black, 32_000), 58_000);
+
+        // 3s of thinking with 1s of reported lag, only 500ms are credited
+        clock.switch_with_lag(33_000, 1_000);
+        assert_eq!(clock.remaining_ms(Color::Black, 33_000), 57_500);
+        assert_eq!(clock.lag_quota_ms(Color::Black), 400);
+
+        clock.switch_with_lag(34_000, 1_000);
+        assert_eq!(clock.remaining_ms(Color::White, 34_000), 59_500);
+
+        clock.switch_with_lag(35_000, 1_000);
+        assert_eq!(
+            clock.remaining_ms(Color::Black, 35_000),
+            57_500 - 1_000 + 400
+        );
+        assert_eq!(clock.lag_quota_ms(Color::Black), 100);
+    }
 }
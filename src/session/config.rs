@@ -14,6 +14,10 @@ pub struct SessionConfig {
     pub starting_position: StartingPosition,
     pub time_control: TimeControl,
     pub pgn: PgnHeaders,
+    /// The game is aborted instead of lost if a side doesn't make its first move within this time
+    pub first_move_timeout_ms: Option<u64>,
+    /// Enables premoves, the time in ms charged to the clock for every premove
+    pub premove_charge_ms: Option<u64>,
 }
 
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
@@ -30,7 +34,7 @@ pub enum StartingPosition {
     Fen(String),
 }
 
-#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
@@ -43,3 +47,13 @@ pub enum TimeControl {
     Unlimited,
     Clock(ChessClockConfig),
 }
+
+impl TimeControl {
+    /// The value of the PGN `TimeControl` tag, `-` if there is no time control
+    pub fn pgn_tag(&self) -> String {
+        match self {
+            Self::Unlimited => "-".to_string(),
+            Self::Clock(config) => config.pgn_time_control(),
+        }
+    }
+}
@@ -1,17 +1,63 @@
 use crate::game::outcome::GameOutcome;
-use crate::prelude::Color;
+use crate::prelude::{ChessMove, Color, Piece};
+use crate::session::action::Premove;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(
     feature = "bit-codec",
     derive(bit_codec::BitEncode, bit_codec::BitDecode)
 )]
-#[cfg_attr(feature = "bit-codec", bits(disc = 2))]
+#[cfg_attr(feature = "bit-codec", bits(disc = 4))]
 pub enum SessionEvent {
-    BoardUpdate,
-    DrawOffered { by: Color },
-    DrawOfferDeclined { by: Color },
+    MovePlayed {
+        color: Color,
+        mv: ChessMove,
+        san: String,
+        /// The piece that was taken by this move, en passant included
+        captured: Option<Piece>,
+        /// If the move gave check (or mate)
+        check: bool,
+        /// Remaining time of the moving side after the move in ms, increment included
+        clock_after: Option<u64>,
+    },
+    /// The remaining times after a move in ms
+    ClockUpdate {
+        white_ms: u64,
+        black_ms: u64,
+    },
+    DrawOffered {
+        by: Color,
+    },
+    DrawOfferDeclined {
+        by: Color,
+    },
+    /// The side that offered the draw moved on
+    DrawOfferWithdrawn {
+        by: Color,
+    },
+    ClockPaused,
+    ClockResumed,
+    PremoveQueued {
+        by: Color,
+        premove: Premove,
+    },
+    /// The premove was cancelled or became illegal
+    PremoveDiscarded {
+        by: Color,
+        premove: Premove,
+    },
+    TakebackRequested {
+        by: Color,
+    },
+    /// The given amount of plies were taken back
+    TakebackAccepted {
+        by: Color,
+        plies: u8,
+    },
+    TakebackDeclined {
+        by: Color,
+    },
     GameOver(GameOutcome),
 }
@@ -0,0 +1,274 @@
+use crate::core::position::Position;
+use crate::error::{SessionError, SessionResult};
+use crate::game::Game;
+use crate::game::mode::GameMode;
+use crate::game::outcome::GameOutcome;
+use crate::game::pocket::Pocket;
+use crate::prelude::{ChessMove, Color, Session};
+use crate::session::SessionRecord;
+use std::str::FromStr;
+
+/// Everything a spectator needs to display a session, without any session internals
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub struct SessionSnapshot {
+    pub mode: GameMode,
+    pub fen: String,
+    /// Amount of moves played in this session
+    pub ply: u16,
+    /// In UCI notation
+    pub last_move: Option<String>,
+    pub clock: Option<ClockSnapshot>,
+    pub draw_offer: Option<Color>,
+    /// None in modes without drops
+    pub pocket: Option<Pocket>,
+    /// In UCI notation, drops included
+    pub legal_moves: Vec<String>,
+    pub outcome: Option<GameOutcome>,
+}
+
+/// Only the changes caused by a single move, applied to a snapshot with [`SessionSnapshot::apply`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub struct SessionDelta {
+    /// The ply after the move, clients use it to detect missed deltas
+    pub ply: u16,
+    pub mv: ChessMove,
+    pub clock: Option<ClockSnapshot>,
+    /// The pocket after the move, None in modes without drops
+    pub pocket: Option<Pocket>,
+    pub outcome: Option<GameOutcome>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "bitcode", derive(bitcode::Encode, bitcode::Decode))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "bit-codec",
+    derive(bit_codec::BitEncode, bit_codec::BitDecode)
+)]
+pub struct ClockSnapshot {
+    pub white_ms: u64,
+    pub black_ms: u64,
+    /// The side whose clock is running, None if the clock is paused or didn't start yet
+    pub running: Option<Color>,
+}
+
+impl Session {
+    pub fn snapshot(&self, unix_ms: u64) -> SessionSnapshot {
+        let game = self.game();
+        SessionSnapshot {
+            mode: game.mode(),
+            fen: game.position().to_string(),
+            ply: game.history().len() as u16,
+            last_move: game.history().last().map(ChessMove::to_uci),
+            clock: self.clock_snapshot(unix_ms),
+            draw_offer: self.draw_offer(),
+            pocket: game.mode().has_drops().then(|| *game.pocket()),
+            legal_moves: legal_moves_uci(game),
+            outcome: game.outcome(),
+        }
+    }
+
+    /// The delta of the last move, None if no move was played yet
+    pub fn last_delta(&self, unix_ms: u64) -> Option<SessionDelta> {
+        let history = self.game().history();
+        Some(SessionDelta {
+            ply: history.len() as u16,
+            mv: *history.last()?,
+            clock: self.clock_snapshot(unix_ms),
+            pocket: self
+                .game()
+                .mode()
+                .has_drops()
+                .then(|| *self.game().pocket()),
+            outcome: self.game().outcome(),
+        })
+    }
+
+    fn clock_snapshot(&self, unix_ms: u64) -> Option<ClockSnapshot> {
+        let clock = self.clock()?;
+        let running = clock.flag_deadline_ms().is_some().then(|| clock.active());
+        Some(ClockSnapshot {
+            white_ms: clock.remaining_ms(Color::White, unix_ms),
+            black_ms: clock.remaining_ms(Color::Black, unix_ms),
+            running,
+        })
+    }
+}
+
+impl SessionRecord {
+    /// The snapshot of the restored session
+    pub fn snapshot(self, unix_ms: u64) -> SessionResult<SessionSnapshot> {
+        Ok(self.restore()?.snapshot(unix_ms))
+    }
+}
+
+impl SessionSnapshot {
+    /// Plays the move of the delta, fails if the delta doesn't directly follow this snapshot
+    pub fn apply(&mut self, delta: &SessionDelta) -> SessionResult<()> {
+        if delta.ply != self.ply + 1 {
+            return Err(SessionError::DeltaOutOfSync {
+                ply: self.ply,
+                delta_ply: delta.ply,
+            });
+        }
+
+        let position = Position::from_str(&self.fen).map_err(SessionError::InvalidFen)?;
+        let mut game = Game::from_position(position)
+            .with_mode(self.mode)
+            .with_pocket(self.pocket.unwrap_or_default());
+        game.play_move(delta.mv)?;
+        // The pocket may also have changed through captures on the partner's board
+        let game = Game::from_position(*game.position())
+            .with_mode(self.mode)
+            .with_pocket(delta.pocket.unwrap_or_default());
+
+        self.fen = game.position().to_string();
+        self.ply = delta.ply;
+        self.last_move = Some(delta.mv.to_uci());
+        self.clock = delta.clock;
+        self.draw_offer = None;
+        self.pocket = delta.pocket;
+        self.outcome = delta.outcome;
+        self.legal_moves = if delta.outcome.is_some() {
+            vec![]
+        } else {
+            legal_moves_uci(&game)
+        };
+        Ok(())
+    }
+}
+
+fn legal_moves_uci(game: &Game) -> Vec<String> {
+    if game.is_over() {
+        return vec![];
+    }
+    game.legal_moves()
+        .iter()
+        .copied()
+        .chain(game.legal_drops())
+        .map(|mv| mv.to_uci())
+        .collect()
+}
+
+#[cfg(feature = "bit-codec")]
+mod codec {
+    use super::{SessionDelta, SessionSnapshot};
+    use bit_codec::{BitEncode, BitReader, BitWriter};
+
+    impl SessionSnapshot {
+        pub fn to_bytes(&self) -> std::io::Result<Vec<u8>> {
+            to_bytes(self)
+        }
+
+        pub fn from_bytes(bytes: &[u8]) -> std::io::Result<Self> {
+            BitReader::new(bytes).read()
+        }
+    }
+
+    impl SessionDelta {
+        pub fn to_bytes(&self) -> std::io::Result<Vec<u8>> {
+            to_bytes(self)
+        }
+
+        pub fn from_bytes(bytes: &[u8]) -> std::io::Result<Self> {
+            BitReader::new(bytes).read()
+        }
+    }
+
+    fn to_bytes<T: BitEncode>(value: &T) -> std::io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut w = BitWriter::new(&mut buf);
+        w.write(value)?;
+        w.flush()?;
+        drop(w);
+        Ok(buf)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::prelude::*;
+        use crate::session::action::SessionAction;
+        use crate::session::snapshot::{SessionDelta, SessionSnapshot};
+
+        #[test]
+        fn test_round_trip() {
+            let mut session = Session::from_config(&Default::default()).unwrap();
+            session
+                .act(
+                    Color::White,
+                    SessionAction::MoveFromTo {
+                        from: E2,
+                        to: E4,
+                        promotion: None,
+                    },
+                    0,
+                )
+                .unwrap();
+
+            let snapshot = session.snapshot(0);
+            let bytes = snapshot.to_bytes().unwrap();
+            assert_eq!(SessionSnapshot::from_bytes(&bytes).unwrap(), snapshot);
+
+            let delta = session.last_delta(0).unwrap();
+            let bytes = delta.to_bytes().unwrap();
+            assert_eq!(SessionDelta::from_bytes(&bytes).unwrap(), delta);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::session::action::SessionAction;
+    use crate::session::clock::ChessClockConfig;
+    use crate::session::config::{SessionConfig, TimeControl};
+
+    #[test]
+    fn test_snapshot_follows_deltas() {
+        let config = SessionConfig {
+            time_control: TimeControl::Clock(ChessClockConfig::fischer(60_000, 1_000)),
+            ..Default::default()
+        };
+        let mut session = Session::from_config(&config).unwrap();
+        let mut snapshot = session.snapshot(0);
+        assert_eq!(snapshot.legal_moves.len(), 20);
+        assert_eq!(snapshot.clock.unwrap().running, None);
+
+        for (i, (from, to)) in [(F2, F3), (E7, E5), (G2, G4), (D8, H4)]
+            .into_iter()
+            .enumerate()
+        {
+            let action = SessionAction::MoveFromTo {
+                from,
+                to,
+                promotion: None,
+            };
+            let unix_ms = i as u64 * 1_000;
+            session.act(session.turn(), action, unix_ms).unwrap();
+            snapshot
+                .apply(&session.last_delta(unix_ms).unwrap())
+                .unwrap();
+            assert_eq!(snapshot, session.snapshot(unix_ms));
+        }
+
+        assert_eq!(snapshot.last_move.as_deref(), Some("d8h4"));
+        assert!(snapshot.outcome.is_some());
+        assert!(snapshot.legal_moves.is_empty());
+
+        let stale = session.last_delta(3_000).unwrap();
+        assert!(snapshot.apply(&stale).is_err());
+        assert_eq!(session.record().snapshot(3_000).unwrap(), snapshot);
+    }
+}
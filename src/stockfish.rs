@@ -1,20 +1,32 @@
 use crate::stockfish::command::SfCommand;
 use crate::stockfish::error::SfResult;
 use crate::stockfish::event::SfEvent;
+use crate::stockfish::options::EngineOptions;
 use crate::stockfish::reader::{FromTokens, TokenReader};
 
+pub mod analysis;
 pub mod command;
+pub mod engine;
 pub mod error;
 pub mod event;
+pub mod options;
 pub mod reader;
 
+/// Keeps track of the UCI conversation with a Stockfish process.
+///
+/// [`StockfishManager::send`] queues any command as is, `setoption` included, so options can be set
+/// before the engine announced them. Use [`StockfishManager::set_option`] to validate the value first.
 pub struct StockfishManager {
     outbox: Vec<SfCommand>,
+    options: EngineOptions,
 }
 
 impl Default for StockfishManager {
     fn default() -> Self {
-        let mut manager = Self { outbox: Vec::new() };
+        let mut manager = Self {
+            outbox: Vec::new(),
+            options: EngineOptions::default(),
+        };
         manager.send(SfCommand::Uci);
         manager
     }
@@ -27,9 +39,17 @@ impl StockfishManager {
         }
         let mut reader = TokenReader::new(line);
         let event = SfEvent::parse(&mut reader)?;
+        if let SfEvent::Option(option) = &event {
+            self.options.register(option.clone());
+        }
         Ok(Some(event))
     }
 
+    /// The options the engine announced, complete once [`SfEvent::Ok`] was received
+    pub fn options(&self) -> &EngineOptions {
+        &self.options
+    }
+
     pub fn drain_commands(&mut self) -> Vec<SfCommand> {
         std::mem::take(&mut self.outbox)
     }
@@ -37,7 +57,15 @@ impl StockfishManager {
 
 // Commands
 impl StockfishManager {
+    /// Queues the command without validation
     pub fn send(&mut self, cmd: SfCommand) {
         self.outbox.push(cmd);
     }
+
+    /// Validates the value against the engine's declared options before sending it
+    pub fn set_option(&mut self, name: &str, value: &str) -> SfResult<()> {
+        let cmd = self.options.set(name, value)?;
+        self.send(cmd);
+        Ok(())
+    }
 }
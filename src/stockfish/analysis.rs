@@ -0,0 +1,261 @@
+use crate::core::position::Position;
+use crate::moves::generator::MoveGenerator;
+use crate::moves::naive::NaivePromotionMove;
+use crate::notation::san::move_to_san;
+use crate::prelude::ChessMove;
+use crate::stockfish::error::{SfError, SfResult};
+use crate::stockfish::event::{SfInfo, SfScore, SfWdl};
+use std::collections::BTreeMap;
+
+/// A principal variation reported by the engine, normalized to white's perspective.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SfLine {
+    /// Which line this is when MultiPV > 1 (1-indexed)
+    pub multi_pv: u32,
+    pub depth: u32,
+    /// Evaluation from white's perspective
+    pub score: SfScore,
+    /// Win/Draw/Loss probabilities from white's perspective, only present when UCI_ShowWDL is enabled
+    pub wdl: Option<SfWdl>,
+    pub pv: Vec<ChessMove>,
+    /// The principal variation in SAN
+    pub san: Vec<String>,
+}
+
+impl SfLine {
+    pub fn best_move(&self) -> Option<ChessMove> {
+        self.pv.first().copied()
+    }
+}
+
+/// Collects the interleaved `info multipv N` lines of a search on a single position.
+///
+/// The latest line is kept per multipv index per depth, so a consistent ranking can be
+/// built even while the engine is halfway through reporting a new depth.
+#[derive(Debug, Clone)]
+pub struct MultiPvAggregator {
+    position: Position,
+    /// depth -> multipv index -> line
+    lines: BTreeMap<u32, BTreeMap<u32, SfLine>>,
+}
+
+impl MultiPvAggregator {
+    pub fn new(position: Position) -> Self {
+        Self {
+            position,
+            lines: BTreeMap::new(),
+        }
+    }
+
+    /// Clears all collected lines and starts collecting for a new position
+    pub fn reset(&mut self, position: Position) {
+        self.position = position;
+        self.lines.clear();
+    }
+
+    /// Records an info line, returns false if it didn't carry a scored principal variation.
+    /// Bound scores don't replace an exact score already reported for the same line and depth.
+    pub fn push(&mut self, info: &SfInfo) -> SfResult<bool> {
+        let (Some(depth), Some(score)) = (info.depth, info.score) else {
+            return Ok(false);
+        };
+        if info.pv.is_empty() {
+            return Ok(false);
+        }
+
+        let multi_pv = info.multi_pv.unwrap_or(1);
+        let has_exact = self
+            .lines
+            .get(&depth)
+            .and_then(|lines| lines.get(&multi_pv))
+            .is_some_and(|line| line.score.bound().is_none());
+        if has_exact && score.bound().is_some() {
+            return Ok(false);
+        }
+
+        let side_to_move = self.position.side_to_move;
+        let (pv, san) = self.convert_pv(&info.pv)?;
+        let line = SfLine {
+            multi_pv,
+            depth,
+            score: score.white_perspective(side_to_move),
+            wdl: info.wdl.map(|wdl| wdl.white_perspective(side_to_move)),
+            pv,
+            san,
+        };
+
+        self.lines.entry(depth).or_default().insert(multi_pv, line);
+        Ok(true)
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// The deepest depth any line was reported for
+    pub fn depth(&self) -> Option<u32> {
+        self.lines.keys().next_back().copied()
+    }
+
+    pub fn lines_at(&self, depth: u32) -> impl Iterator<Item = &SfLine> {
+        self.lines
+            .get(&depth)
+            .into_iter()
+            .flat_map(|lines| lines.values())
+    }
+
+    /// The candidate moves ranked best to worst.
+    ///
+    /// Lines of the deepest depth come first in multipv order, followed by the lines of
+    /// shallower depths whose first move isn't covered yet. This keeps all candidates
+    /// available while the engine is halfway through reporting a new depth.
+    pub fn ranked(&self) -> Vec<&SfLine> {
+        let mut ranked: Vec<&SfLine> = Vec::new();
+        for lines in self.lines.values().rev() {
+            for line in lines.values() {
+                if !ranked
+                    .iter()
+                    .any(|other| other.best_move() == line.best_move())
+                {
+                    ranked.push(line);
+                }
+            }
+        }
+        ranked
+    }
+
+    pub fn best(&self) -> Option<&SfLine> {
+        self.ranked().into_iter().next()
+    }
+
+    fn convert_pv(&self, uci_moves: &[String]) -> SfResult<(Vec<ChessMove>, Vec<String>)> {
+        let mut pos = self.position;
+        let mut moves = Vec::with_capacity(uci_moves.len());
+        let mut san = Vec::with_capacity(uci_moves.len());
+
+        for uci in uci_moves {
+            let legal_moves = MoveGenerator::get().generate(&pos);
+            let mv = uci
+                .parse::<NaivePromotionMove>()
+                .ok()
+                .and_then(|naive| {
+                    legal_moves.iter().copied().find(|mv| {
+                        mv.from() == naive.mv.from
+                            && mv.to() == naive.mv.to
+                            && mv.flags().promotion_piece() == naive.promotion
+                    })
+                })
+                .ok_or_else(|| SfError::IllegalPvMove(uci.clone()))?;
+
+            san.push(
+                move_to_san(&pos, mv, &legal_moves)
+                    .map_err(|_| SfError::IllegalPvMove(uci.clone()))?,
+            );
+            moves.push(mv);
+            pos = pos.make_move(mv);
+        }
+
+        Ok((moves, san))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::position::Position;
+    use crate::stockfish::analysis::MultiPvAggregator;
+    use crate::stockfish::error::SfError;
+    use crate::stockfish::event::{SfBound, SfEvent, SfScore, SfWdl};
+    use crate::stockfish::reader::{FromTokens, TokenReader};
+    use std::str::FromStr;
+
+    fn push(aggregator: &mut MultiPvAggregator, line: &str) -> Result<bool, SfError> {
+        let SfEvent::Info(info) = SfEvent::parse(&mut TokenReader::new(line)).unwrap() else {
+            panic!("Expected info event for {line}");
+        };
+        aggregator.push(&info)
+    }
+
+    #[test]
+    fn test_ranked_lines_white_perspective() {
+        let pos = Position::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+            .unwrap();
+        let mut aggregator = MultiPvAggregator::new(pos);
+
+        let lines = [
+            "info depth 10 multipv 1 score cp -20 wdl 40 900 60 pv c7c5 g1f3",
+            "info depth 10 multipv 2 score cp -30 upperbound pv e7e5 g1f3 b8c6",
+            "info depth 10 multipv 3 score cp -45 pv e7e6",
+            "info depth 11 currmove e7e5 currmovenumber 1",
+            "info depth 11 multipv 1 score cp -25 pv e7e5 g1f3",
+        ];
+        let recorded: Vec<bool> = lines
+            .iter()
+            .map(|line| push(&mut aggregator, line).unwrap())
+            .collect();
+        assert_eq!(recorded, [true, true, true, false, true]);
+        assert_eq!(aggregator.depth(), Some(11));
+
+        let ranked = aggregator.ranked();
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[0].depth, 11);
+        assert_eq!(ranked[0].san, ["e5", "Nf3"]);
+        assert_eq!(
+            ranked[0].score,
+            SfScore::Cp {
+                value: 25,
+                bound: None
+            }
+        );
+        assert_eq!(ranked[1].san, ["c5", "Nf3"]);
+        assert_eq!(ranked[2].multi_pv, 3);
+        assert_eq!(ranked[2].san, ["e6"]);
+
+        let depth_ten: Vec<_> = aggregator.lines_at(10).collect();
+        assert_eq!(
+            depth_ten[0].wdl,
+            Some(SfWdl {
+                win: 60,
+                draw: 900,
+                loss: 40
+            })
+        );
+        assert_eq!(depth_ten[1].score.bound(), Some(SfBound::Lower));
+    }
+
+    #[test]
+    fn test_bound_keeps_exact_score() {
+        let mut aggregator = MultiPvAggregator::new(Position::default());
+        let lines = [
+            "info depth 12 multipv 1 score cp 30 pv e2e4",
+            "info depth 12 multipv 1 score cp 45 lowerbound pv d2d4",
+            "info depth 12 multipv 2 score cp 10 upperbound pv g1f3",
+            "info depth 12 multipv 2 score cp 5 pv c2c4",
+        ];
+        let recorded: Vec<bool> = lines
+            .iter()
+            .map(|line| push(&mut aggregator, line).unwrap())
+            .collect();
+        assert_eq!(recorded, [true, false, true, true]);
+
+        let ranked = aggregator.ranked();
+        assert_eq!(ranked[0].san, ["e4"]);
+        assert_eq!(
+            ranked[0].score,
+            SfScore::Cp {
+                value: 30,
+                bound: None
+            }
+        );
+        assert_eq!(ranked[1].san, ["c4"]);
+        assert_eq!(ranked[1].score.bound(), None);
+    }
+
+    #[test]
+    fn test_illegal_pv_move() {
+        let mut aggregator = MultiPvAggregator::new(Position::default());
+        assert!(matches!(
+            push(&mut aggregator, "info depth 1 score cp 10 pv e2e5"),
+            Err(SfError::IllegalPvMove(mv)) if mv == "e2e5"
+        ));
+    }
+}
@@ -19,10 +19,8 @@ pub enum SfCommand {
     /// If the position is from a new game, ucinewgame has to be sent first
     /// It's best to use the moves instead of the raw FEN so the engine has more game-knowledge (repetition, etc.)
     Position(SfPosition),
-    SetOption {
-        name: String,
-        value: Option<String>,
-    },
+    /// Change an engine option, prefer building this via [`EngineOptions`](crate::stockfish::options::EngineOptions) which validates the value
+    SetOption { name: String, value: Option<String> },
     /// Start searching for the best move
     Go(SfGo),
 }
@@ -72,6 +70,8 @@ pub struct SfGo {
     pub depth: Option<u64>,
     /// Stop search if approaching this time in ms
     pub move_time: Option<u64>,
+    /// Stop the search after this many nodes
+    pub nodes: Option<u64>,
     /// Search until the stop command is sent
     pub infinite: bool,
     /// How much time white has left in ms
@@ -98,6 +98,9 @@ impl Display for SfGo {
         if let Some(time) = self.move_time {
             write!(f, " movetime {}", time)?;
         }
+        if let Some(nodes) = self.nodes {
+            write!(f, " nodes {}", nodes)?;
+        }
         if let Some(time) = self.white_time {
             write!(f, " wtime {}", time)?;
         }
@@ -0,0 +1,217 @@
+use crate::engine::{Engine, SearchLimit, SearchLine, SearchResult};
+use crate::error::{EngineError, EngineResult};
+use crate::game::Game;
+use crate::prelude::Color;
+use crate::stockfish::StockfishManager;
+use crate::stockfish::analysis::MultiPvAggregator;
+use crate::stockfish::command::{SfCommand, SfGo, SfPosition};
+use crate::stockfish::event::{SfEvent, SfId};
+use crate::stockfish::options::{EngineOptions, MULTI_PV};
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// Used when the search limit doesn't restrict the search at all
+const DEFAULT_MOVE_TIME_MS: u64 = 1000;
+
+/// Runs a UCI engine (like Stockfish) as a child process and drives it through a [`StockfishManager`].
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    manager: StockfishManager,
+    name: Option<String>,
+    multi_pv: u32,
+}
+
+impl UciEngine {
+    /// Starts the engine and waits for the UCI handshake to complete
+    pub fn spawn(program: impl AsRef<OsStr>) -> EngineResult<Self> {
+        let mut child = Command::new(program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| EngineError::Protocol("Engine stdin unavailable".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| EngineError::Protocol("Engine stdout unavailable".to_string()))?;
+
+        let mut engine = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            manager: StockfishManager::default(),
+            name: None,
+            multi_pv: 1,
+        };
+
+        engine.flush()?;
+        loop {
+            match engine.next_event()? {
+                SfEvent::Id(SfId::Name(name)) => engine.name = Some(name),
+                SfEvent::Ok => break,
+                _ => {}
+            }
+        }
+        engine.sync()?;
+        Ok(engine)
+    }
+
+    /// The name the engine reported during the handshake
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn options(&self) -> &EngineOptions {
+        self.manager.options()
+    }
+
+    /// Validates the value against the engine's declared options before sending it
+    pub fn set_option(&mut self, name: &str, value: &str) -> EngineResult<()> {
+        self.manager
+            .set_option(name, value)
+            .map_err(|err| EngineError::Protocol(err.to_string()))?;
+        if name.eq_ignore_ascii_case(MULTI_PV) {
+            self.multi_pv = value.parse().unwrap_or(self.multi_pv);
+        }
+        self.flush()?;
+        self.sync()
+    }
+
+    fn flush(&mut self) -> EngineResult<()> {
+        for cmd in self.manager.drain_commands() {
+            writeln!(self.stdin, "{cmd}")?;
+        }
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Blocks until the engine produced the next event, lines that can't be parsed are skipped
+    fn next_event(&mut self) -> EngineResult<SfEvent> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err(EngineError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+            }
+            if let Ok(Some(event)) = self.manager.read_line(&line) {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Waits until the engine processed all previous commands
+    fn sync(&mut self) -> EngineResult<()> {
+        self.manager.send(SfCommand::IsReady);
+        self.flush()?;
+        while !matches!(self.next_event()?, SfEvent::ReadyOk) {}
+        Ok(())
+    }
+}
+
+impl Engine for UciEngine {
+    fn new_game(&mut self) -> EngineResult<()> {
+        self.manager.send(SfCommand::UciNewGame);
+        self.sync()
+    }
+
+    fn search(&mut self, game: &Game, limit: &SearchLimit) -> EngineResult<SearchResult> {
+        if let Some(result) = SearchResult::for_finished(game) {
+            return Ok(result);
+        }
+
+        let multi_pv = limit.multi_pv.max(1);
+        if multi_pv != self.multi_pv && self.options().contains(MULTI_PV) {
+            self.set_option(MULTI_PV, &multi_pv.to_string())?;
+        }
+
+        let start = *game.start_position();
+        let fen = (start != Default::default()).then(|| start.to_string());
+        self.manager.send(SfCommand::Position(SfPosition {
+            fen,
+            moves: game.history().iter().map(|mv| mv.to_uci()).collect(),
+        }));
+
+        let unlimited = limit.depth.is_none()
+            && limit.move_time_ms.is_none()
+            && limit.nodes.is_none()
+            && limit.clock.is_none();
+        self.manager.send(SfCommand::Go(SfGo {
+            depth: limit.depth.map(u64::from),
+            move_time: limit
+                .move_time_ms
+                .or(unlimited.then_some(DEFAULT_MOVE_TIME_MS)),
+            nodes: limit.nodes,
+            white_time: limit.clock.map(|clock| clock.white_ms),
+            black_time: limit.clock.map(|clock| clock.black_ms),
+            white_inc: limit.clock.map(|clock| clock.white_inc_ms),
+            black_inc: limit.clock.map(|clock| clock.black_inc_ms),
+            ..Default::default()
+        }));
+        self.flush()?;
+
+        // Output is read up to the best move even after an error, so the next search doesn't see it
+        let mut aggregator = MultiPvAggregator::new(*game.position());
+        let mut error = None;
+        let best = loop {
+            match self.next_event()? {
+                SfEvent::Info(info) => {
+                    if let Err(err) = aggregator.push(&info) {
+                        error.get_or_insert(EngineError::IllegalMove(err.to_string()));
+                    }
+                }
+                SfEvent::BestMove { mv, .. } => break mv,
+                _ => {}
+            }
+        };
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        let best = game
+            .legal_moves()
+            .iter()
+            .copied()
+            .find(|mv| mv.to_uci() == best)
+            .ok_or(EngineError::IllegalMove(best))?;
+
+        let mut lines: Vec<SearchLine> = aggregator
+            .ranked()
+            .into_iter()
+            .take(multi_pv as usize)
+            .map(|line| SearchLine {
+                score: line.score.to_score(Color::White),
+                depth: line.depth,
+                pv: line.pv.clone(),
+            })
+            .collect();
+
+        // The reported best move is authoritative, even if the last info lines disagree
+        match lines.iter().position(|line| line.pv.first() == Some(&best)) {
+            Some(index) => lines[..=index].rotate_right(1),
+            None => lines.insert(
+                0,
+                SearchLine {
+                    score: lines.first().map(|line| line.score).unwrap_or_default(),
+                    depth: 0,
+                    pv: vec![best],
+                },
+            ),
+        }
+        Ok(SearchResult { lines })
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        self.manager.send(SfCommand::Quit);
+        if self.flush().is_err() || self.child.wait().is_err() {
+            let _ = self.child.kill();
+        }
+    }
+}
@@ -2,8 +2,19 @@ pub type SfResult<T> = Result<T, SfError>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum SfError {
+    #[error("Illegal move in principal variation: {0}")]
+    IllegalPvMove(String),
+    #[error("Invalid value for option {name}: {value}")]
+    InvalidOptionValue { name: String, value: String },
     #[error("Missing expected token/terminator: {0}")]
     MissingToken(String),
+    #[error("Value {value} for option {name} is out of range {min}..={max}")]
+    OptionOutOfRange {
+        name: String,
+        value: i64,
+        min: i64,
+        max: i64,
+    },
     #[error("Parse failed: {0}")]
     ParseFailed(String),
     #[error("Unexpected EOF")]
@@ -16,6 +27,8 @@ pub enum SfError {
     UnknownIdValue { id_type: String, value: String },
     #[error("Invalid option value of type {value_type}: {value}")]
     UnknownOptionValue { value_type: String, value: String },
+    #[error("Unknown option: {0}")]
+    UnknownOption(String),
     #[error("Unknown score type: {0}")]
     UnknownScoreType(String),
 }
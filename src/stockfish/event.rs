@@ -1,3 +1,5 @@
+use crate::engine::score::Score;
+use crate::prelude::Color;
 use crate::stockfish::error::{SfError, SfResult};
 use crate::stockfish::reader::{FromTokens, TokenReader};
 
@@ -85,6 +87,7 @@ pub enum SfOptionValue {
     Check(bool),
     String(String),
     Spin { default: i64, min: i64, max: i64 },
+    Combo { default: String, vars: Vec<String> },
 }
 
 impl FromTokens for SfOptionValue {
@@ -102,6 +105,16 @@ impl FromTokens for SfOptionValue {
                 min: reader.parse_assert_prefix("min")?,
                 max: reader.parse_assert_prefix("max")?,
             }),
+            "combo" => {
+                reader.assert_next("default")?;
+                let default = reader.read_until("var");
+                let mut vars = Vec::new();
+                while reader.peek() == Some("var") {
+                    reader.try_next()?;
+                    vars.push(reader.read_until("var"));
+                }
+                Ok(SfOptionValue::Combo { default, vars })
+            }
             _ => Err(SfError::UnknownOptionValue {
                 value_type: option_type.to_string(),
                 value: reader.consume(),
@@ -142,25 +155,101 @@ pub struct SfInfo {
     pub string: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+/// Engines report scores from the perspective of the side to move
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum SfScore {
     Cp { value: i32, bound: Option<SfBound> },
     Mate { value: i32, bound: Option<SfBound> },
 }
 
-#[derive(Debug, Clone)]
+impl SfScore {
+    /// The same score from the other side's perspective, bounds are swapped accordingly
+    pub fn flipped(&self) -> Self {
+        match *self {
+            Self::Cp { value, bound } => Self::Cp {
+                value: -value,
+                bound: bound.map(|b| b.flipped()),
+            },
+            Self::Mate { value, bound } => Self::Mate {
+                value: -value,
+                bound: bound.map(|b| b.flipped()),
+            },
+        }
+    }
+
+    /// Converts a score reported for the given side to move to white's perspective
+    pub fn white_perspective(&self, side_to_move: Color) -> Self {
+        match side_to_move {
+            Color::White => *self,
+            Color::Black => self.flipped(),
+        }
+    }
+
+    /// Converts a score reported for the given side to move into a white-perspective [`Score`], bounds are dropped
+    pub fn to_score(&self, side_to_move: Color) -> Score {
+        match *self {
+            Self::Cp { value, .. } => Score::from_perspective(value, side_to_move),
+            Self::Mate { value, .. } => {
+                let winner = if value > 0 {
+                    side_to_move
+                } else {
+                    side_to_move.opposite()
+                };
+                Score::Mate {
+                    winner,
+                    moves: value.unsigned_abs(),
+                }
+            }
+        }
+    }
+
+    pub fn bound(&self) -> Option<SfBound> {
+        match self {
+            Self::Cp { bound, .. } | Self::Mate { bound, .. } => *bound,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum SfBound {
     Upper,
     Lower,
 }
 
-#[derive(Debug, Clone, Default)]
+impl SfBound {
+    pub fn flipped(&self) -> Self {
+        match self {
+            Self::Upper => Self::Lower,
+            Self::Lower => Self::Upper,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
 pub struct SfWdl {
     pub win: u32,
     pub draw: u32,
     pub loss: u32,
 }
 
+impl SfWdl {
+    pub fn flipped(&self) -> Self {
+        Self {
+            win: self.loss,
+            draw: self.draw,
+            loss: self.win,
+        }
+    }
+
+    /// Converts probabilities reported for the given side to move to white's perspective
+    pub fn white_perspective(&self, side_to_move: Color) -> Self {
+        match side_to_move {
+            Color::White => *self,
+            Color::Black => self.flipped(),
+        }
+    }
+}
+
 impl FromTokens for SfInfo {
     fn parse(reader: &mut TokenReader) -> SfResult<Self> {
         let mut info = SfInfo::default();
@@ -0,0 +1,257 @@
+use crate::stockfish::command::SfCommand;
+use crate::stockfish::error::{SfError, SfResult};
+use crate::stockfish::event::{SfOption, SfOptionValue};
+
+pub const HASH: &str = "Hash";
+pub const THREADS: &str = "Threads";
+pub const MULTI_PV: &str = "MultiPV";
+pub const UCI_LIMIT_STRENGTH: &str = "UCI_LimitStrength";
+pub const UCI_ELO: &str = "UCI_Elo";
+pub const UCI_SHOW_WDL: &str = "UCI_ShowWDL";
+
+/// The options an engine announced during the `uci` handshake.
+/// Used to validate `setoption` commands before they are sent to the engine.
+#[derive(Debug, Default, Clone)]
+pub struct EngineOptions {
+    options: Vec<SfOption>,
+}
+
+impl EngineOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an option, replacing a previously registered option of the same name
+    pub fn register(&mut self, option: SfOption) {
+        match self.position(&option.name) {
+            Some(index) => self.options[index] = option,
+            None => self.options.push(option),
+        }
+    }
+
+    /// Option names are case-insensitive as per the UCI protocol
+    pub fn get(&self, name: &str) -> Option<&SfOption> {
+        self.position(name).map(|index| &self.options[index])
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SfOption> {
+        self.options.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.options.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+
+    /// Validates a raw value against the option's declaration and builds the `setoption` command
+    pub fn set(&self, name: &str, value: &str) -> SfResult<SfCommand> {
+        let option = self.try_get(name)?;
+        let value = match &option.value {
+            SfOptionValue::Button => return Err(invalid_value(option, value)),
+            SfOptionValue::Check(_) => match value {
+                "true" | "false" => value.to_string(),
+                _ => return Err(invalid_value(option, value)),
+            },
+            SfOptionValue::Spin { .. } => {
+                let parsed = value
+                    .parse::<i64>()
+                    .map_err(|_| invalid_value(option, value))?;
+                return self.set_spin(name, parsed);
+            }
+            SfOptionValue::Combo { vars, .. } => vars
+                .iter()
+                .find(|var| var.eq_ignore_ascii_case(value))
+                .cloned()
+                .ok_or_else(|| invalid_value(option, value))?,
+            SfOptionValue::String(_) => value.to_string(),
+        };
+        Ok(set_option(option, Some(value)))
+    }
+
+    pub fn set_check(&self, name: &str, value: bool) -> SfResult<SfCommand> {
+        let option = self.try_get(name)?;
+        match option.value {
+            SfOptionValue::Check(_) => Ok(set_option(option, Some(value.to_string()))),
+            _ => Err(invalid_value(option, &value.to_string())),
+        }
+    }
+
+    pub fn set_spin(&self, name: &str, value: i64) -> SfResult<SfCommand> {
+        let option = self.try_get(name)?;
+        match option.value {
+            SfOptionValue::Spin { min, max, .. } => {
+                if value < min || value > max {
+                    return Err(SfError::OptionOutOfRange {
+                        name: option.name.clone(),
+                        value,
+                        min,
+                        max,
+                    });
+                }
+                Ok(set_option(option, Some(value.to_string())))
+            }
+            _ => Err(invalid_value(option, &value.to_string())),
+        }
+    }
+
+    /// Triggers a button option such as `Clear Hash`
+    pub fn press(&self, name: &str) -> SfResult<SfCommand> {
+        let option = self.try_get(name)?;
+        match option.value {
+            SfOptionValue::Button => Ok(set_option(option, None)),
+            _ => Err(invalid_value(option, "")),
+        }
+    }
+
+    fn try_get(&self, name: &str) -> SfResult<&SfOption> {
+        self.get(name)
+            .ok_or_else(|| SfError::UnknownOption(name.to_string()))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.options
+            .iter()
+            .position(|option| option.name.eq_ignore_ascii_case(name))
+    }
+}
+
+// Well-known options
+impl EngineOptions {
+    /// Size of the transposition table in MB
+    pub fn hash(&self, mb: i64) -> SfResult<SfCommand> {
+        self.set_spin(HASH, mb)
+    }
+
+    pub fn threads(&self, threads: i64) -> SfResult<SfCommand> {
+        self.set_spin(THREADS, threads)
+    }
+
+    /// How many principal variations the engine reports
+    pub fn multi_pv(&self, lines: i64) -> SfResult<SfCommand> {
+        self.set_spin(MULTI_PV, lines)
+    }
+
+    /// Makes the engine play at the strength set via [`EngineOptions::elo`]
+    pub fn limit_strength(&self, enabled: bool) -> SfResult<SfCommand> {
+        self.set_check(UCI_LIMIT_STRENGTH, enabled)
+    }
+
+    pub fn elo(&self, elo: i64) -> SfResult<SfCommand> {
+        self.set_spin(UCI_ELO, elo)
+    }
+
+    /// Makes the engine report win/draw/loss probabilities in its info lines
+    pub fn show_wdl(&self, enabled: bool) -> SfResult<SfCommand> {
+        self.set_check(UCI_SHOW_WDL, enabled)
+    }
+}
+
+fn set_option(option: &SfOption, value: Option<String>) -> SfCommand {
+    SfCommand::SetOption {
+        name: option.name.clone(),
+        value,
+    }
+}
+
+fn invalid_value(option: &SfOption, value: &str) -> SfError {
+    SfError::InvalidOptionValue {
+        name: option.name.clone(),
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stockfish::error::SfError;
+    use crate::stockfish::event::{SfEvent, SfOptionValue};
+    use crate::stockfish::options::EngineOptions;
+    use crate::stockfish::reader::{FromTokens, TokenReader};
+
+    fn options() -> EngineOptions {
+        let lines = [
+            "option name Threads type spin default 1 min 1 max 1024",
+            "option name Hash type spin default 16 min 1 max 33554432",
+            "option name Clear Hash type button",
+            "option name UCI_LimitStrength type check default false",
+            "option name UCI_Elo type spin default 1320 min 1320 max 3190",
+            "option name Analysis Contempt type combo default Both var Off var White var Black var Both",
+            "option name SyzygyPath type string default <empty>",
+        ];
+
+        let mut options = EngineOptions::new();
+        for line in lines {
+            let SfEvent::Option(option) = SfEvent::parse(&mut TokenReader::new(line)).unwrap()
+            else {
+                panic!("Expected option event for {line}");
+            };
+            options.register(option);
+        }
+        options
+    }
+
+    #[test]
+    fn test_parse_combo() {
+        let options = options();
+        let option = options.get("analysis contempt").unwrap();
+        match &option.value {
+            SfOptionValue::Combo { default, vars } => {
+                assert_eq!(default, "Both");
+                assert_eq!(vars, &["Off", "White", "Black", "Both"]);
+            }
+            other => panic!("Expected combo, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_spin_bounds() {
+        let options = options();
+        assert_eq!(
+            options.hash(256).unwrap().to_string(),
+            "setoption name Hash value 256"
+        );
+        assert!(matches!(
+            options.threads(0),
+            Err(SfError::OptionOutOfRange { min: 1, .. })
+        ));
+        assert!(matches!(
+            options.elo(4000),
+            Err(SfError::OptionOutOfRange { max: 3190, .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_and_combo_values() {
+        let options = options();
+        assert_eq!(
+            options.limit_strength(true).unwrap().to_string(),
+            "setoption name UCI_LimitStrength value true"
+        );
+        assert!(options.set("UCI_LimitStrength", "yes").is_err());
+        assert_eq!(
+            options
+                .set("Analysis Contempt", "white")
+                .unwrap()
+                .to_string(),
+            "setoption name Analysis Contempt value White"
+        );
+        assert!(options.set("Analysis Contempt", "Both Sides").is_err());
+    }
+
+    #[test]
+    fn test_unknown_option() {
+        let options = options();
+        assert!(matches!(
+            options.multi_pv(3),
+            Err(SfError::UnknownOption(name)) if name == "MultiPV"
+        ));
+        assert!(options.press("Clear Hash").is_ok());
+        assert!(options.press("Hash").is_err());
+    }
+}
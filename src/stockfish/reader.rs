@@ -72,6 +72,20 @@ impl<'a> TokenReader<'a> {
         Ok(result)
     }
 
+    /// Reads until the terminator without consuming it, the terminator is optional
+    pub fn read_until(&mut self, terminator: &str) -> String {
+        let mut result = String::new();
+
+        while let Some(token) = self.tokens.next_if(|token| *token != terminator) {
+            if !result.is_empty() {
+                result.push(' ');
+            }
+            result.push_str(token);
+        }
+
+        result
+    }
+
     pub fn consume(&mut self) -> String {
         let rest: Vec<&str> = self.tokens.by_ref().collect();
         rest.join(" ")
@@ -0,0 +1,328 @@
+use crate::engine::score::Score;
+use crate::engine::{Engine, SearchClock, SearchLimit};
+use crate::error::{EngineError, SessionResult};
+use crate::game::outcome::GameOutcome;
+use crate::notation::pgn::PgnHeaders;
+use crate::prelude::{ChessMove, Color};
+use crate::session::action::SessionAction;
+use crate::session::clock::ChessClockConfig;
+use crate::session::config::{SessionConfig, TimeControl};
+use crate::session::{Session, SessionRecord};
+use crate::tournament::adjudication::{
+    Adjudication, AdjudicationConfig, Adjudicator, TablebaseProbe,
+};
+use crate::tournament::book::OpeningBook;
+use crate::tournament::stats::{MatchStats, Sprt, SprtVerdict};
+use std::time::Instant;
+
+pub mod adjudication;
+pub mod book;
+pub mod stats;
+
+pub struct MatchPlayer {
+    pub name: String,
+    pub engine: Box<dyn Engine>,
+}
+
+impl MatchPlayer {
+    pub fn new(name: impl Into<String>, engine: impl Engine + 'static) -> Self {
+        Self {
+            name: name.into(),
+            engine: Box::new(engine),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchConfig {
+    /// Maximum amount of games, the match may end earlier if the SPRT concludes
+    pub games: usize,
+    pub time_control: ChessClockConfig,
+    /// Additional limits for every search, the remaining clock time is always passed to the engines
+    pub limit: SearchLimit,
+    /// Every opening is played twice with alternating colors, the default position is used without a book
+    pub book: Option<OpeningBook>,
+    pub adjudication: AdjudicationConfig,
+    pub sprt: Option<Sprt>,
+    /// The event name of the PGN headers
+    pub event: Option<String>,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            time_control: ChessClockConfig::fischer(10_000, 100),
+            limit: SearchLimit::default(),
+            book: None,
+            adjudication: AdjudicationConfig::default(),
+            sprt: None,
+            event: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchGame {
+    /// 0-indexed, the first player plays white in even rounds
+    pub round: usize,
+    pub first_player_color: Color,
+    pub outcome: GameOutcome,
+    /// Set if the game was ended early by the adjudication rules
+    pub adjudication: Option<Adjudication>,
+    /// Set if an engine failed and forfeited the game
+    pub engine_error: Option<(Color, String)>,
+    pub record: SessionRecord,
+    pub pgn: String,
+}
+
+/// Plays a match between two engines, the stats are kept from the first player's perspective.
+///
+/// All games run through a [`Session`] with a clock so time losses are handled like in a real game.
+pub struct Match {
+    config: MatchConfig,
+    players: [MatchPlayer; 2],
+    tablebase: Option<Box<dyn TablebaseProbe>>,
+    stats: MatchStats,
+    games: Vec<MatchGame>,
+    epoch: Instant,
+}
+
+impl Match {
+    pub fn new(config: MatchConfig, first: MatchPlayer, second: MatchPlayer) -> Self {
+        Self {
+            config,
+            players: [first, second],
+            tablebase: None,
+            stats: MatchStats::default(),
+            games: Vec::new(),
+            epoch: Instant::now(),
+        }
+    }
+
+    pub fn with_tablebase(mut self, tablebase: impl TablebaseProbe + 'static) -> Self {
+        self.tablebase = Some(Box::new(tablebase));
+        self
+    }
+
+    /// Plays games until the configured amount is reached or the SPRT concluded
+    pub fn run(&mut self) -> SessionResult<&MatchStats> {
+        while self.play_next()?.is_some() {}
+        Ok(&self.stats)
+    }
+
+    /// Plays the next game, None if the match is finished
+    pub fn play_next(&mut self) -> SessionResult<Option<&MatchGame>> {
+        if self.is_finished() {
+            return Ok(None);
+        }
+
+        let game = self.play_game(self.games.len())?;
+        match game.outcome {
+            GameOutcome::Decisive { winner, .. } if winner == game.first_player_color => {
+                self.stats.wins += 1
+            }
+            GameOutcome::Decisive { .. } => self.stats.losses += 1,
+            GameOutcome::Draw(_) => self.stats.draws += 1,
+            GameOutcome::Aborted(_) => {}
+        }
+        self.games.push(game);
+        Ok(self.games.last())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.games.len() >= self.config.games || self.sprt_verdict() != SprtVerdict::Continue
+    }
+
+    /// Continue if no SPRT is configured
+    pub fn sprt_verdict(&self) -> SprtVerdict {
+        self.config
+            .sprt
+            .map_or(SprtVerdict::Continue, |sprt| sprt.verdict(&self.stats))
+    }
+
+    fn play_game(&mut self, round: usize) -> SessionResult<MatchGame> {
+        let epoch = self.epoch;
+        let now_ms = || epoch.elapsed().as_millis() as u64;
+
+        let first_player_color = if round.is_multiple_of(2) {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let player_index = |color: Color| usize::from(color != first_player_color);
+
+        let opening = self
+            .config
+            .book
+            .as_ref()
+            .and_then(|book| book.opening(round / 2))
+            .cloned()
+            .unwrap_or_default();
+        let session_config = SessionConfig {
+            starting_position: opening.starting_position(),
+            time_control: TimeControl::Clock(self.config.time_control.clone()),
+            pgn: PgnHeaders {
+                event: self.config.event.clone(),
+                round: Some((round + 1).to_string()),
+                white: Some(self.players[player_index(Color::White)].name.clone()),
+                black: Some(self.players[player_index(Color::Black)].name.clone()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut session = Session::from_config(&session_config)?;
+        for mv in &opening.moves {
+            session.act(session.turn(), SessionAction::Move(*mv), now_ms())?;
+        }
+
+        let mut adjudicator =
+            Adjudicator::new(&self.config.adjudication, self.tablebase.as_deref());
+        let mut adjudication = None;
+        let mut engine_error = None;
+
+        for color in [first_player_color, first_player_color.opposite()] {
+            if let Err(err) = self.players[player_index(color)].engine.new_game() {
+                engine_error.get_or_insert((color, err.to_string()));
+            }
+        }
+        if let Some((color, _)) = engine_error {
+            session.act(color, SessionAction::Resign, now_ms())?;
+        }
+
+        while !session.game().is_over() {
+            let color = session.turn();
+            let mut limit = self.config.limit.clone();
+            if let Some(clock) = session.clock() {
+                let now = now_ms();
+                limit.clock = Some(SearchClock {
+                    white_ms: clock.remaining_ms(Color::White, now),
+                    black_ms: clock.remaining_ms(Color::Black, now),
+                    white_inc_ms: clock.increment_ms(Color::White),
+                    black_inc_ms: clock.increment_ms(Color::Black),
+                });
+            }
+
+            let engine = &mut self.players[player_index(color)].engine;
+            let (mv, score) = match search(engine.as_mut(), &session, &limit) {
+                Ok(found) => found,
+                Err(err) => {
+                    engine_error = Some((color, err.to_string()));
+                    session.act(color, SessionAction::Resign, now_ms())?;
+                    break;
+                }
+            };
+
+            let now = now_ms();
+            if session
+                .clock()
+                .is_some_and(|clock| clock.is_out_of_time(color, now))
+            {
+                session.act(color.opposite(), SessionAction::ClaimTimeout, now)?;
+                break;
+            }
+            session.act(color, SessionAction::Move(mv), now)?;
+            if session.game().is_over() {
+                break;
+            }
+
+            if let Some(verdict) = adjudicator.update(session.game(), score) {
+                adjudication = Some(verdict);
+                session.adjudicate(verdict.winner, verdict.reason.to_string())?;
+            }
+        }
+
+        Ok(MatchGame {
+            round,
+            first_player_color,
+            outcome: session
+                .game()
+                .outcome()
+                .expect("The game loop only ends once the game is over"),
+            adjudication,
+            engine_error,
+            record: session.record(),
+            pgn: session.pgn(),
+        })
+    }
+}
+
+// Accessors
+impl Match {
+    pub fn config(&self) -> &MatchConfig {
+        &self.config
+    }
+
+    pub fn players(&self) -> &[MatchPlayer; 2] {
+        &self.players
+    }
+
+    pub fn stats(&self) -> &MatchStats {
+        &self.stats
+    }
+
+    pub fn games(&self) -> &[MatchGame] {
+        &self.games
+    }
+}
+
+fn search(
+    engine: &mut dyn Engine,
+    session: &Session,
+    limit: &SearchLimit,
+) -> Result<(ChessMove, Option<Score>), EngineError> {
+    let result = engine.search(session.game(), limit)?;
+    let mv = result.best_move().ok_or(EngineError::NoBestMove)?;
+    Ok((mv, result.score()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::SearchLimit;
+    use crate::engine::search::MaterialSearch;
+    use crate::prelude::Color;
+    use crate::tournament::adjudication::{AdjudicationConfig, AdjudicationReason};
+    use crate::tournament::book::OpeningBook;
+    use crate::tournament::{Match, MatchConfig, MatchPlayer};
+
+    #[test]
+    fn test_match_alternates_colors_and_adjudicates() {
+        let book = OpeningBook::from_pgn("1. e4 e5 2. Nf3 Nc6 *\n\n1. d4 d5 *", Some(2)).unwrap();
+        let config = MatchConfig {
+            games: 4,
+            limit: SearchLimit::depth(1),
+            book: Some(book),
+            adjudication: AdjudicationConfig {
+                max_plies: Some(12),
+                ..Default::default()
+            },
+            event: Some("Test".to_string()),
+            ..Default::default()
+        };
+        let mut tournament = Match::new(
+            config,
+            MatchPlayer::new("First", MaterialSearch::new(1)),
+            MatchPlayer::new("Second", MaterialSearch::new(1)),
+        );
+
+        let stats = *tournament.run().unwrap();
+        assert_eq!(stats.games(), 4);
+        assert!(tournament.is_finished());
+
+        let games = tournament.games();
+        assert_eq!(games[0].first_player_color, Color::White);
+        assert_eq!(games[1].first_player_color, Color::Black);
+        assert_eq!(games[0].record.moves[..2], games[1].record.moves[..2]);
+        assert_ne!(games[0].record.moves[0], games[2].record.moves[0]);
+        assert!(games[1].pgn.contains("[White \"Second\"]"));
+        for game in games {
+            assert!(game.engine_error.is_none());
+            assert!(game.record.moves.len() <= 12);
+            if let Some(adjudication) = game.adjudication {
+                assert_ne!(adjudication.reason, AdjudicationReason::Tablebase);
+                assert!(game.pgn.contains("[Termination \"adjudication\"]"));
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::core::position::Position;
+use crate::engine::score::Score;
+use crate::game::Game;
+use crate::prelude::Color;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdjudicationConfig {
+    pub resign: Option<ResignRule>,
+    pub draw: Option<DrawRule>,
+    /// Declare the game drawn once it reaches this many plies
+    pub max_plies: Option<usize>,
+}
+
+impl Default for AdjudicationConfig {
+    fn default() -> Self {
+        Self {
+            resign: Some(ResignRule {
+                score_cp: 1000,
+                moves: 3,
+            }),
+            draw: Some(DrawRule {
+                score_cp: 10,
+                moves: 8,
+                min_ply: 80,
+            }),
+            max_plies: None,
+        }
+    }
+}
+
+/// A side loses once both engines agree it's behind by at least `score_cp` for `moves` consecutive moves
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResignRule {
+    pub score_cp: i32,
+    pub moves: u32,
+}
+
+/// The game is drawn once both engines report a score within ±`score_cp` for `moves` consecutive moves
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DrawRule {
+    pub score_cp: i32,
+    pub moves: u32,
+    /// Only start counting after this many plies
+    pub min_ply: usize,
+}
+
+/// Win/Draw/Loss from the perspective of the side to move
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TablebaseWdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Access to endgame tablebases, for example Syzygy tables
+pub trait TablebaseProbe {
+    /// The most pieces (kings included) a position may have to be covered
+    fn max_pieces(&self) -> u8;
+
+    /// None if the position isn't covered by the tables
+    fn probe(&self, pos: &Position) -> Option<TablebaseWdl>;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AdjudicationReason {
+    Resign,
+    Draw,
+    MaxPlies,
+    Tablebase,
+}
+
+impl Display for AdjudicationReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Self::Resign => "Resign score threshold reached",
+            Self::Draw => "Draw score threshold reached",
+            Self::MaxPlies => "Maximum game length reached",
+            Self::Tablebase => "Tablebase position",
+        };
+        f.write_str(description)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Adjudication {
+    /// None if the game was adjudicated as a draw
+    pub winner: Option<Color>,
+    pub reason: AdjudicationReason,
+}
+
+/// Watches the engine scores of a single game and decides when to end it early
+pub struct Adjudicator<'a> {
+    config: &'a AdjudicationConfig,
+    tablebase: Option<&'a dyn TablebaseProbe>,
+    /// Consecutive plies white at 0, black at 1 was behind by the resign score
+    losing_plies: [u32; 2],
+    drawish_plies: u32,
+}
+
+impl<'a> Adjudicator<'a> {
+    pub fn new(config: &'a AdjudicationConfig, tablebase: Option<&'a dyn TablebaseProbe>) -> Self {
+        Self {
+            config,
+            tablebase,
+            losing_plies: [0, 0],
+            drawish_plies: 0,
+        }
+    }
+
+    /// Called after every move with the score the moving engine reported
+    pub fn update(&mut self, game: &Game, score: Option<Score>) -> Option<Adjudication> {
+        if let Some(adjudication) = self.probe(game.position()) {
+            return Some(adjudication);
+        }
+
+        let ply = game.history().len();
+        if self.config.max_plies.is_some_and(|max| ply >= max) {
+            return Some(Adjudication {
+                winner: None,
+                reason: AdjudicationReason::MaxPlies,
+            });
+        }
+
+        let Some(score) = score else {
+            self.losing_plies = [0, 0];
+            self.drawish_plies = 0;
+            return None;
+        };
+
+        if let Some(rule) = self.config.resign {
+            for color in [Color::White, Color::Black] {
+                let index = color as usize;
+                if score.cp_for(color) <= -rule.score_cp {
+                    self.losing_plies[index] += 1;
+                } else {
+                    self.losing_plies[index] = 0;
+                }
+                if self.losing_plies[index] >= rule.moves * 2 {
+                    return Some(Adjudication {
+                        winner: Some(color.opposite()),
+                        reason: AdjudicationReason::Resign,
+                    });
+                }
+            }
+        }
+
+        if let Some(rule) = self.config.draw {
+            if ply >= rule.min_ply
+                && !score.is_mate()
+                && score.cp_for(Color::White).abs() <= rule.score_cp
+            {
+                self.drawish_plies += 1;
+            } else {
+                self.drawish_plies = 0;
+            }
+            if self.drawish_plies >= rule.moves * 2 {
+                return Some(Adjudication {
+                    winner: None,
+                    reason: AdjudicationReason::Draw,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn probe(&self, pos: &Position) -> Option<Adjudication> {
+        let tablebase = self.tablebase?;
+        if pos.board.total_piece_count() > tablebase.max_pieces() {
+            return None;
+        }
+        let winner = match tablebase.probe(pos)? {
+            TablebaseWdl::Win => Some(pos.side_to_move),
+            TablebaseWdl::Draw => None,
+            TablebaseWdl::Loss => Some(pos.side_to_move.opposite()),
+        };
+        Some(Adjudication {
+            winner,
+            reason: AdjudicationReason::Tablebase,
+        })
+    }
+}
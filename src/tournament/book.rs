@@ -0,0 +1,90 @@
+use crate::core::position::Position;
+use crate::error::PgnResult;
+use crate::notation::pgn::parse_pgn;
+use crate::prelude::ChessMove;
+use crate::session::config::StartingPosition;
+use std::str::FromStr;
+
+/// A starting position for a pair of games, played once with each engine as white
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Opening {
+    /// None for the default starting position
+    pub fen: Option<String>,
+    /// Moves played from the starting position before the engines take over
+    pub moves: Vec<ChessMove>,
+}
+
+impl Opening {
+    pub fn starting_position(&self) -> StartingPosition {
+        match &self.fen {
+            Some(fen) => StartingPosition::Fen(fen.clone()),
+            None => StartingPosition::Default,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OpeningBook {
+    openings: Vec<Opening>,
+}
+
+impl OpeningBook {
+    pub fn new(openings: Vec<Opening>) -> Self {
+        Self { openings }
+    }
+
+    /// One position per line, only the first four EPD fields are used and operations are ignored
+    pub fn from_epd(epd: &str) -> PgnResult<Self> {
+        let mut openings = Vec::new();
+        for line in epd.lines() {
+            let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+            if fields.len() < 4 || fields[0].starts_with('#') {
+                continue;
+            }
+            let fen = format!("{} 0 1", fields.join(" "));
+            Position::from_str(&fen)?;
+            openings.push(Opening {
+                fen: Some(fen),
+                moves: vec![],
+            });
+        }
+        Ok(Self { openings })
+    }
+
+    /// Every game's main line becomes an opening, optionally cut off after `max_plies`
+    pub fn from_pgn(pgn: &str, max_plies: Option<usize>) -> PgnResult<Self> {
+        let openings = parse_pgn(pgn)?
+            .into_iter()
+            .map(|mut game| {
+                if let Some(max_plies) = max_plies {
+                    game.moves.truncate(max_plies);
+                }
+                Opening {
+                    fen: game.fen,
+                    moves: game.moves,
+                }
+            })
+            .collect();
+        Ok(Self { openings })
+    }
+
+    /// The opening for the given pair of games, wraps around once the book is exhausted
+    pub fn opening(&self, pair: usize) -> Option<&Opening> {
+        if self.openings.is_empty() {
+            return None;
+        }
+        self.openings.get(pair % self.openings.len())
+    }
+
+    pub fn openings(&self) -> &[Opening] {
+        &self.openings
+    }
+
+    pub fn len(&self) -> usize {
+        self.openings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.openings.is_empty()
+    }
+}
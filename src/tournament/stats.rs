@@ -0,0 +1,213 @@
+use std::fmt::{Display, Formatter};
+
+/// z-score of the 95% confidence interval
+const Z_95: f64 = 1.959964;
+
+/// Results from the perspective of the first player of a match
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Average points per game (0-1), None if no games were played
+    pub fn score(&self) -> Option<f64> {
+        let games = self.games();
+        if games == 0 {
+            return None;
+        }
+        Some((self.wins as f64 + self.draws as f64 / 2.0) / games as f64)
+    }
+
+    /// Estimated Elo difference with its 95% error margin
+    pub fn elo(&self) -> Option<EloEstimate> {
+        let score = self.score()?;
+        let games = self.games() as f64;
+        let variance = self.variance(score);
+        let std_error = (variance / games).sqrt();
+
+        let difference = score_to_elo(score);
+        let margin = if difference.is_finite() {
+            let upper = score_to_elo((score + Z_95 * std_error).min(1.0));
+            let lower = score_to_elo((score - Z_95 * std_error).max(0.0));
+            (upper - lower) / 2.0
+        } else {
+            f64::INFINITY
+        };
+
+        Some(EloEstimate { difference, margin })
+    }
+
+    /// Per game variance of the score
+    fn variance(&self, score: f64) -> f64 {
+        let games = self.games() as f64;
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games
+    }
+}
+
+/// Formats like `W: 10 D: 5 L: 3`
+impl Display for MatchStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "W: {} D: {} L: {}", self.wins, self.draws, self.losses)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EloEstimate {
+    pub difference: f64,
+    /// Half the width of the 95% confidence interval
+    pub margin: f64,
+}
+
+/// Formats like `45.2 +/- 30.1`
+impl Display for EloEstimate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1} +/- {:.1}", self.difference, self.margin)
+    }
+}
+
+/// The expected score of a player with the given Elo advantage
+pub fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+pub fn score_to_elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Sequential probability ratio test deciding between H0 (the Elo difference is `elo0`)
+/// and H1 (the Elo difference is `elo1`) with the given error probabilities.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// Probability of accepting H1 although H0 is true
+    pub alpha: f64,
+    /// Probability of accepting H0 although H1 is true
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SprtVerdict {
+    /// The Elo difference is more likely `elo0` (or less) than `elo1`
+    AcceptH0,
+    /// The Elo difference is more likely `elo1` (or more) than `elo0`
+    AcceptH1,
+    /// More games are needed
+    Continue,
+}
+
+impl Sprt {
+    /// The bounds (lower, upper) of the log-likelihood ratio
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log-likelihood ratio of the results, approximated with a normal distribution of the score
+    pub fn llr(&self, stats: &MatchStats) -> f64 {
+        let Some(score) = stats.score() else {
+            return 0.0;
+        };
+        let variance = stats.variance(score);
+        if variance <= 0.0 {
+            return 0.0;
+        }
+
+        let s0 = elo_to_score(self.elo0);
+        let s1 = elo_to_score(self.elo1);
+        let games = stats.games() as f64;
+        (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance / games)
+    }
+
+    pub fn verdict(&self, stats: &MatchStats) -> SprtVerdict {
+        let llr = self.llr(stats);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtVerdict::AcceptH1
+        } else if llr <= lower {
+            SprtVerdict::AcceptH0
+        } else {
+            SprtVerdict::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tournament::stats::{MatchStats, Sprt, SprtVerdict, elo_to_score, score_to_elo};
+
+    #[test]
+    fn test_elo_estimate() {
+        let even = MatchStats {
+            wins: 10,
+            draws: 20,
+            losses: 10,
+        };
+        let elo = even.elo().unwrap();
+        assert!(elo.difference.abs() < 1e-9);
+        assert!(elo.margin > 0.0);
+        assert_eq!(MatchStats::default().elo(), None);
+
+        let stronger = MatchStats {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+        };
+        let elo = stronger.elo().unwrap();
+        assert!((elo.difference - score_to_elo(0.7)).abs() < 1e-9);
+        assert!((score_to_elo(elo_to_score(120.0)) - 120.0).abs() < 1e-9);
+        assert!(elo.difference - elo.margin > 0.0);
+    }
+
+    #[test]
+    fn test_sprt_verdict() {
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 1e-3);
+        assert!((lower + 2.944).abs() < 1e-3);
+
+        let few = MatchStats {
+            wins: 3,
+            draws: 4,
+            losses: 2,
+        };
+        assert_eq!(sprt.verdict(&few), SprtVerdict::Continue);
+
+        let clearly_better = MatchStats {
+            wins: 600,
+            draws: 300,
+            losses: 100,
+        };
+        assert_eq!(sprt.verdict(&clearly_better), SprtVerdict::AcceptH1);
+
+        let clearly_worse = MatchStats {
+            wins: 100,
+            draws: 300,
+            losses: 600,
+        };
+        assert_eq!(sprt.verdict(&clearly_worse), SprtVerdict::AcceptH0);
+    }
+}